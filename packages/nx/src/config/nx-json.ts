@@ -640,6 +640,12 @@ export interface NxJsonConfiguration<T = '*' | string[]> {
    */
   cacheDirectory?: string;
 
+  /**
+   * Records the hash of every file matched by file set inputs, so that the files which changed
+   * between two task hashes can be reported. Defaults to `false`.
+   */
+  detailedFileSets?: boolean;
+
   /**
    * Set this to false to disable the daemon.
    */
@@ -665,6 +671,12 @@ export interface NxJsonConfiguration<T = '*' | string[]> {
    */
   maxCacheSize?: string;
 
+  /**
+   * How outputs are restored from the local cache. Defaults to `copy`.
+   * `link` reflinks or hardlinks outputs instead of copying them, which is faster and saves disk space.
+   */
+  cacheRestoreMode?: 'copy' | 'link';
+
   /**
    * Settings for the Nx Terminal User Interface (TUI)
    */
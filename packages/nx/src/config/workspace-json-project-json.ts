@@ -204,8 +204,28 @@ export type InputDefinition =
   | { input: string; dependencies: true }
   | { input: string }
   | { fileset: string }
-  | { runtime: string }
+  | {
+      runtime: string;
+      /**
+       * Milliseconds after which the command is killed and hashing fails.
+       */
+      timeout?: number;
+      /**
+       * Names of the environment variables passed to the command, besides `PATH` and `HOME`.
+       * No other environment variables are passed when this is not set.
+       */
+      env?: string[];
+      /**
+       * Directory to run the command in, relative to the project root.
+       */
+      cwd?: string;
+      /**
+       * Reuses the result in other processes, for commands whose output only depends on their environment.
+       */
+      persist?: boolean;
+    }
   | { externalDependencies: string[] }
+  | { json: string }
   | { dependentTasksOutputFiles: string; transitive?: boolean }
   | { env: string };
 
@@ -33,7 +33,15 @@ import {
   DaemonProjectGraphError,
   ProjectGraphError,
 } from '../../project-graph/error-types';
-import { IS_WASM, NxWorkspaceFiles, TaskRun, TaskTarget } from '../../native';
+import {
+  DuplicateFiles,
+  FilesQuery,
+  FilesQueryResult,
+  IS_WASM,
+  NxWorkspaceFiles,
+  TaskRun,
+  TaskTarget,
+} from '../../native';
 import {
   HandleGlobMessage,
   HandleMultiGlobMessage,
@@ -56,6 +64,12 @@ import {
   HandleHashGlobMessage,
   HandleHashMultiGlobMessage,
 } from '../message-types/hash-glob';
+import {
+  FIND_DUPLICATE_FILES,
+  HandleFindDuplicateFilesMessage,
+  HandleQueryFilesMessage,
+  QUERY_FILES,
+} from '../message-types/query-files';
 import {
   GET_ESTIMATED_TASK_TIMINGS,
   GET_FLAKY_TASKS,
@@ -356,6 +370,22 @@ export class DaemonClient {
     return this.sendToDaemonViaQueue(message);
   }
 
+  queryFiles(query: FilesQuery): Promise<FilesQueryResult> {
+    const message: HandleQueryFilesMessage = {
+      type: QUERY_FILES,
+      query,
+    };
+    return this.sendToDaemonViaQueue(message);
+  }
+
+  findDuplicateFiles(query?: FilesQuery): Promise<DuplicateFiles[]> {
+    const message: HandleFindDuplicateFilesMessage = {
+      type: FIND_DUPLICATE_FILES,
+      query,
+    };
+    return this.sendToDaemonViaQueue(message);
+  }
+
   getWorkspaceContextFileData(): Promise<FileData[]> {
     const message: HandleContextFileDataMessage = {
       type: GET_CONTEXT_FILE_DATA,
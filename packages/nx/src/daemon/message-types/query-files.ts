@@ -0,0 +1,37 @@
+import type { FilesQuery } from '../../native';
+
+export const QUERY_FILES = 'QUERY_FILES' as const;
+
+export type HandleQueryFilesMessage = {
+  type: typeof QUERY_FILES;
+  query: FilesQuery;
+};
+
+export function isHandleQueryFilesMessage(
+  message: unknown
+): message is HandleQueryFilesMessage {
+  return (
+    typeof message === 'object' &&
+    message !== null &&
+    'type' in message &&
+    message['type'] === QUERY_FILES
+  );
+}
+
+export const FIND_DUPLICATE_FILES = 'FIND_DUPLICATE_FILES' as const;
+
+export type HandleFindDuplicateFilesMessage = {
+  type: typeof FIND_DUPLICATE_FILES;
+  query?: FilesQuery;
+};
+
+export function isHandleFindDuplicateFilesMessage(
+  message: unknown
+): message is HandleFindDuplicateFilesMessage {
+  return (
+    typeof message === 'object' &&
+    message !== null &&
+    'type' in message &&
+    message['type'] === FIND_DUPLICATE_FILES
+  );
+}
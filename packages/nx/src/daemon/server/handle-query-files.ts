@@ -0,0 +1,30 @@
+import type { FilesQuery } from '../../native';
+import { workspaceRoot } from '../../utils/workspace-root';
+import {
+  findDuplicateFilesWithWorkspaceContext,
+  queryFilesWithWorkspaceContext,
+} from '../../utils/workspace-context';
+import { HandlerResult } from './server';
+
+export async function handleQueryFiles(
+  query: FilesQuery
+): Promise<HandlerResult> {
+  const result = await queryFilesWithWorkspaceContext(workspaceRoot, query);
+  return {
+    response: JSON.stringify(result),
+    description: 'handleQueryFiles',
+  };
+}
+
+export async function handleFindDuplicateFiles(
+  query?: FilesQuery
+): Promise<HandlerResult> {
+  const duplicates = await findDuplicateFilesWithWorkspaceContext(
+    workspaceRoot,
+    query
+  );
+  return {
+    response: JSON.stringify(duplicates),
+    description: 'handleFindDuplicateFiles',
+  };
+}
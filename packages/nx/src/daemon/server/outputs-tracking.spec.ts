@@ -44,6 +44,22 @@ describe('outputs tracking', () => {
     expect(recordedHash('dist/app/app1')).toBeUndefined();
   });
 
+  it('should invalidate output when a file is renamed out of it', () => {
+    _recordOutputsHash(['dist/app/app1'], '123');
+    processFileChangesInOutputs(
+      [
+        {
+          path: 'dist/app2/main.js',
+          type: EventType.rename,
+          from: 'dist/app/app1/main.js',
+          to: 'dist/app2/main.js',
+        },
+      ],
+      now
+    );
+    expect(recordedHash('dist/app/app1')).toBeUndefined();
+  });
+
   it('should not invalidate anything when no match', () => {
     _recordOutputsHash(['dist/app/app1'], '123');
     processFileChangesInOutputs(
@@ -71,25 +71,26 @@ export function processFileChangesInOutputs(
     now = new Date().getTime();
   }
   for (let e of changeEvents) {
-    let current = e.path;
+    // a rename changes the outputs containing either of its paths
+    for (let current of e.type === 'rename' ? [e.from, e.path] : [e.path]) {
+      // the path is either an output itself or a parent
+      if (dirsContainingOutputs[current]) {
+        dirsContainingOutputs[current].forEach((output) => {
+          if (now - timestamps[output] > 2000) {
+            recordedHashes[output] = undefined;
+          }
+        });
+        continue;
+      }
 
-    // the path is either an output itself or a parent
-    if (dirsContainingOutputs[current]) {
-      dirsContainingOutputs[current].forEach((output) => {
-        if (now - timestamps[output] > 2000) {
-          recordedHashes[output] = undefined;
+      // the path is a child of some output or unrelated
+      while (current != dirname(current)) {
+        if (recordedHashes[current] && now - timestamps[current] > 2000) {
+          recordedHashes[current] = undefined;
+          break;
         }
-      });
-      continue;
-    }
-
-    // the path is a child of some output or unrelated
-    while (current != dirname(current)) {
-      if (recordedHashes[current] && now - timestamps[current] > 2000) {
-        recordedHashes[current] = undefined;
-        break;
+        current = dirname(current);
       }
-      current = dirname(current);
     }
   }
 }
@@ -191,22 +191,22 @@ async function processCollectedUpdatedAndDeletedFiles(
 
       ({ ...fileMapWithFiles } = await retrieveWorkspaceFiles(
         workspaceRoot,
-        projectRootMap
+        projectRootMap,
+        true
       ));
 
       knownExternalNodes = externalNodes;
     } else {
-      if (fileMapWithFiles) {
-        fileMapWithFiles = updateFileMap(
-          projects,
-          fileMapWithFiles.rustReferences,
-          updatedFileHashes,
-          deletedFiles
-        );
+      const updatedFileMap = fileMapWithFiles
+        ? updateFileMap(fileMapWithFiles, updatedFileHashes, deletedFiles)
+        : undefined;
+      if (updatedFileMap) {
+        fileMapWithFiles = updatedFileMap;
       } else {
         fileMapWithFiles = await retrieveWorkspaceFiles(
           workspaceRoot,
-          projectRootMap
+          projectRootMap,
+          true
         );
       }
     }
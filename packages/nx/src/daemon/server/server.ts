@@ -39,18 +39,17 @@ import {
   registerProjectGraphRecomputationListener,
 } from './project-graph-incremental-recomputation';
 import {
-  getOutputWatcherInstance,
   getWatcherInstance,
   handleServerProcessTermination,
   resetInactivityTimeout,
   respondToClient,
   respondWithErrorAndExit,
   SERVER_INACTIVITY_TIMEOUT_MS,
-  storeOutputWatcherInstance,
   storeWatcherInstance,
 } from './shutdown-utils';
 import {
   convertChangeEventsToLogMessage,
+  createWatcher,
   FileWatcherCallback,
   watchOutputFiles,
   watchWorkspace,
@@ -83,6 +82,16 @@ import {
   isHandleHashMultiGlobMessage,
 } from '../message-types/hash-glob';
 import { handleHashGlob, handleHashMultiGlob } from './handle-hash-glob';
+import {
+  FIND_DUPLICATE_FILES,
+  isHandleFindDuplicateFilesMessage,
+  isHandleQueryFilesMessage,
+  QUERY_FILES,
+} from '../message-types/query-files';
+import {
+  handleFindDuplicateFiles,
+  handleQueryFiles,
+} from './handle-query-files';
 import {
   GET_ESTIMATED_TASK_TIMINGS,
   GET_FLAKY_TASKS,
@@ -272,6 +281,14 @@ async function handleMessage(socket, data: string) {
     await handleResult(socket, HASH_GLOB, () =>
       handleHashMultiGlob(payload.globGroups)
     );
+  } else if (isHandleQueryFilesMessage(payload)) {
+    await handleResult(socket, QUERY_FILES, () =>
+      handleQueryFiles(payload.query)
+    );
+  } else if (isHandleFindDuplicateFilesMessage(payload)) {
+    await handleResult(socket, FIND_DUPLICATE_FILES, () =>
+      handleFindDuplicateFiles(payload.query)
+    );
   } else if (isHandleGetFlakyTasksMessage(payload)) {
     await handleResult(socket, GET_FLAKY_TASKS, () =>
       handleGetFlakyTasks(payload.hashes)
@@ -483,6 +500,9 @@ const handleWorkspaceChanges: FileWatcherCallback = async (
       if (event.type === 'delete') {
         deletedFiles.push(event.path);
       } else {
+        if (event.type === 'rename') {
+          deletedFiles.push(event.from);
+        }
         try {
           const s = statSync(join(workspaceRoot, event.path));
           if (s.isFile()) {
@@ -570,21 +590,16 @@ export async function startServer(): Promise<Server> {
           daemonIsOutdated();
 
           if (!getWatcherInstance()) {
-            storeWatcherInstance(
-              await watchWorkspace(server, handleWorkspaceChanges)
-            );
+            const watcher = await createWatcher(server);
+            watchWorkspace(watcher, server, handleWorkspaceChanges);
+            watchOutputFiles(watcher, server, handleOutputsChanges);
+            storeWatcherInstance(watcher);
 
             serverLogger.watcherLog(
               `Subscribed to changes within: ${workspaceRoot} (native)`
             );
           }
 
-          if (!getOutputWatcherInstance()) {
-            storeOutputWatcherInstance(
-              await watchOutputFiles(server, handleOutputsChanges)
-            );
-          }
-
           // listen for project graph recomputation events to collect and schedule sync generators
           registerProjectGraphRecomputationListener(
             collectAndScheduleSyncGenerators
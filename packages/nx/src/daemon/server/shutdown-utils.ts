@@ -23,16 +23,6 @@ export function getWatcherInstance() {
   return watcherInstance;
 }
 
-let outputWatcherInstance: Watcher | undefined;
-
-export function storeOutputWatcherInstance(instance: Watcher) {
-  outputWatcherInstance = instance;
-}
-
-export function getOutputWatcherInstance() {
-  return outputWatcherInstance;
-}
-
 interface HandleServerProcessTerminationParams {
   server: Server;
   reason: string;
@@ -57,16 +47,7 @@ export async function handleServerProcessTermination({
 
     if (watcherInstance) {
       await watcherInstance.stop();
-      serverLogger.watcherLog(
-        `Stopping the watcher for ${workspaceRoot} (sources)`
-      );
-    }
-
-    if (outputWatcherInstance) {
-      await outputWatcherInstance.stop();
-      serverLogger.watcherLog(
-        `Stopping the watcher for ${workspaceRoot} (outputs)`
-      );
+      serverLogger.watcherLog(`Stopping the watcher for ${workspaceRoot}`);
     }
 
     deleteDaemonJsonProcessCache();
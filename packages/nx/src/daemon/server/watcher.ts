@@ -4,63 +4,88 @@ import { handleServerProcessTermination } from './shutdown-utils';
 import { Server } from 'net';
 import { normalizePath } from '../../utils/path';
 import { getDaemonProcessIdSync, serverProcessJsonPath } from '../cache';
-import type { WatchEvent } from '../../native';
+import type { WatchEvent, Watcher, WatchStatus } from '../../native';
 import { openSockets } from './server';
+import { serverLogger } from './logger';
 
 export type FileWatcherCallback = (
   err: Error | string | null,
   changeEvents: WatchEvent[] | null
 ) => Promise<void>;
 
-export async function watchWorkspace(server: Server, cb: FileWatcherCallback) {
+/**
+ * Creates the watcher which the sources and the outputs subscribe to.
+ * It passes on changes to gitignored files, so that outputs are watched
+ * as well, and the sources subscription drops them.
+ */
+export async function createWatcher(server: Server) {
   const { Watcher } = await import('../../native');
 
-  const watcher = new Watcher(workspaceRoot);
-  watcher.watch((err, events) => {
+  const watcher = new Watcher(
+    workspaceRoot,
+    [`!${relativeServerProcess()}`],
+    false
+  );
+  watcher.onStatus((err, status) => {
     if (err) {
-      return cb(err, null);
+      return;
     }
-
-    for (const event of events) {
-      if (event.path.endsWith('.gitignore') || event.path === '.nxignore') {
-        // If the ignore files themselves have changed we need to dynamically update our cached ignoreGlobs
-        handleServerProcessTermination({
-          server,
-          reason:
-            'Stopping the daemon the set of ignored files changed (native)',
-          sockets: openSockets,
-        });
-      }
+    if (status.type === 'rootDeleted') {
+      return handleServerProcessTermination({
+        server,
+        reason: 'Stopping the daemon because the workspace root was deleted',
+        sockets: openSockets,
+      });
     }
-
-    cb(null, events);
+    serverLogger.watcherLog(convertWatchStatusToLogMessage(status));
   });
 
   return watcher;
 }
 
-export async function watchOutputFiles(
+export function watchWorkspace(
+  watcher: Watcher,
   server: Server,
   cb: FileWatcherCallback
 ) {
-  const { Watcher } = await import('../../native');
+  watcher.subscribe(
+    (err, events) => {
+      if (err) {
+        return cb(err, null);
+      }
 
-  const relativeServerProcess = normalizePath(
-    relative(workspaceRoot, serverProcessJsonPath)
-  );
-  const watcher = new Watcher(
-    workspaceRoot,
-    [`!${relativeServerProcess}`],
-    false
+      for (const event of events) {
+        if (event.path.endsWith('.gitignore') || event.path === '.nxignore') {
+          // If the ignore files themselves have changed we need to dynamically update our cached ignoreGlobs
+          handleServerProcessTermination({
+            server,
+            reason:
+              'Stopping the daemon the set of ignored files changed (native)',
+            sockets: openSockets,
+          });
+        }
+      }
+
+      cb(null, events);
+    },
+    { useIgnore: true, exclude: [relativeServerProcess()] }
   );
-  watcher.watch((err, events) => {
+}
+
+export function watchOutputFiles(
+  watcher: Watcher,
+  server: Server,
+  cb: FileWatcherCallback
+) {
+  const serverProcess = relativeServerProcess();
+  watcher.subscribe((err, events) => {
     if (err) {
       return cb(err, null);
     }
 
     for (const event of events) {
       if (
-        event.path == relativeServerProcess &&
+        event.path == serverProcess &&
         getDaemonProcessIdSync() !== process.pid
       ) {
         return handleServerProcessTermination({
@@ -71,11 +96,12 @@ export async function watchOutputFiles(
       }
     }
 
-    if (events.length !== 0) {
-      cb(null, events);
-    }
+    cb(null, events);
   });
-  return watcher;
+}
+
+function relativeServerProcess() {
+  return normalizePath(relative(workspaceRoot, serverProcessJsonPath));
 }
 
 /**
@@ -88,7 +114,7 @@ export function convertChangeEventsToLogMessage(
 ): string {
   // If only a single file was changed, show the information inline
   if (changeEvents.length === 1) {
-    const { path, type } = changeEvents[0];
+    const { path, type, from } = changeEvents[0];
     let typeLog = 'updated';
     switch (type) {
       case 'create':
@@ -100,6 +126,8 @@ export function convertChangeEventsToLogMessage(
       case 'delete':
         typeLog = 'deleted';
         break;
+      case 'rename':
+        return `${from} was renamed to ${path}`;
     }
     return `${path} was ${typeLog}`;
   }
@@ -107,6 +135,7 @@ export function convertChangeEventsToLogMessage(
   let numCreatedOrRestoredFiles = 0;
   let numModifiedFiles = 0;
   let numDeletedFiles = 0;
+  let numRenamedFiles = 0;
   for (const event of changeEvents) {
     switch (event.type) {
       case 'create':
@@ -118,8 +147,26 @@ export function convertChangeEventsToLogMessage(
       case 'delete':
         numDeletedFiles++;
         break;
+      case 'rename':
+        numRenamedFiles++;
+        break;
     }
   }
 
-  return `${numCreatedOrRestoredFiles} file(s) created or restored, ${numModifiedFiles} file(s) modified, ${numDeletedFiles} file(s) deleted`;
+  return `${numCreatedOrRestoredFiles} file(s) created or restored, ${numModifiedFiles} file(s) modified, ${numDeletedFiles} file(s) deleted, ${numRenamedFiles} file(s) renamed`;
+}
+
+function convertWatchStatusToLogMessage(status: WatchStatus) {
+  switch (status.type) {
+    case 'started':
+      return 'Watching started';
+    case 'watchLimitReached':
+      return `The limit of watched directories${
+        status.limit ? ` (${status.limit})` : ''
+      } was reached, some changes will not be picked up`;
+    case 'overflow':
+      return 'File changes were lost, rescanning the workspace';
+    default:
+      return `Watcher status: ${status.type}`;
+  }
 }
@@ -59,11 +59,14 @@ export async function hashTasksThatDoNotDependOnOutputsOfOtherTasks(
   }
   if (tasksDetails?.recordTaskDetails) {
     tasksDetails.recordTaskDetails(
-      tasksToHash.map((task) => ({
+      tasksToHash.map((task, i) => ({
         hash: task.hash,
         project: task.target.project,
         target: task.target.target,
         configuration: task.target.configuration,
+        details: getRecordedHashDetails(task.hashDetails),
+        kinds: hashes[i].kinds,
+        files: hashes[i].files,
       }))
     );
   }
@@ -90,7 +93,7 @@ export async function hashTask(
   const projectsConfigurations =
     readProjectsConfigurationFromProjectGraph(projectGraph);
 
-  const { value, details } = await (customHasher
+  const { value, details, kinds, files } = await (customHasher
     ? customHasher(task, {
         hasher,
         projectGraph,
@@ -111,6 +114,9 @@ export async function hashTask(
         project: task.target.project,
         target: task.target.target,
         configuration: task.target.configuration,
+        details: getRecordedHashDetails(details),
+        kinds,
+        files,
       },
     ]);
   }
@@ -122,3 +128,21 @@ export async function hashTask(
     'hashSingleTask:end'
   );
 }
+
+/**
+ * Records the command and the hash of every input, keyed by instruction.
+ * Custom hashers can return inputs which are not hashes, only the string
+ * values are recorded to explain hash changes later on
+ */
+function getRecordedHashDetails(
+  details: Task['hashDetails'] | undefined
+): Record<string, string> | undefined {
+  if (!details) {
+    return undefined;
+  }
+  return Object.fromEntries(
+    Object.entries({ command: details.command, ...details.nodes }).filter(
+      ([, value]) => typeof value === 'string'
+    )
+  );
+}
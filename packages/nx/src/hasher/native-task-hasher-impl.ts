@@ -6,8 +6,10 @@ import {
   FileData,
   HasherOptions,
   HashPlanner,
+  IS_WASM,
   NxWorkspaceFilesExternals,
   ProjectGraph as NativeProjectGraph,
+  RuntimeInputHashes,
   TaskHasher,
   transferProjectGraph,
 } from '../native';
@@ -15,6 +17,20 @@ import { transformProjectGraphForRust } from '../native/transform-objects';
 import { PartialHash, TaskHasherImpl } from './task-hasher';
 import { readJsonFile } from '../utils/fileutils';
 import { getRootTsConfigPath } from '../plugins/js/utils/typescript';
+import { getDbConnection } from '../utils/db-connection';
+
+let runtimeInputHashes: RuntimeInputHashes;
+
+function getRuntimeInputHashes(): RuntimeInputHashes | null {
+  // TODO: Remove when wasm supports sqlite
+  if (process.env.NX_DISABLE_DB === 'true' || IS_WASM) {
+    return null;
+  }
+  if (!runtimeInputHashes) {
+    runtimeInputHashes = new RuntimeInputHashes(getDbConnection());
+  }
+  return runtimeInputHashes;
+}
 
 export class NativeTaskHasherImpl implements TaskHasherImpl {
   hasher: TaskHasher;
@@ -23,13 +39,14 @@ export class NativeTaskHasherImpl implements TaskHasherImpl {
   allWorkspaceFilesRef: ExternalObject<FileData[]>;
   projectFileMapRef: ExternalObject<Record<string, FileData[]>>;
   options: HasherOptions | undefined;
+  private seededRuntimeHashes = false;
 
   constructor(
     workspaceRoot: string,
     nxJson: NxJsonConfiguration,
     projectGraph: ProjectGraph,
     externals: NxWorkspaceFilesExternals,
-    options: { selectivelyHashTsConfig: boolean }
+    options: { selectivelyHashTsConfig: boolean; detailedFileSets?: boolean }
   ) {
     this.projectGraphRef = transferProjectGraph(
       transformProjectGraphForRust(projectGraph)
@@ -68,7 +85,7 @@ export class NativeTaskHasherImpl implements TaskHasherImpl {
     env: NodeJS.ProcessEnv
   ): Promise<PartialHash> {
     const plans = this.planner.getPlansReference([task.id], taskGraph);
-    const hashes = this.hasher.hashPlans(plans, env);
+    const hashes = this.hashPlans(plans, env);
 
     return hashes[task.id];
   }
@@ -82,7 +99,25 @@ export class NativeTaskHasherImpl implements TaskHasherImpl {
       tasks.map((t) => t.id),
       taskGraph
     );
-    const hashes = this.hasher.hashPlans(plans, env);
+    const hashes = this.hashPlans(plans, env);
     return tasks.map((t) => hashes[t.id]);
   }
+
+  /**
+   * Hashes the plans, reusing and persisting the hashes of runtime inputs
+   * which are configured with `persist`
+   */
+  private hashPlans(
+    plans: Parameters<TaskHasher['hashPlans']>[0],
+    env: NodeJS.ProcessEnv
+  ) {
+    const store = getRuntimeInputHashes();
+    if (store && !this.seededRuntimeHashes) {
+      this.hasher.seedRuntimeHashes(store.getRuntimeHashes());
+      this.seededRuntimeHashes = true;
+    }
+    const hashes = this.hasher.hashPlans(plans, env);
+    store?.recordRuntimeHashes(this.hasher.takeRuntimeHashesToPersist());
+    return hashes;
+  }
 }
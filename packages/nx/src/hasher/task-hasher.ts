@@ -11,7 +11,7 @@ import { InputDefinition } from '../config/workspace-json-project-json';
 import { minimatch } from 'minimatch';
 import { NativeTaskHasherImpl } from './native-task-hasher-impl';
 import { workspaceRoot } from '../utils/workspace-root';
-import { NxWorkspaceFilesExternals } from '../native';
+import { HashInstructionKind, NxWorkspaceFilesExternals } from '../native';
 
 /**
  * A data structure returned by the default hasher.
@@ -21,6 +21,24 @@ export interface PartialHash {
   details: {
     [name: string]: string;
   };
+  /**
+   * Kind of each input in details, like `environment` or `projectFileSet`
+   */
+  kinds?: {
+    [name: string]: HashInstructionKind;
+  };
+  /**
+   * Files matched by each file set, when the hasher records them
+   */
+  files?: {
+    [name: string]: string;
+  };
+  /**
+   * Variables matched by each env pattern, like `VITE_*`
+   */
+  env?: {
+    [name: string]: string[];
+  };
 }
 
 /**
@@ -34,6 +52,18 @@ export interface Hash {
     implicitDeps?: { [fileName: string]: string };
     runtime?: { [input: string]: string };
   };
+  /**
+   * Kind of each input in details.nodes, like `environment` or `projectFileSet`
+   */
+  kinds?: { [name: string]: HashInstructionKind };
+  /**
+   * Files matched by each file set, when the hasher records them
+   */
+  files?: { [name: string]: string };
+  /**
+   * Variables matched by each env pattern, like `VITE_*`
+   */
+  env?: { [name: string]: string[] };
 }
 
 export interface TaskHasher {
@@ -140,6 +170,7 @@ export class InProcessTaskHasher implements TaskHasher {
       this.externalRustReferences,
       {
         selectivelyHashTsConfig: this.options?.selectivelyHashTsConfig ?? false,
+        detailedFileSets: this.options?.detailedFileSets ?? false,
       }
     );
   }
@@ -182,6 +213,9 @@ export class InProcessTaskHasher implements TaskHasher {
         implicitDeps: {},
         runtime: {},
       },
+      kinds: res.kinds,
+      files: res.files,
+      env: res.env,
     };
   }
 
@@ -204,8 +238,15 @@ export class InProcessTaskHasher implements TaskHasher {
 
 export type ExpandedSelfInput =
   | { fileset: string }
-  | { runtime: string }
+  | {
+      runtime: string;
+      timeout?: number;
+      env?: string[];
+      cwd?: string;
+      persist?: boolean;
+    }
   | { env: string }
+  | { json: string }
   | { externalDependencies: string[] };
 export type ExpandedDepsOutput = {
   dependentTasksOutputFiles: string;
@@ -372,6 +413,7 @@ export function expandSingleProjectInputs(
         (d as any).fileset ||
         (d as any).env ||
         (d as any).runtime ||
+        (d as any).json ||
         (d as any).externalDependencies ||
         (d as any).dependentTasksOutputFiles
       ) {
@@ -0,0 +1,449 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use tar::{Archive, Builder, EntryType};
+use tracing::trace;
+use walkdir::WalkDir;
+
+use super::{
+    cache::CachedResult,
+    codec::Codec,
+    errors::HttpRemoteCacheErrors,
+    integrity::{
+        ArtifactManifest, HashingReader, MANIFEST_ENTRY, ManifestEntry, SIGNATURE_ENTRY,
+        SigningKey, validate_entry_path,
+    },
+};
+use crate::native::utils::Normalize;
+
+/// Writes the cached outputs of a task, its terminal output and its exit code as a compressed tarball.
+/// The tarball ends with a manifest of the content hashes of its entries, which is signed when a key is set.
+pub fn write_archive<W: Write>(
+    writer: W,
+    codec: Codec,
+    signing_key: Option<&SigningKey>,
+    cache_directory: &Path,
+    hash: &str,
+    terminal_output: &str,
+    code: u32,
+) -> anyhow::Result<()> {
+    let enc = codec.encoder(writer)?;
+    let mut archive = Builder::new(enc);
+    archive.follow_symlinks(false);
+    let mut manifest = ArtifactManifest::new(hash);
+    trace!("Created tar file for writing");
+
+    trace!("Adding cache artifacts to tarball");
+    let outputs_path = cache_directory.join(hash);
+    for entry in WalkDir::new(&outputs_path)
+        .follow_links(false)
+        .sort_by_file_name()
+    {
+        let entry = entry?;
+        let relative_path = entry.path().strip_prefix(&outputs_path)?;
+        if relative_path.as_os_str().is_empty() {
+            continue;
+        }
+        let name = relative_path.to_normalized_string();
+
+        if entry.file_type().is_file() {
+            // Hash the file while it is added so that it is only read once
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&entry.metadata()?);
+            let mut reader = HashingReader::new(fs::File::open(entry.path())?);
+            archive.append_data(&mut header, &name, &mut reader)?;
+            manifest
+                .entries
+                .insert(name, ManifestEntry::File(reader.finish()));
+        } else {
+            if entry.file_type().is_symlink() {
+                let target = fs::read_link(entry.path())?.to_normalized_string();
+                manifest
+                    .entries
+                    .insert(name.clone(), ManifestEntry::Symlink(target));
+            }
+            archive.append_path_with_name(entry.path(), &name)?;
+        }
+    }
+    trace!("Added cache directory to tarball");
+
+    trace!("Adding terminal output to tarball");
+    append_bytes(&mut archive, "terminalOutput", terminal_output.as_bytes())?;
+    manifest.add_file("terminalOutput", terminal_output.as_bytes());
+    trace!("Added terminal output to tarball");
+
+    trace!("Adding code to tarball");
+    append_bytes(&mut archive, "code", &code.to_be_bytes())?;
+    manifest.add_file("code", &code.to_be_bytes());
+    trace!("Added code to tarball");
+
+    trace!("Adding integrity manifest to tarball");
+    let manifest = manifest.to_bytes()?;
+    append_bytes(&mut archive, MANIFEST_ENTRY, &manifest)?;
+    if let Some(signing_key) = signing_key {
+        let signature = signing_key.sign(&manifest);
+        append_bytes(&mut archive, SIGNATURE_ENTRY, signature.as_bytes())?;
+    }
+    trace!("Added integrity manifest to tarball");
+
+    trace!("Finishing tarball");
+    archive
+        .finish() // Finish the archive to get the inner bytes
+        .map_err(|e| anyhow::anyhow!(format!("Failed to finish tarball: {}", e)))?;
+    archive.into_inner()?.finish()?.flush()?;
+    trace!("Finished tarball");
+
+    Ok(())
+}
+
+fn append_bytes<W: Write>(archive: &mut Builder<W>, name: &str, bytes: &[u8]) -> io::Result<()> {
+    let mut header = tar::Header::new_old();
+    header.set_size(bytes.len() as u64);
+    header.set_cksum(); // Ensure the checksum is set correctly
+    archive.append_data(&mut header, name, bytes)
+}
+
+/// Extracts a tarball created by [`write_archive`] into `<cache_directory>/<hash>`.
+/// Nothing is left behind when the tarball does not match its manifest.
+/// Tarballs stored without a manifest are only accepted when no signing key is set.
+pub fn extract_archive<R: Read>(
+    reader: R,
+    encoding: Option<&str>,
+    signing_key: Option<&SigningKey>,
+    cache_directory: &str,
+    hash: &str,
+) -> anyhow::Result<CachedResult> {
+    let output_dir = Path::new(cache_directory).join(hash);
+    let result = unpack_and_verify(reader, encoding, signing_key, &output_dir, hash);
+    if let Err(e) = &result {
+        trace!("Removing {} after failing to extract it: {}", hash, e);
+        if output_dir.exists() {
+            fs::remove_dir_all(&output_dir)?;
+        }
+    }
+    result
+}
+
+fn unpack_and_verify<R: Read>(
+    reader: R,
+    encoding: Option<&str>,
+    signing_key: Option<&SigningKey>,
+    output_dir: &Path,
+    hash: &str,
+) -> anyhow::Result<CachedResult> {
+    let tar = Codec::decode(encoding, reader)?;
+    let mut archive = Archive::new(tar);
+    let entries = archive
+        .entries() // Get the entries in the archive
+        .map_err(|_| anyhow::anyhow!("Failed to read entries from tarball"))?;
+
+    let mut code: Option<i16> = None;
+    let mut terminal_output: Option<String> = None;
+    let mut size: i64 = 0;
+    let mut manifest: Option<Vec<u8>> = None;
+    let mut signature: Option<String> = None;
+    let mut extracted = ArtifactManifest::new(hash);
+
+    // Ensure the directory exists before extracting
+    fs::create_dir_all(output_dir)?;
+
+    for entry in entries {
+        let mut entry = entry.map_err(|_| anyhow::anyhow!("Failed to read entry from tarball"))?;
+
+        let entry_path = entry.path()?.into_owned();
+        validate_entry_path(&entry_path)?;
+        let name = entry_path.to_normalized_string();
+
+        match name.as_str() {
+            "code" => {
+                let code_file_bytes = read_entry(&mut entry)?;
+                extracted.add_file(name, &code_file_bytes);
+                let code_file_bytes: [u8; 4] = code_file_bytes.try_into().map_err(|_| {
+                    HttpRemoteCacheErrors::IntegrityError("The exit code is malformed".to_string())
+                })?;
+                code = Some(u32::from_be_bytes(code_file_bytes) as i16);
+                trace!("Retrieved exit code from cache: {:?}", code);
+            }
+            "terminalOutput" => {
+                let terminal_output_bytes = read_entry(&mut entry)?;
+                let terminal_output_size = terminal_output_bytes.len();
+                extracted.add_file(name, &terminal_output_bytes);
+
+                terminal_output = Some(String::from_utf8(terminal_output_bytes)?);
+                size += terminal_output_size as i64;
+
+                trace!(
+                    "Retrieved terminal output from cache: {} bytes",
+                    terminal_output_size
+                );
+            }
+            MANIFEST_ENTRY => manifest = Some(read_entry(&mut entry)?),
+            SIGNATURE_ENTRY => signature = Some(String::from_utf8(read_entry(&mut entry)?)?),
+            _ => {
+                let entry_type = entry.header().entry_type();
+                match entry_type {
+                    EntryType::Regular | EntryType::Continuous | EntryType::Directory => {}
+                    EntryType::Symlink => {
+                        let target = entry
+                            .link_name()?
+                            .map(|target| target.to_normalized_string())
+                            .unwrap_or_default();
+                        extracted
+                            .entries
+                            .insert(name.clone(), ManifestEntry::Symlink(target));
+                    }
+                    _ => {
+                        return Err(HttpRemoteCacheErrors::IntegrityError(format!(
+                            "Unexpected {:?} entry {}",
+                            entry_type, name
+                        ))
+                        .into());
+                    }
+                }
+
+                trace!("Extracting entry to {}", output_dir.join(&name).display());
+                // unpack_in also refuses to write through symlinks that point outside of the directory
+                let unpacked = entry
+                    .unpack_in(output_dir)
+                    .map_err(|e| anyhow::anyhow!("Failed to unpack entry: {}", e))?;
+                if !unpacked {
+                    return Err(HttpRemoteCacheErrors::IntegrityError(format!(
+                        "Refusing to extract {} outside of the cache directory",
+                        name
+                    ))
+                    .into());
+                }
+
+                if entry_type.is_file() {
+                    let mut reader = HashingReader::new(fs::File::open(output_dir.join(&name))?);
+                    size += io::copy(&mut reader, &mut io::sink())? as i64;
+                    extracted
+                        .entries
+                        .insert(name, ManifestEntry::File(reader.finish()));
+                }
+            }
+        }
+    }
+
+    match (manifest, signing_key) {
+        (Some(manifest), signing_key) => {
+            if let Some(signing_key) = signing_key {
+                let signature = signature.ok_or_else(|| {
+                    HttpRemoteCacheErrors::IntegrityError("The artifact is not signed".to_string())
+                })?;
+                signing_key.verify(&manifest, &signature)?;
+            }
+            ArtifactManifest::from_bytes(&manifest)?.verify(&extracted)?;
+        }
+        // Artifacts stored before manifests were added can only be trusted without a key
+        (None, None) => trace!("Extracted {} without an integrity manifest", hash),
+        (None, Some(_)) => {
+            return Err(HttpRemoteCacheErrors::IntegrityError(
+                "The artifact does not have a manifest".to_string(),
+            )
+            .into());
+        }
+    }
+
+    trace!("Extracted tarball to {}", output_dir.display());
+
+    Ok(CachedResult {
+        terminal_output,
+        code: code.ok_or_else(|| {
+            HttpRemoteCacheErrors::IntegrityError("The exit code is missing".to_string())
+        })?,
+        outputs_path: output_dir.to_string_lossy().into_owned(),
+        size: Some(size),
+    })
+}
+
+fn read_entry<R: Read>(entry: &mut R) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    entry.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn should_extract_written_archives() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/123/dist/file.txt")
+            .write_str("content")
+            .unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("file.txt", temp.join("cache/123/dist/link.txt")).unwrap();
+
+        let mut tarball = vec![];
+        write_archive(
+            &mut tarball,
+            Codec::Zstd(3),
+            None,
+            &temp.join("cache"),
+            "123",
+            "terminal output",
+            1,
+        )
+        .unwrap();
+
+        let extract_dir = temp.join("extracted");
+        let result = extract_archive(
+            tarball.as_slice(),
+            Some("zstd"),
+            None,
+            &extract_dir.to_string_lossy(),
+            "123",
+        )
+        .unwrap();
+
+        assert_eq!(result.code, 1);
+        assert_eq!(result.terminal_output, Some("terminal output".into()));
+        temp.child("extracted/123/dist/file.txt").assert("content");
+        #[cfg(unix)]
+        temp.child("extracted/123/dist/link.txt").assert("content");
+    }
+    /// Builds a tarball without the path checks of [`Builder`]
+    fn raw_tarball(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut archive = Builder::new(Codec::default().encoder(vec![]).unwrap());
+        for (name, contents) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive.append(&header, *contents).unwrap();
+        }
+        archive.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn extract_error(temp: &TempDir, tarball: &[u8], signing_key: Option<&SigningKey>) -> String {
+        let error = extract_archive(
+            tarball,
+            None,
+            signing_key,
+            &temp.join("extracted").to_string_lossy(),
+            "123",
+        )
+        .unwrap_err();
+        assert!(!temp.join("extracted/123").exists());
+        match error.downcast::<HttpRemoteCacheErrors>() {
+            Ok(HttpRemoteCacheErrors::IntegrityError(message)) => message,
+            other => panic!("Expected an integrity error, got {:?}", other),
+        }
+    }
+
+    fn signed_manifest(hash: &str, entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut manifest = ArtifactManifest::new(hash);
+        for (name, contents) in entries {
+            manifest.add_file(*name, contents);
+        }
+        manifest.to_bytes().unwrap()
+    }
+
+    #[test]
+    fn should_reject_entries_outside_of_the_cache_directory() {
+        let temp = TempDir::new().unwrap();
+        let tarball = raw_tarball(&[("../poisoned.txt", b"poisoned")]);
+
+        let message = extract_error(&temp, &tarball, None);
+
+        assert!(message.contains("outside of the cache directory"));
+        assert!(!temp.join("poisoned.txt").exists());
+    }
+
+    #[test]
+    fn should_reject_entries_that_do_not_match_the_manifest() {
+        let temp = TempDir::new().unwrap();
+        let entries: [(&str, &[u8]); 3] = [
+            ("dist/file.txt", b"content"),
+            ("terminalOutput", b""),
+            ("code", &[0, 0, 0, 0]),
+        ];
+        let manifest = signed_manifest("123", &entries);
+
+        let mut tampered = entries.to_vec();
+        tampered[0].1 = b"poisoned";
+        tampered.push((MANIFEST_ENTRY, &manifest));
+        let message = extract_error(&temp, &raw_tarball(&tampered), None);
+        assert_eq!(
+            message,
+            "dist/file.txt does not match the artifact manifest"
+        );
+
+        let mut replayed = entries.to_vec();
+        let other_manifest = signed_manifest("456", &entries);
+        replayed.push((MANIFEST_ENTRY, &other_manifest));
+        let message = extract_error(&temp, &raw_tarball(&replayed), None);
+        assert_eq!(message, "The artifact was stored for 456 instead of 123");
+
+        let message = extract_error(
+            &temp,
+            &raw_tarball(&entries),
+            Some(&SigningKey::new("secret")),
+        );
+        assert_eq!(message, "The artifact does not have a manifest");
+    }
+
+    #[test]
+    fn should_extract_archives_stored_without_a_manifest() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/123/dist/file.txt")
+            .write_str("content")
+            .unwrap();
+
+        // The gzip tarballs written before artifacts had a manifest
+        let encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        let mut archive = Builder::new(encoder);
+        archive.append_dir_all("", temp.join("cache/123")).unwrap();
+        append_bytes(&mut archive, "terminalOutput", b"terminal output").unwrap();
+        append_bytes(&mut archive, "code", &0u32.to_be_bytes()).unwrap();
+        let tarball = archive.into_inner().unwrap().finish().unwrap();
+
+        let extracted = temp.join("extracted").to_string_lossy().into_owned();
+        let result = extract_archive(tarball.as_slice(), None, None, &extracted, "123").unwrap();
+
+        assert_eq!(result.code, 0);
+        assert_eq!(result.terminal_output, Some("terminal output".into()));
+        temp.child("extracted/123/dist/file.txt").assert("content");
+    }
+
+    #[test]
+    fn should_verify_signed_archives() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/123/dist/file.txt")
+            .write_str("content")
+            .unwrap();
+        let key = SigningKey::new("secret");
+        let write = |signing_key: Option<&SigningKey>| {
+            let mut tarball = vec![];
+            write_archive(
+                &mut tarball,
+                Codec::default(),
+                signing_key,
+                &temp.join("cache"),
+                "123",
+                "",
+                0,
+            )
+            .unwrap();
+            tarball
+        };
+
+        let signed = write(Some(&key));
+        let extracted = temp.join("extracted").to_string_lossy().into_owned();
+        assert!(extract_archive(signed.as_slice(), None, Some(&key), &extracted, "123").is_ok());
+
+        let message = extract_error(&temp, &signed, Some(&SigningKey::new("other")));
+        assert_eq!(message, "The artifact manifest signature is invalid");
+
+        let message = extract_error(&temp, &write(None), Some(&key));
+        assert_eq!(message, "The artifact is not signed");
+    }
+}
@@ -5,13 +5,20 @@ use std::time::Instant;
 use fs_extra::remove_items;
 use napi::bindgen_prelude::*;
 use regex::Regex;
-use rusqlite::params;
+use rusqlite::{Connection, OptionalExtension, params};
 use sysinfo::Disks;
 use tracing::trace;
 
+use crate::native::cache::content_store::{ContentStore, StoredFile};
+use crate::native::cache::eviction::{
+    BlobReference, DEFAULT_TTL_SECONDS, EVICTION_BATCH_SIZE, EvictionOptions, EvictionPlan,
+    EvictionReport,
+};
 use crate::native::cache::expand_outputs::_expand_outputs;
-use crate::native::cache::file_ops::_copy;
+use crate::native::cache::file_ops::{_restore, CacheRestoreMode, break_links};
+use crate::native::cache::query::{self, CacheEntry, CacheEntryFilter, CacheStats};
 use crate::native::db::connection::NxDbConnection;
+use crate::native::tasks::details::{create_task_details_tables, delete_hash_details};
 use crate::native::utils::Normalize;
 
 #[napi(object)]
@@ -29,8 +36,11 @@ pub struct NxCache {
     workspace_root: PathBuf,
     cache_path: PathBuf,
     db: External<NxDbConnection>,
+    content_store: ContentStore,
     link_task_details: bool,
     max_cache_size: i64,
+    restore_mode: CacheRestoreMode,
+    eviction: EvictionOptions,
 }
 
 #[napi]
@@ -42,7 +52,17 @@ impl NxCache {
         db_connection: External<NxDbConnection>,
         link_task_details: Option<bool>,
         max_cache_size: Option<i64>,
+        restore_mode: Option<CacheRestoreMode>,
+        eviction: Option<EvictionOptions>,
     ) -> anyhow::Result<Self> {
+        let eviction = eviction.unwrap_or_default();
+        if let Some(ttl_seconds) = eviction.ttl_seconds.filter(|ttl| *ttl < 0) {
+            anyhow::bail!(
+                "The TTL of cache entries cannot be negative, got {} seconds",
+                ttl_seconds
+            );
+        }
+
         let cache_path = PathBuf::from(&cache_path);
 
         create_dir_all(&cache_path)?;
@@ -52,11 +72,14 @@ impl NxCache {
 
         let r = Self {
             db: db_connection,
+            content_store: ContentStore::new(&cache_path)?,
             workspace_root: PathBuf::from(workspace_root),
             cache_directory: cache_path.to_normalized_string(),
             cache_path,
             link_task_details: link_task_details.unwrap_or(true),
             max_cache_size,
+            restore_mode: restore_mode.unwrap_or_default(),
+            eviction,
         };
 
         r.setup()?;
@@ -65,11 +88,16 @@ impl NxCache {
     }
 
     fn setup(&self) -> anyhow::Result<()> {
+        if self.link_task_details {
+            create_task_details_tables(&self.db)?;
+        }
         let query = if self.link_task_details {
             "CREATE TABLE IF NOT EXISTS cache_outputs (
                     hash    TEXT PRIMARY KEY NOT NULL,
                     code   INTEGER NOT NULL,
                     size   INTEGER NOT NULL,
+                    terminal_output_size INTEGER NOT NULL DEFAULT 0,
+                    hit_count INTEGER NOT NULL DEFAULT 0,
                     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
                     accessed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
                     FOREIGN KEY (hash) REFERENCES task_details (hash)
@@ -80,6 +108,8 @@ impl NxCache {
                     hash    TEXT PRIMARY KEY NOT NULL,
                     code   INTEGER NOT NULL,
                     size   INTEGER NOT NULL,
+                    terminal_output_size INTEGER NOT NULL DEFAULT 0,
+                    hit_count INTEGER NOT NULL DEFAULT 0,
                     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
                     accessed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
                 );
@@ -87,6 +117,25 @@ impl NxCache {
         };
 
         self.db.execute(query, []).map_err(anyhow::Error::from)?;
+        self.db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS cache_blobs (
+                    digest TEXT PRIMARY KEY NOT NULL,
+                    size INTEGER NOT NULL,
+                    ref_count INTEGER NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS cache_manifests (
+                    hash TEXT NOT NULL,
+                    path TEXT NOT NULL,
+                    digest TEXT NOT NULL,
+                    PRIMARY KEY (hash, path),
+                    FOREIGN KEY (digest) REFERENCES cache_blobs (digest)
+                );
+                CREATE INDEX IF NOT EXISTS cache_manifests_digest_idx ON cache_manifests (digest);
+                CREATE TABLE IF NOT EXISTS cache_pins (
+                    hash TEXT PRIMARY KEY NOT NULL
+                );
+                ",
+        )?;
         Ok(())
     }
 
@@ -102,7 +151,7 @@ impl NxCache {
             .db
             .query_row(
                 "UPDATE cache_outputs
-                    SET accessed_at = CURRENT_TIMESTAMP
+                    SET accessed_at = CURRENT_TIMESTAMP, hit_count = hit_count + 1
                     WHERE hash = ?1
                     RETURNING code, size",
                 params![hash],
@@ -124,10 +173,32 @@ impl NxCache {
                 },
             )
             .map_err(|e| anyhow::anyhow!("Unable to get {}: {:?}", &hash, e))?;
+
+        if r.is_some() && !task_dir.exists() {
+            trace!("Restoring task directory {:?} from blobs", &task_dir);
+            let files = self.get_manifest(&hash)?;
+            self.content_store.materialize(&task_dir, &files)?;
+        }
+
         trace!("GET {} {:?}", &hash, start.elapsed());
         Ok(r)
     }
 
+    /// Returns the hashes which are not in the cache. Unlike `get`, this does not count as accessing the entries.
+    #[napi]
+    pub fn missing_hashes(&self, hashes: Vec<String>) -> anyhow::Result<Vec<String>> {
+        let mut exists = self
+            .db
+            .prepare("SELECT EXISTS(SELECT 1 FROM cache_outputs WHERE hash = ?1)")?;
+        let mut missing = vec![];
+        for hash in hashes {
+            if !exists.query_row(params![hash], |row| row.get::<_, bool>(0))? {
+                missing.push(hash);
+            }
+        }
+        Ok(missing)
+    }
+
     #[napi]
     pub fn put(
         &mut self,
@@ -150,29 +221,32 @@ impl NxCache {
         // Write the terminal outputs into a file
         let task_outputs_path = self.get_task_outputs_path_internal(&hash);
         trace!("Writing terminal outputs to: {:?}", &task_outputs_path);
-        let mut total_size: i64 = terminal_output.len() as i64;
+        let terminal_output_size = terminal_output.len() as i64;
         write(task_outputs_path, terminal_output)?;
 
         // Expand the outputs
         let expanded_outputs = _expand_outputs(&self.workspace_root, outputs)?;
 
-        // Copy the outputs to the cache
+        // Store the outputs in the content store and link them into the task directory
+        let mut files = vec![];
         for expanded_output in expanded_outputs.iter() {
             let p = self.workspace_root.join(expanded_output);
             if p.exists() {
-                let cached_outputs_dir = task_dir.join(expanded_output);
-                trace!("Copying {:?} -> {:?}", &p, &cached_outputs_dir);
-                total_size += _copy(p, cached_outputs_dir)?;
+                trace!("Storing {:?} -> {:?}", &p, &task_dir.join(expanded_output));
+                files.extend(
+                    self.content_store
+                        .store_output(&p, &task_dir, expanded_output)?,
+                );
             }
         }
 
-        self.record_to_cache(hash, code, total_size)?;
+        self.record_to_cache(hash, code, terminal_output_size, files)?;
         Ok(())
     }
 
     #[napi]
     pub fn apply_remote_cache_results(
-        &self,
+        &mut self,
         hash: String,
         result: CachedResult,
         outputs: Option<Vec<String>>,
@@ -182,17 +256,22 @@ impl NxCache {
             &hash, &result.outputs_path
         );
         let terminal_output = result.terminal_output.clone().unwrap_or(String::from(""));
-        let mut size = terminal_output.len() as i64;
+        let terminal_output_size = terminal_output.len() as i64;
+
+        // Move the extracted artifacts into the content store before restoring them
+        let files = self
+            .content_store
+            .adopt_dir(Path::new(&result.outputs_path))?;
+
         if let Some(outputs) = outputs {
             if outputs.len() > 0 && result.code == 0 {
-                size +=
-                    try_and_retry(|| self.copy_files_from_cache(result.clone(), outputs.clone()))?;
+                try_and_retry(|| self.copy_files_from_cache(result.clone(), outputs.clone()))?;
             };
         }
         write(self.get_task_outputs_path(hash.clone()), terminal_output)?;
 
         let code: i16 = result.code;
-        self.record_to_cache(hash, code, size)?;
+        self.record_to_cache(hash, code, terminal_output_size, files)?;
         Ok(())
     }
 
@@ -206,74 +285,313 @@ impl NxCache {
             .to_normalized_string()
     }
 
-    fn record_to_cache(&self, hash: String, code: i16, size: i64) -> anyhow::Result<()> {
+    fn record_to_cache(
+        &mut self,
+        hash: String,
+        code: i16,
+        terminal_output_size: i64,
+        files: Vec<StoredFile>,
+    ) -> anyhow::Result<()> {
+        let size = terminal_output_size + files.iter().map(|f| f.size).sum::<i64>();
         trace!("Recording to cache: {}, {}, {}", &hash, code, size);
-        self.db.execute(
-            "INSERT OR REPLACE INTO cache_outputs (hash, code, size) VALUES (?1, ?2, ?3)",
-            params![hash, code, size],
-        )?;
+        let unreferenced_blobs = self.db.transaction(|conn| {
+            // Release the blobs of a previous entry with the same hash
+            let mut unreferenced_blobs = release_manifest(conn, &hash)?;
+
+            conn.execute(
+                "INSERT OR REPLACE INTO cache_outputs (hash, code, size, terminal_output_size) VALUES (?1, ?2, ?3, ?4)",
+                params![hash, code, size, terminal_output_size],
+            )?;
+
+            let mut insert_blob = conn.prepare(
+                "INSERT INTO cache_blobs (digest, size, ref_count) VALUES (?1, ?2, 1)
+                    ON CONFLICT (digest) DO UPDATE SET ref_count = ref_count + 1",
+            )?;
+            let mut insert_manifest = conn.prepare(
+                "INSERT OR REPLACE INTO cache_manifests (hash, path, digest) VALUES (?1, ?2, ?3)",
+            )?;
+            for file in files.iter() {
+                insert_blob.execute(params![file.digest, file.size])?;
+                insert_manifest.execute(params![hash, file.path, file.digest])?;
+            }
+
+            // Blobs released above may be referenced again by the new entry
+            unreferenced_blobs.retain(|(digest, _)| !files.iter().any(|f| &f.digest == digest));
+            Ok(unreferenced_blobs)
+        })?;
+
+        for (digest, _) in unreferenced_blobs {
+            self.content_store.remove(&digest)?;
+        }
+
         if self.max_cache_size != 0 {
             self.ensure_cache_size_within_limit()?
         }
         Ok(())
     }
 
+    fn get_manifest(&self, hash: &str) -> anyhow::Result<Vec<StoredFile>> {
+        self.db
+            .prepare(
+                "SELECT cache_manifests.path, cache_manifests.digest, cache_blobs.size
+                    FROM cache_manifests
+                        JOIN cache_blobs ON cache_manifests.digest = cache_blobs.digest
+                    WHERE cache_manifests.hash = ?1",
+            )?
+            .query_map(params![hash], |row| {
+                Ok(StoredFile {
+                    path: row.get(0)?,
+                    digest: row.get(1)?,
+                    size: row.get(2)?,
+                })
+            })?
+            .map(|r| r.map_err(anyhow::Error::from))
+            .collect()
+    }
+
+    /// Removes a cache entry and the blobs that are no longer referenced by any other entry.
+    /// Returns the number of bytes freed on disk.
+    fn remove_cache_entry(&mut self, hash: &str) -> anyhow::Result<i64> {
+        let link_task_details = self.link_task_details;
+        let (terminal_output_size, unreferenced_blobs) = self.db.transaction(|conn| {
+            let unreferenced_blobs = release_manifest(conn, hash)?;
+            let terminal_output_size = conn
+                .query_row(
+                    "DELETE FROM cache_outputs WHERE hash = ?1 RETURNING terminal_output_size",
+                    params![hash],
+                    |row| row.get::<_, i64>(0),
+                )
+                .optional()?
+                .unwrap_or(0);
+            if link_task_details {
+                delete_hash_details(conn, hash)?;
+            }
+            Ok((terminal_output_size, unreferenced_blobs))
+        })?;
+
+        remove_items(&[
+            self.cache_path.join(hash),
+            self.get_task_outputs_path_internal(hash),
+        ])?;
+
+        let mut freed = terminal_output_size;
+        for (digest, size) in unreferenced_blobs {
+            self.content_store.remove(&digest)?;
+            freed += size;
+        }
+        Ok(freed)
+    }
+
+    /// Returns the number of bytes the cache takes up on disk.
+    /// Outputs which are shared between entries are only counted once.
     #[napi]
     pub fn get_cache_size(&self) -> anyhow::Result<i64> {
         self.db
-            .query_row("SELECT SUM(size) FROM cache_outputs", [], |row| {
-                row.get::<_, Option<i64>>(0)
-                    // If there are no cache entries, the result is
-                    // a single row with a NULL value. This would look like:
-                    // Ok(None). We need to convert this to Ok(0).
-                    .transpose()
-                    .unwrap_or(Ok(0))
-            })
+            .query_row(
+                "SELECT
+                    (SELECT COALESCE(SUM(size), 0) FROM cache_blobs) +
+                    (SELECT COALESCE(SUM(terminal_output_size), 0) FROM cache_outputs)",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
             // The query_row returns an Result<Option<T>> to account for
             // a query that returned no rows. This isn't possible when using
-            // SUM, so we can safely unwrap the Option, but need to transpose
-            // to access it. The result represents a db error or mapping error.
-            .transpose()
-            .unwrap_or(Ok(0))
+            // SUM, so we can safely default the Option.
+            .map(|size| size.unwrap_or(0))
     }
 
-    fn ensure_cache_size_within_limit(&self) -> anyhow::Result<()> {
-        // 0 is equivalent to being unlimited.
-        if self.max_cache_size == 0 {
+    /// Lists the entries in the cache along with the project, target and configuration of their tasks
+    #[napi]
+    pub fn list_entries(
+        &self,
+        filter: Option<CacheEntryFilter>,
+    ) -> anyhow::Result<Vec<CacheEntry>> {
+        query::list_entries(&self.db, filter.unwrap_or_default())
+    }
+
+    /// Sums up the entries in the cache per project, largest first
+    #[napi]
+    pub fn stats_by_project(&self) -> anyhow::Result<Vec<CacheStats>> {
+        query::stats(&self.db, false)
+    }
+
+    /// Sums up the entries in the cache per project and target, largest first
+    #[napi]
+    pub fn stats_by_target(&self) -> anyhow::Result<Vec<CacheStats>> {
+        query::stats(&self.db, true)
+    }
+
+    fn ensure_cache_size_within_limit(&mut self) -> anyhow::Result<()> {
+        self.evict_entries(false, true, false)?;
+        Ok(())
+    }
+
+    /// Gives outputs which were restored as hardlinks into the cache copies of their own,
+    /// so that a task which writes its outputs in place cannot change the cache
+    #[napi]
+    pub fn break_output_links(&self, outputs: Vec<String>) -> anyhow::Result<()> {
+        if self.restore_mode != CacheRestoreMode::link {
             return Ok(());
         }
-        let user_specified_max_cache_size = self.max_cache_size;
-        let buffer_amount = (0.1 * user_specified_max_cache_size as f64) as i64;
-        let target_cache_size = user_specified_max_cache_size - buffer_amount;
-
-        let full_cache_size = self.get_cache_size()?;
-        if user_specified_max_cache_size < full_cache_size {
-            let mut cache_size = full_cache_size;
-            let mut stmt = self.db.prepare(
-                "SELECT hash, size FROM cache_outputs ORDER BY accessed_at ASC LIMIT 100",
+        for output in _expand_outputs(&self.workspace_root, outputs)? {
+            break_links(&self.workspace_root.join(output), &self.content_store)?;
+        }
+        Ok(())
+    }
+
+    /// Evicts the entries which have not been accessed within the TTL and, when the cache is over its
+    /// maximum size, the entries picked by the eviction policy until it is back under the limit.
+    /// Pinned entries are never evicted.
+    /// With `dry_run`, nothing is removed and the report lists what would have been evicted.
+    #[napi]
+    pub fn evict(&mut self, dry_run: Option<bool>) -> anyhow::Result<EvictionReport> {
+        self.evict_entries(true, true, dry_run.unwrap_or(false))
+    }
+
+    /// Prevents the entries for `hashes` from being evicted, including ones which are not cached yet
+    #[napi]
+    pub fn pin(&mut self, hashes: Vec<String>) -> anyhow::Result<()> {
+        self.db.transaction(|conn| {
+            let mut insert = conn.prepare("INSERT OR IGNORE INTO cache_pins (hash) VALUES (?1)")?;
+            for hash in hashes.iter() {
+                insert.execute(params![hash])?;
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    #[napi]
+    pub fn unpin(&mut self, hashes: Vec<String>) -> anyhow::Result<()> {
+        self.db.transaction(|conn| {
+            let mut delete = conn.prepare("DELETE FROM cache_pins WHERE hash = ?1")?;
+            for hash in hashes.iter() {
+                delete.execute(params![hash])?;
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    #[napi]
+    pub fn get_pinned_hashes(&self) -> anyhow::Result<Vec<String>> {
+        self.db
+            .prepare("SELECT hash FROM cache_pins ORDER BY hash")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|r| r.map_err(anyhow::Error::from))
+            .collect()
+    }
+
+    fn evict_entries(
+        &mut self,
+        evict_expired: bool,
+        enforce_size_limit: bool,
+        dry_run: bool,
+    ) -> anyhow::Result<EvictionReport> {
+        let mut plan = EvictionPlan::default();
+
+        if evict_expired {
+            let ttl_seconds = self.eviction.ttl_seconds.unwrap_or(DEFAULT_TTL_SECONDS);
+            self.plan_evictions(
+                &mut plan,
+                &format!(
+                    "AND accessed_at < datetime('now', '-{} seconds')",
+                    ttl_seconds
+                ),
+                "accessed_at ASC",
+                |_| false,
             )?;
-            'outer: while cache_size > target_cache_size {
-                let rows = stmt.query_map([], |r| {
-                    let hash: String = r.get(0)?;
-                    let size: i64 = r.get(1)?;
-                    Ok((hash, size))
+        }
+
+        // 0 is equivalent to being unlimited.
+        if enforce_size_limit && self.max_cache_size != 0 {
+            let user_specified_max_cache_size = self.max_cache_size;
+            let buffer_amount = (0.1 * user_specified_max_cache_size as f64) as i64;
+            let target_cache_size = user_specified_max_cache_size - buffer_amount;
+
+            let full_cache_size = self.get_cache_size()?;
+            if user_specified_max_cache_size < full_cache_size - plan.freed_bytes() {
+                let policy = self.eviction.policy.unwrap_or_default();
+                // Only blobs which are not shared with other entries free up space.
+                // Stop once enough cache entries are planned to be deleted to be under the target cache size.
+                self.plan_evictions(&mut plan, "", policy.order_by(), |plan| {
+                    full_cache_size - plan.freed_bytes() < target_cache_size
                 })?;
-                for row in rows {
-                    if let Ok((hash, size)) = row {
-                        cache_size -= size;
-                        self.db
-                            .execute("DELETE FROM cache_outputs WHERE hash = ?1", params![hash])?;
-                        remove_items(&[self.cache_path.join(&hash)])?;
-                    }
-                    // We've deleted enough cache entries to be under the
-                    // target cache size, stop looking for more.
-                    if cache_size < target_cache_size {
-                        break 'outer;
-                    }
+            }
+        }
+
+        if !dry_run {
+            for hash in plan.hashes() {
+                self.remove_cache_entry(hash)?;
+            }
+        }
+        trace!(
+            "Evicted {} entries ({} bytes), dry run: {}",
+            plan.hashes().len(),
+            plan.freed_bytes(),
+            dry_run
+        );
+        Ok(plan.into_report())
+    }
+
+    /// Plans to evict the entries which are not pinned and match the `condition`, in `order`, until `is_done`.
+    /// Candidates are read a batch at a time, together with the blobs they reference.
+    fn plan_evictions(
+        &self,
+        plan: &mut EvictionPlan,
+        condition: &str,
+        order: &str,
+        is_done: impl Fn(&EvictionPlan) -> bool,
+    ) -> anyhow::Result<()> {
+        let mut candidates = self.db.prepare(&format!(
+            "WITH candidates AS (
+                SELECT hash, terminal_output_size, ROW_NUMBER() OVER (ORDER BY {order}) AS position
+                    FROM cache_outputs
+                    WHERE hash NOT IN (SELECT hash FROM cache_pins) {condition}
+                    ORDER BY position
+                    LIMIT ?1 OFFSET ?2
+            )
+            SELECT candidates.hash, candidates.terminal_output_size,
+                    cache_blobs.digest, cache_blobs.size, cache_blobs.ref_count, COUNT(cache_blobs.digest)
+                FROM candidates
+                    LEFT JOIN cache_manifests ON cache_manifests.hash = candidates.hash
+                    LEFT JOIN cache_blobs ON cache_blobs.digest = cache_manifests.digest
+                GROUP BY candidates.position, cache_blobs.digest
+                ORDER BY candidates.position"
+        ))?;
+
+        let mut offset = 0;
+        loop {
+            let mut entries: Vec<(String, i64, Vec<BlobReference>)> = vec![];
+            let mut rows = candidates.query(params![EVICTION_BATCH_SIZE, offset])?;
+            while let Some(row) = rows.next()? {
+                let hash: String = row.get(0)?;
+                if entries.last().is_none_or(|(last, ..)| *last != hash) {
+                    entries.push((hash, row.get(1)?, vec![]));
+                }
+                if let Some(digest) = row.get::<_, Option<String>>(2)? {
+                    let (_, _, blobs) = entries.last_mut().expect("the entry was just added");
+                    blobs.push(BlobReference {
+                        digest,
+                        size: row.get(3)?,
+                        ref_count: row.get(4)?,
+                        references: row.get(5)?,
+                    });
+                }
+            }
+
+            let batch_size = entries.len() as i64;
+            for (hash, terminal_output_size, blobs) in entries {
+                if is_done(plan) {
+                    return Ok(());
                 }
+                plan.add(hash, terminal_output_size, blobs);
+            }
+            if batch_size < EVICTION_BATCH_SIZE {
+                return Ok(());
             }
+            offset += EVICTION_BATCH_SIZE;
         }
-        Ok(())
     }
 
     #[napi]
@@ -296,10 +614,10 @@ impl NxCache {
         )?;
 
         trace!(
-            "Copying Files from Cache {:?} -> {:?}",
-            &outputs_path, &self.workspace_root
+            "Restoring Files from Cache ({:?}) {:?} -> {:?}",
+            &self.restore_mode, &outputs_path, &self.workspace_root
         );
-        let sz = _copy(outputs_path, &self.workspace_root);
+        let sz = _restore(outputs_path, &self.workspace_root, self.restore_mode);
 
         match sz {
             Err(e) => {
@@ -321,27 +639,11 @@ impl NxCache {
         }
     }
 
+    /// Evicts the entries which have not been accessed within the TTL
     #[napi]
-    pub fn remove_old_cache_records(&self) -> anyhow::Result<()> {
-        let outdated_cache = self
-            .db
-            .prepare(
-                "DELETE FROM cache_outputs WHERE accessed_at < datetime('now', '-7 days') RETURNING hash",
-            )?
-            .query_map(params![], |row| {
-                let hash: String = row.get(0)?;
-
-                Ok(vec![
-                    self.cache_path.join(&hash),
-                    self.get_task_outputs_path_internal(&hash),
-                ])
-            })?
-            .filter_map(anyhow::Result::ok)
-            .flatten()
-            .collect::<Vec<_>>();
-
-        remove_items(&outdated_cache)?;
-
+    pub fn remove_old_cache_records(&mut self) -> anyhow::Result<()> {
+        // The size limit is enforced whenever an entry is recorded
+        self.evict_entries(true, false, false)?;
         Ok(())
     }
 
@@ -397,6 +699,24 @@ fn get_default_max_cache_size(cache_path: String) -> i64 {
     100 * 1024 * 1024 * 1024
 }
 
+/// Drops the manifest of a cache entry and decrements the reference counts of its blobs.
+/// Returns the digests and sizes of blobs which are no longer referenced by any entry.
+fn release_manifest(conn: &Connection, hash: &str) -> rusqlite::Result<Vec<(String, i64)>> {
+    conn.execute(
+        "UPDATE cache_blobs
+            SET ref_count = ref_count - (
+                SELECT COUNT(*) FROM cache_manifests
+                    WHERE cache_manifests.hash = ?1 AND cache_manifests.digest = cache_blobs.digest
+            )
+            WHERE digest IN (SELECT digest FROM cache_manifests WHERE hash = ?1)",
+        params![hash],
+    )?;
+    conn.execute("DELETE FROM cache_manifests WHERE hash = ?1", params![hash])?;
+    conn.prepare("DELETE FROM cache_blobs WHERE ref_count <= 0 RETURNING digest, size")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect()
+}
+
 fn try_and_retry<T, F>(mut f: F) -> anyhow::Result<T>
 where
     F: FnMut() -> anyhow::Result<T>,
@@ -432,3 +752,143 @@ fn underlying_io_error_kind(error: &anyhow::Error) -> Option<std::io::ErrorKind>
     }
     None
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::native::tasks::details::{HashedTask, TaskDetails};
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+    use std::collections::HashMap;
+
+    fn cache(temp: &TempDir, max_cache_size: i64) -> NxCache {
+        let connection = Connection::open(temp.join("cache.db")).unwrap();
+        NxCache::new(
+            temp.to_string_lossy().into(),
+            temp.join("cache").to_string_lossy().into(),
+            External::new(NxDbConnection::new(connection)),
+            Some(false),
+            Some(max_cache_size),
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_plan_evictions_across_batches() {
+        let temp = TempDir::new().unwrap();
+        let mut unlimited = cache(&temp, 0);
+        temp.child("dist/shared.txt").write_str("shared").unwrap();
+        for i in 0..250 {
+            temp.child("dist/output.txt")
+                .write_str(&format!("{i:010}"))
+                .unwrap();
+            unlimited
+                .put(format!("{i:03}"), String::new(), vec!["dist".into()], 0)
+                .unwrap();
+        }
+        // Entries with higher hashes were accessed longer ago
+        unlimited
+            .db
+            .execute(
+                "UPDATE cache_outputs SET accessed_at = datetime('now', '-' || CAST(hash AS INTEGER) || ' days')",
+                [],
+            )
+            .unwrap();
+
+        // Entries which were not accessed for 100 days expire
+        let expired = NxCache {
+            eviction: EvictionOptions {
+                ttl_seconds: Some(100 * 24 * 60 * 60),
+                ..Default::default()
+            },
+            ..cache(&temp, 0)
+        }
+        .evict(Some(true))
+        .unwrap();
+        assert_eq!(expired.hashes.len(), 149);
+        assert_eq!(expired.hashes.first().unwrap(), "249");
+        assert_eq!(expired.hashes.last().unwrap(), "101");
+        assert_eq!(expired.freed_bytes, 149 * 10);
+
+        // The least recently used entries are evicted until the cache is under 90% of its maximum size
+        let mut limited = cache(&temp, 1000);
+        limited.eviction.ttl_seconds = Some(1000 * 24 * 60 * 60);
+        let evicted = limited.evict(Some(true)).unwrap();
+        assert_eq!(evicted.hashes.len(), 161);
+        assert_eq!(evicted.hashes.first().unwrap(), "249");
+        assert_eq!(evicted.hashes.last().unwrap(), "089");
+        assert_eq!(evicted.freed_bytes, 161 * 10);
+    }
+
+    #[test]
+    fn should_reject_negative_ttls() {
+        let temp = TempDir::new().unwrap();
+        let cache = NxCache::new(
+            temp.to_string_lossy().into(),
+            temp.join("cache").to_string_lossy().into(),
+            External::new(NxDbConnection::new(
+                Connection::open(temp.join("cache.db")).unwrap(),
+            )),
+            Some(false),
+            None,
+            None,
+            Some(EvictionOptions {
+                ttl_seconds: Some(-1),
+                ..Default::default()
+            }),
+        );
+
+        assert!(cache.err().is_some());
+    }
+
+    #[test]
+    fn should_delete_hash_details_of_evicted_entries() {
+        let temp = TempDir::new().unwrap();
+        let mut cache = NxCache::new(
+            temp.to_string_lossy().into(),
+            temp.join("cache").to_string_lossy().into(),
+            External::new(NxDbConnection::new(
+                Connection::open(temp.join("cache.db")).unwrap(),
+            )),
+            Some(true),
+            Some(0),
+            None,
+            None,
+        )
+        .unwrap();
+        let mut details = TaskDetails::new(External::new(NxDbConnection::new(
+            Connection::open(temp.join("cache.db")).unwrap(),
+        )))
+        .unwrap();
+        temp.child("dist/output.txt").write_str("output").unwrap();
+        for hash in ["evicted", "kept"] {
+            details
+                .record_task_details(vec![HashedTask {
+                    hash: hash.into(),
+                    project: "proj".into(),
+                    target: "build".into(),
+                    details: Some(HashMap::from([("AllOtherTasks".into(), hash.into())])),
+                    ..Default::default()
+                }])
+                .unwrap();
+            cache
+                .put(hash.into(), String::new(), vec!["dist".into()], 0)
+                .unwrap();
+        }
+
+        cache.remove_cache_entry("evicted").unwrap();
+
+        assert!(
+            details
+                .explain_hash_diff("kept".into(), "kept".into())
+                .is_ok()
+        );
+        assert!(
+            details
+                .explain_hash_diff("evicted".into(), "kept".into())
+                .is_err()
+        );
+    }
+}
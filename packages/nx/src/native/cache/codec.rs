@@ -0,0 +1,218 @@
+use std::env;
+use std::io::{self, Cursor, Read, Write};
+
+use flate2::Compression;
+
+use crate::native::cache::errors::HttpRemoteCacheErrors;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression used for remote cache artifacts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Gzip(u32),
+    Zstd(i32),
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Codec::Gzip(Compression::default().level())
+    }
+}
+
+impl Codec {
+    /// Reads the codec from `NX_SELF_HOSTED_REMOTE_CACHE_COMPRESSION` (`gzip` or `zstd`)
+    /// and its level from `NX_SELF_HOSTED_REMOTE_CACHE_COMPRESSION_LEVEL`
+    pub fn from_env() -> Result<Self, HttpRemoteCacheErrors> {
+        let Ok(name) = env::var("NX_SELF_HOSTED_REMOTE_CACHE_COMPRESSION") else {
+            return Ok(Codec::default());
+        };
+        let codec = Codec::from_name(&name).ok_or_else(|| {
+            HttpRemoteCacheErrors::Misconfigured(format!(
+                "Unsupported compression for the remote cache: {}. Use gzip or zstd.",
+                name
+            ))
+        })?;
+
+        let Ok(level) = env::var("NX_SELF_HOSTED_REMOTE_CACHE_COMPRESSION_LEVEL") else {
+            return Ok(codec);
+        };
+        codec.with_level(&level).ok_or_else(|| {
+            HttpRemoteCacheErrors::Misconfigured(format!(
+                "Invalid {} compression level for the remote cache: {}",
+                codec.name(),
+                level
+            ))
+        })
+    }
+
+    /// Returns the codec for a `Content-Encoding` style name with its default level
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "gzip" => Some(Codec::default()),
+            "zstd" => Some(Codec::Zstd(zstd::DEFAULT_COMPRESSION_LEVEL)),
+            _ => None,
+        }
+    }
+
+    /// Detects the codec of an artifact from its first bytes
+    pub fn detect(magic: &[u8]) -> Option<Self> {
+        if magic.starts_with(&ZSTD_MAGIC) {
+            Codec::from_name("zstd")
+        } else if magic.starts_with(&GZIP_MAGIC) {
+            Codec::from_name("gzip")
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Codec::Gzip(_) => "gzip",
+            Codec::Zstd(_) => "zstd",
+        }
+    }
+
+    fn with_level(self, level: &str) -> Option<Self> {
+        match self {
+            Codec::Gzip(_) => level
+                .parse::<u32>()
+                .ok()
+                .filter(|level| *level <= 9)
+                .map(Codec::Gzip),
+            Codec::Zstd(_) => level
+                .parse::<i32>()
+                .ok()
+                .filter(|level| zstd::compression_level_range().contains(level))
+                .map(Codec::Zstd),
+        }
+    }
+
+    pub fn encoder<W: Write>(self, writer: W) -> io::Result<Encoder<W>> {
+        Ok(match self {
+            Codec::Gzip(level) => Encoder::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                Compression::new(level),
+            )),
+            Codec::Zstd(level) => Encoder::Zstd(zstd::Encoder::new(writer, level)?),
+        })
+    }
+
+    pub fn decoder<'a, R: Read + 'a>(self, reader: R) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Codec::Gzip(_) => Box::new(flate2::read::GzDecoder::new(reader)),
+            Codec::Zstd(_) => Box::new(zstd::Decoder::new(reader)?),
+        })
+    }
+
+    /// Picks the decoder for an artifact from its encoding name,
+    /// falling back to the magic bytes at the start of the artifact
+    pub fn decode<'a, R: Read + 'a>(
+        encoding: Option<&str>,
+        mut reader: R,
+    ) -> io::Result<Box<dyn Read + 'a>> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        let reader = Cursor::new(magic).chain(reader);
+
+        encoding
+            .and_then(Codec::from_name)
+            .or_else(|| Codec::detect(&magic))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Unsupported remote cache artifact encoding: {}",
+                        encoding.unwrap_or("unknown")
+                    ),
+                )
+            })?
+            .decoder(reader)
+    }
+}
+
+pub enum Encoder<W: Write> {
+    Gzip(flate2::write::GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Writes the remaining compressed data and returns the underlying writer
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(codec: Codec, encoding: Option<&str>) -> Vec<u8> {
+        let mut encoder = codec.encoder(vec![]).unwrap();
+        encoder.write_all(b"remote cache artifact").unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut decompressed = vec![];
+        Codec::decode(encoding, compressed.as_slice())
+            .unwrap()
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        decompressed
+    }
+
+    #[test]
+    fn should_round_trip_with_the_encoding_name() {
+        assert_eq!(
+            round_trip(Codec::Gzip(9), Some("gzip")),
+            b"remote cache artifact"
+        );
+        assert_eq!(
+            round_trip(Codec::Zstd(19), Some("zstd")),
+            b"remote cache artifact"
+        );
+    }
+
+    #[test]
+    fn should_detect_the_codec_without_an_encoding_name() {
+        assert_eq!(round_trip(Codec::default(), None), b"remote cache artifact");
+        assert_eq!(
+            round_trip(Codec::Zstd(3), Some("br")),
+            b"remote cache artifact"
+        );
+    }
+
+    #[test]
+    fn should_parse_levels() {
+        assert_eq!(
+            Codec::from_name("zstd").unwrap().with_level("19"),
+            Some(Codec::Zstd(19))
+        );
+        assert_eq!(
+            Codec::from_name("GZIP").unwrap().with_level("1"),
+            Some(Codec::Gzip(1))
+        );
+        assert_eq!(Codec::from_name("gzip").unwrap().with_level("10"), None);
+        assert_eq!(Codec::from_name("zstd").unwrap().with_level("fast"), None);
+        assert_eq!(Codec::from_name("lz4"), None);
+    }
+}
@@ -0,0 +1,285 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+use tracing::trace;
+use walkdir::WalkDir;
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::native::cache::file_ops::symlink;
+use crate::native::utils::Normalize;
+
+/// A regular file inside of a cached task directory and the blob that holds its contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredFile {
+    /// Path relative to the task directory
+    pub path: String,
+    pub digest: String,
+    pub size: i64,
+}
+
+/// Content-addressed storage for cached outputs.
+///
+/// Each unique file is stored once under `<cache>/blobs/<xx>/<digest>`.
+/// Task directories (`<cache>/<hash>`) are populated with hardlinks into the store,
+/// so identical outputs produced by many tasks only take up space on disk once.
+pub struct ContentStore {
+    root: PathBuf,
+}
+
+impl ContentStore {
+    pub fn new(cache_path: &Path) -> io::Result<Self> {
+        let root = cache_path.join("blobs");
+        fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+
+    pub fn blob_path(&self, digest: &str) -> PathBuf {
+        self.root.join(&digest[..2]).join(digest)
+    }
+
+    /// Stores the file or directory at `src` and links it into `task_dir` at `output`
+    pub fn store_output(
+        &self,
+        src: &Path,
+        task_dir: &Path,
+        output: &str,
+    ) -> anyhow::Result<Vec<StoredFile>> {
+        let output = without_current_dir(output);
+        let mut stored = vec![];
+
+        if src.is_symlink() && !src.is_dir() {
+            let dest = task_dir.join(&output);
+            create_parent_dir(&dest)?;
+            symlink(fs::read_link(src)?, dest)?;
+            return Ok(stored);
+        }
+
+        for entry in WalkDir::new(src).follow_links(false) {
+            let entry = entry?;
+            let relative_path = match entry.path().strip_prefix(src)? {
+                p if p.as_os_str().is_empty() => output.clone(),
+                p => output.join(p),
+            };
+            let dest = task_dir.join(&relative_path);
+            let file_type = entry.file_type();
+
+            if file_type.is_dir() {
+                fs::create_dir_all(&dest)?;
+            } else if file_type.is_symlink() {
+                create_parent_dir(&dest)?;
+                symlink(fs::read_link(entry.path())?, dest)?;
+            } else {
+                let (digest, size) = self.insert(entry.path(), false)?;
+                create_parent_dir(&dest)?;
+                self.link(&digest, &dest)?;
+                stored.push(StoredFile {
+                    path: relative_path.to_normalized_string(),
+                    digest,
+                    size,
+                });
+            }
+        }
+
+        Ok(stored)
+    }
+
+    /// Moves the regular files of an already populated task directory into the store,
+    /// replacing them with links to their blobs.
+    /// This is used for task directories that were extracted from a remote cache.
+    pub fn adopt_dir(&self, task_dir: &Path) -> anyhow::Result<Vec<StoredFile>> {
+        let mut stored = vec![];
+        if !task_dir.is_dir() {
+            return Ok(stored);
+        }
+
+        for entry in WalkDir::new(task_dir).follow_links(false) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let (digest, size) = self.insert(path, true)?;
+            self.link(&digest, path)?;
+            stored.push(StoredFile {
+                path: path.strip_prefix(task_dir)?.to_normalized_string(),
+                digest,
+                size,
+            });
+        }
+
+        Ok(stored)
+    }
+
+    /// Recreates the regular files of a task directory from their blobs
+    pub fn materialize(&self, task_dir: &Path, files: &[StoredFile]) -> anyhow::Result<()> {
+        for file in files {
+            let dest = task_dir.join(&file.path);
+            create_parent_dir(&dest)?;
+            self.link(&file.digest, &dest)?;
+        }
+        Ok(())
+    }
+
+    /// The blob holding the same contents as the file at `path`, if there is one
+    pub fn find_blob(&self, path: &Path) -> io::Result<Option<PathBuf>> {
+        let (digest, _) = digest_file(path)?;
+        let blob = self.blob_path(&digest);
+        Ok(blob.exists().then_some(blob))
+    }
+
+    pub fn remove(&self, digest: &str) -> io::Result<()> {
+        trace!("Removing blob {}", digest);
+        match fs::remove_file(self.blob_path(digest)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds the contents of `src` to the store if they are not already there.
+    /// When `take` is set, the file is moved into the store instead of copied when possible.
+    fn insert(&self, src: &Path, take: bool) -> anyhow::Result<(String, i64)> {
+        let (digest, size) = digest_file(src)?;
+        let blob = self.blob_path(&digest);
+        if blob.exists() {
+            trace!("Blob {} already stored", digest);
+            return Ok((digest, size));
+        }
+
+        create_parent_dir(&blob)?;
+        // Write to a temporary file first so concurrent processes never observe a partial blob
+        let tmp = blob.with_extension(format!("{}.tmp", rand::random::<u32>()));
+        if !(take && fs::rename(src, &tmp).is_ok()) {
+            fs::copy(src, &tmp)?;
+        }
+        fs::rename(&tmp, &blob)?;
+        trace!("Stored blob {} ({} bytes)", digest, size);
+
+        Ok((digest, size))
+    }
+
+    fn link(&self, digest: &str, dest: &Path) -> io::Result<()> {
+        let blob = self.blob_path(digest);
+        if dest.exists() || dest.is_symlink() {
+            fs::remove_file(dest)?;
+        }
+        if let Err(e) = fs::hard_link(&blob, dest) {
+            // Some filesystems do not support hardlinks, fall back to a copy of the blob
+            trace!("Unable to link {:?} -> {:?}: {:?}", &blob, dest, e);
+            fs::copy(&blob, dest)?;
+        }
+        Ok(())
+    }
+}
+
+fn digest_file(path: &Path) -> io::Result<(String, i64)> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = [0; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as i64;
+    }
+    Ok((format!("{:032x}", hasher.digest128()), size))
+}
+
+fn create_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.exists() => fs::create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
+
+fn without_current_dir(path: &str) -> PathBuf {
+    Path::new(path)
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn should_store_identical_files_once() {
+        let temp = TempDir::new().unwrap();
+        temp.child("dist/a.txt").write_str("same").unwrap();
+        temp.child("dist/nested/b.txt").write_str("same").unwrap();
+        temp.child("dist/c.txt").write_str("different").unwrap();
+
+        let store = ContentStore::new(&temp.join("cache")).unwrap();
+        let task_dir = temp.join("cache/123");
+        let mut stored = store
+            .store_output(&temp.join("dist"), &task_dir, "dist")
+            .unwrap();
+        stored.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let paths = stored.iter().map(|f| f.path.as_str()).collect::<Vec<_>>();
+        assert_eq!(paths, vec!["dist/a.txt", "dist/c.txt", "dist/nested/b.txt"]);
+        assert_eq!(stored[0].digest, stored[2].digest);
+        assert_ne!(stored[0].digest, stored[1].digest);
+
+        let blobs = WalkDir::new(temp.join("cache/blobs"))
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+            .count();
+        assert_eq!(blobs, 2);
+
+        temp.child("cache/123/dist/nested/b.txt").assert("same");
+    }
+
+    #[test]
+    fn should_store_single_files() {
+        let temp = TempDir::new().unwrap();
+        temp.child("out.txt").write_str("content").unwrap();
+
+        let store = ContentStore::new(&temp.join("cache")).unwrap();
+        let stored = store
+            .store_output(&temp.join("out.txt"), &temp.join("cache/123"), "out.txt")
+            .unwrap();
+
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].path, "out.txt");
+        assert_eq!(stored[0].size, 7);
+        temp.child("cache/123/out.txt").assert("content");
+    }
+
+    #[test]
+    fn should_adopt_extracted_directories() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/123/dist/a.txt").write_str("a").unwrap();
+
+        let store = ContentStore::new(&temp.join("cache")).unwrap();
+        let stored = store.adopt_dir(&temp.join("cache/123")).unwrap();
+
+        assert_eq!(stored.len(), 1);
+        assert!(store.blob_path(&stored[0].digest).exists());
+        temp.child("cache/123/dist/a.txt").assert("a");
+    }
+
+    #[test]
+    fn should_materialize_removed_task_directories() {
+        let temp = TempDir::new().unwrap();
+        temp.child("dist/a.txt").write_str("a").unwrap();
+
+        let store = ContentStore::new(&temp.join("cache")).unwrap();
+        let task_dir = temp.join("cache/123");
+        let stored = store
+            .store_output(&temp.join("dist"), &task_dir, "dist")
+            .unwrap();
+        fs::remove_dir_all(&task_dir).unwrap();
+
+        store.materialize(&task_dir, &stored).unwrap();
+
+        temp.child("cache/123/dist/a.txt").assert("a");
+    }
+}
@@ -12,6 +12,10 @@ pub enum HttpRemoteCacheErrors {
     Misconfigured(String),
     #[error("Failed to send request: {0}")]
     RequestError(String),
+    #[error("Remote cache artifact failed verification: {0}")]
+    IntegrityError(String),
+    #[error("The remote cache is degraded and will not be used for the rest of this run: {0}")]
+    Degraded(String),
 }
 
 pub type AsyncHttpRemoteCacheErrors = Pin<Box<dyn Future<Output = HttpRemoteCacheErrors>>>;
@@ -59,6 +63,8 @@ impl AsRef<str> for HttpRemoteCacheErrors {
             HttpRemoteCacheErrors::Unauthorized(_) => "Unauthorized",
             HttpRemoteCacheErrors::Misconfigured(_) => "Misconfigured",
             HttpRemoteCacheErrors::RequestError(_) => "RequestError",
+            HttpRemoteCacheErrors::IntegrityError(_) => "IntegrityError",
+            HttpRemoteCacheErrors::Degraded(_) => "Degraded",
             // _ => "Error",
         }
     }
@@ -82,3 +88,11 @@ impl From<HttpRemoteCacheErrors> for napi::Error {
         napi::Error::new(status, err.to_string())
     }
 }
+
+/// Converts errors that were propagated through `anyhow`, keeping the status of remote cache errors
+pub fn convert_anyhow_error(err: anyhow::Error) -> napi::Error {
+    match err.downcast::<HttpRemoteCacheErrors>() {
+        Ok(err) => err.into(),
+        Err(err) => err.into(),
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::{HashMap, HashSet};
+
+/// Entries which have not been accessed for this long are evicted, unless configured otherwise
+pub const DEFAULT_TTL_SECONDS: i64 = 7 * 24 * 60 * 60;
+/// Number of eviction candidates which are read from the database at once
+pub const EVICTION_BATCH_SIZE: i64 = 100;
+
+/// How cache entries are picked for eviction once the cache grows over its maximum size
+#[napi(string_enum)]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the entries which were accessed the longest time ago first
+    #[default]
+    #[allow(non_camel_case_types)]
+    lru,
+    /// Evict large entries which have not been accessed for a while first,
+    /// weighing the size of an entry by the time since it was last accessed
+    #[allow(non_camel_case_types)]
+    sizeWeighted,
+}
+
+impl EvictionPolicy {
+    /// Orders the entries of the `cache_outputs` table, the first ones are evicted first
+    pub fn order_by(&self) -> &'static str {
+        match self {
+            EvictionPolicy::lru => "accessed_at ASC",
+            EvictionPolicy::sizeWeighted => {
+                "size * (julianday('now') - julianday(accessed_at)) DESC, accessed_at ASC"
+            }
+        }
+    }
+}
+
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct EvictionOptions {
+    /// Defaults to `lru`
+    pub policy: Option<EvictionPolicy>,
+    /// Entries which have not been accessed for this many seconds are evicted. Defaults to 7 days,
+    /// negative values are rejected.
+    pub ttl_seconds: Option<i64>,
+}
+
+/// The entries which were, or in a dry run would be, evicted
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct EvictionReport {
+    pub hashes: Vec<String>,
+    /// Bytes freed on disk. Outputs which are shared with entries that are kept do not free anything.
+    pub freed_bytes: i64,
+}
+
+/// A blob referenced by a cache entry
+pub struct BlobReference {
+    pub digest: String,
+    pub size: i64,
+    /// How many times the blob is referenced across all entries
+    pub ref_count: i64,
+    /// How many times the blob is referenced by this entry
+    pub references: i64,
+}
+
+/// Keeps track of the entries that are going to be evicted and of the bytes that frees,
+/// without touching the cache itself so that it can be used for dry runs.
+#[derive(Default)]
+pub struct EvictionPlan {
+    hashes: Vec<String>,
+    planned: HashSet<String>,
+    freed_bytes: i64,
+    remaining_references: HashMap<String, i64>,
+}
+
+impl EvictionPlan {
+    pub fn contains(&self, hash: &str) -> bool {
+        self.planned.contains(hash)
+    }
+
+    pub fn freed_bytes(&self) -> i64 {
+        self.freed_bytes
+    }
+
+    pub fn hashes(&self) -> &[String] {
+        &self.hashes
+    }
+
+    /// Plans to evict an entry, which frees its terminal output
+    /// and the blobs that no entry which is kept references.
+    pub fn add(
+        &mut self,
+        hash: String,
+        terminal_output_size: i64,
+        blobs: impl IntoIterator<Item = BlobReference>,
+    ) {
+        if !self.planned.insert(hash.clone()) {
+            return;
+        }
+        self.freed_bytes += terminal_output_size;
+        for blob in blobs {
+            let remaining = self
+                .remaining_references
+                .entry(blob.digest)
+                .or_insert(blob.ref_count);
+            *remaining -= blob.references;
+            if *remaining == 0 {
+                self.freed_bytes += blob.size;
+            }
+        }
+        self.hashes.push(hash);
+    }
+
+    pub fn into_report(self) -> EvictionReport {
+        EvictionReport {
+            hashes: self.hashes,
+            freed_bytes: self.freed_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn blob(digest: &str, size: i64, ref_count: i64) -> BlobReference {
+        BlobReference {
+            digest: digest.into(),
+            size,
+            ref_count,
+            references: 1,
+        }
+    }
+
+    #[test]
+    fn should_only_free_blobs_once_nothing_references_them() {
+        let mut plan = EvictionPlan::default();
+
+        plan.add("1".into(), 5, [blob("shared", 100, 2), blob("a", 10, 1)]);
+        assert_eq!(plan.freed_bytes(), 15);
+
+        plan.add("2".into(), 5, [blob("shared", 100, 2)]);
+        assert_eq!(plan.freed_bytes(), 120);
+        assert_eq!(plan.hashes(), ["1", "2"]);
+    }
+
+    #[test]
+    fn should_count_entries_once() {
+        let mut plan = EvictionPlan::default();
+
+        plan.add("1".into(), 5, [blob("shared", 100, 2)]);
+        plan.add("1".into(), 5, [blob("shared", 100, 2)]);
+
+        assert_eq!(plan.freed_bytes(), 5);
+        assert!(plan.contains("1"));
+        assert_eq!(plan.into_report().hashes, vec!["1"]);
+    }
+}
@@ -4,6 +4,8 @@ use std::{fs, io};
 use fs_extra::error::ErrorKind;
 use tracing::trace;
 
+use crate::native::cache::content_store::ContentStore;
+
 #[napi]
 pub fn remove(src: String) -> anyhow::Result<()> {
     fs_extra::remove_items(&[src]).map_err(|err| match err.kind {
@@ -17,7 +19,37 @@ pub fn copy(src: String, dest: String) -> anyhow::Result<i64> {
     _copy(src, dest)
 }
 
+/// How cached outputs are restored into the workspace
+#[napi(string_enum)]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum CacheRestoreMode {
+    /// Copy every file out of the cache
+    #[default]
+    #[allow(non_camel_case_types)]
+    copy,
+    /// Try a copy-on-write reflink, then a hardlink, and fall back to a copy.
+    /// Hardlinked outputs share their contents with the cache until they are given copies
+    /// of their own, see `NxCache::break_output_links`.
+    #[allow(non_camel_case_types)]
+    link,
+}
+
 pub fn _copy<P>(src: P, dest: P) -> anyhow::Result<i64>
+where
+    P: AsRef<Path>,
+{
+    copy_with(src, dest, &|src, dest| fs::copy(src, dest))
+}
+
+/// Restores cached outputs from `src` into `dest` using the given [`CacheRestoreMode`]
+pub fn _restore<P>(src: P, dest: P, mode: CacheRestoreMode) -> anyhow::Result<i64>
+where
+    P: AsRef<Path>,
+{
+    copy_with(src, dest, &move |src, dest| restore_file(src, dest, mode))
+}
+
+fn copy_with<P>(src: P, dest: P, copy_file: &CopyFile) -> anyhow::Result<i64>
 where
     P: AsRef<Path>,
 {
@@ -32,17 +64,141 @@ where
     }
 
     let size = if src.is_dir() {
-        copy_dir_all(&src, dest).map_err(anyhow::Error::new)?
+        copy_dir_all(&src, dest, copy_file).map_err(anyhow::Error::new)?
     } else if src.is_symlink() {
         symlink(fs::read_link(src)?, dest)?;
         0
     } else {
-        fs::copy(src, dest)?
+        copy_file(&src, &dest)?
     };
 
     Ok(size as i64)
 }
 
+type CopyFile = dyn Fn(&Path, &Path) -> io::Result<u64>;
+
+fn restore_file(src: &Path, dest: &Path, mode: CacheRestoreMode) -> io::Result<u64> {
+    if mode == CacheRestoreMode::link {
+        if dest.exists() || dest.is_symlink() {
+            fs::remove_file(dest)?;
+        }
+        if let Some(size) = link_file(src, dest) {
+            return Ok(size);
+        }
+    }
+
+    let size = fs::copy(src, dest)?;
+    // Copies keep the permissions of the cached file, which older versions protected on link restores
+    set_writable(dest, true)?;
+    Ok(size)
+}
+
+fn link_file(src: &Path, dest: &Path) -> Option<u64> {
+    let size = fs::metadata(src).ok()?.len();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if reflink_copy::reflink(src, dest).is_ok() {
+        trace!("Reflinked {:?} -> {:?}", src, dest);
+        // A reflink has its own copy of the contents, so it is safe to edit
+        set_writable(dest, true).ok()?;
+        return Some(size);
+    }
+
+    // A hardlink shares its contents with the cache, `break_links` gives it a copy of its own
+    // before a task writes it
+    #[cfg(unix)]
+    if fs::hard_link(src, dest).is_ok() {
+        trace!("Hardlinked {:?} -> {:?}", src, dest);
+        return Some(size);
+    }
+
+    trace!("Unable to link {:?} -> {:?}, copying instead", src, dest);
+    None
+}
+
+/// Replaces files which are hardlinked to blobs of the `store` with copies of their own, so that
+/// outputs restored from the cache can be rewritten in place without changing the cache.
+/// Files which are hardlinked to anything else are left as they are.
+pub fn break_links(path: &Path, store: &ContentStore) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            break_links(&entry?.path(), store)?;
+        }
+    } else if metadata.is_file()
+        && is_hardlinked(&metadata)
+        && is_linked_to_blob(path, &metadata, store)?
+    {
+        trace!("Breaking the link of {:?}", path);
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let copy = path.with_file_name(format!(".{file_name}.nx-unlink"));
+        fs::copy(path, &copy)?;
+        set_writable(&copy, true)?;
+        // Renaming over the link leaves the file it shares its contents with untouched
+        fs::rename(&copy, path)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_hardlinked(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+// Outputs are only hardlinked on unix
+#[cfg(not(unix))]
+fn is_hardlinked(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn is_linked_to_blob(
+    path: &Path,
+    metadata: &fs::Metadata,
+    store: &ContentStore,
+) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let Some(blob) = store.find_blob(path)? else {
+        return Ok(false);
+    };
+    let blob = fs::metadata(blob)?;
+    Ok(blob.dev() == metadata.dev() && blob.ino() == metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn is_linked_to_blob(
+    _path: &Path,
+    _metadata: &fs::Metadata,
+    _store: &ContentStore,
+) -> io::Result<bool> {
+    Ok(false)
+}
+
+#[cfg(unix)]
+fn set_writable(path: &Path, writable: bool) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(path)?.permissions();
+    let mode = permissions.mode();
+    let new_mode = if writable {
+        mode | 0o200
+    } else {
+        mode & !0o222
+    };
+    if new_mode != mode {
+        permissions.set_mode(new_mode);
+        fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+// Cached files are only protected on unix, where hardlinks are used
+#[cfg(not(unix))]
+fn set_writable(_path: &Path, _writable: bool) -> io::Result<()> {
+    Ok(())
+}
+
 fn remove_trailing_single_dot(path: impl AsRef<Path>) -> PathBuf {
     let mut components = path.as_ref().components().collect::<Vec<_>>();
 
@@ -56,21 +212,25 @@ fn remove_trailing_single_dot(path: impl AsRef<Path>) -> PathBuf {
 }
 
 #[cfg(windows)]
-fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
+pub(crate) fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
     std::os::windows::fs::symlink_file(original, link)
 }
 
 #[cfg(unix)]
-fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
+pub(crate) fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
     std::os::unix::fs::symlink(original, link)
 }
 
 #[cfg(target_os = "wasi")]
-fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
+pub(crate) fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
     std::os::wasi::fs::symlink_path(original, link)
 }
 
-fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<u64> {
+fn copy_dir_all(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    copy_file: &CopyFile,
+) -> io::Result<u64> {
     trace!("creating directory: {:?}", dst.as_ref());
     fs::create_dir_all(&dst)?;
     trace!("reading source directory: {:?}", src.as_ref());
@@ -79,7 +239,11 @@ fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<u64>
         let entry = entry?;
         let ty = entry.file_type()?;
         let size: u64 = if ty.is_dir() {
-            copy_dir_all(entry.path(), dst.as_ref().join(entry.file_name()))?
+            copy_dir_all(
+                entry.path(),
+                dst.as_ref().join(entry.file_name()),
+                copy_file,
+            )?
         } else if ty.is_symlink() {
             symlink(
                 fs::read_link(entry.path())?,
@@ -88,7 +252,7 @@ fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<u64>
             // Handle this
             0
         } else {
-            fs::copy(entry.path(), dst.as_ref().join(entry.file_name()))?
+            copy_file(&entry.path(), &dst.as_ref().join(entry.file_name()))?
         };
         total_size += size;
     }
@@ -156,6 +320,96 @@ mod test {
         );
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn should_restore_links_without_protecting_the_cache() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/dist/file.txt")
+            .write_str("content")
+            .unwrap();
+
+        let src = temp.join("cache");
+        let dest = temp.join("workspace");
+        _restore(&src, &dest, CacheRestoreMode::link).unwrap();
+
+        temp.child("workspace/dist/file.txt").assert("content");
+        // The cached file may share its inode with other outputs, its permissions are left alone
+        let cached = temp.join("cache/dist/file.txt");
+        assert!(!fs::metadata(&cached).unwrap().permissions().readonly());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn should_break_links_to_the_cache() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp = TempDir::new().unwrap();
+        temp.child("dist/file.txt").write_str("content").unwrap();
+        let store = ContentStore::new(&temp.join("cache")).unwrap();
+        let stored = store
+            .store_output(&temp.join("dist"), &temp.join("cache/123"), "dist")
+            .unwrap();
+        fs::remove_dir_all(temp.join("dist")).unwrap();
+        _restore(
+            &temp.join("cache/123"),
+            &temp.join("workspace"),
+            CacheRestoreMode::link,
+        )
+        .unwrap();
+
+        break_links(&temp.join("workspace"), &store).unwrap();
+
+        let restored = temp.join("workspace/dist/file.txt");
+        let blob = store.blob_path(&stored[0].digest);
+        assert_ne!(
+            fs::metadata(&restored).unwrap().ino(),
+            fs::metadata(&blob).unwrap().ino()
+        );
+        fs::write(&restored, "new content").unwrap();
+        assert_eq!(fs::read_to_string(&blob).unwrap(), "content");
+        temp.child("workspace/dist/file.txt").assert("new content");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn should_keep_links_which_are_not_to_the_cache() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp = TempDir::new().unwrap();
+        let store = ContentStore::new(&temp.join("cache")).unwrap();
+        temp.child("workspace/dist/file.txt")
+            .write_str("content")
+            .unwrap();
+        let output = temp.join("workspace/dist/file.txt");
+        let link = temp.join("workspace/dist/link.txt");
+        fs::hard_link(&output, &link).unwrap();
+
+        break_links(&temp.join("workspace"), &store).unwrap();
+
+        assert_eq!(
+            fs::metadata(&output).unwrap().ino(),
+            fs::metadata(&link).unwrap().ino()
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn should_restore_writable_copies() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/file.txt").write_str("content").unwrap();
+        let cached = temp.join("cache/file.txt");
+        set_writable(&cached, false).unwrap();
+
+        let src = temp.join("cache");
+        let dest = temp.join("workspace");
+        _restore(&src, &dest, CacheRestoreMode::copy).unwrap();
+
+        let restored = temp.join("workspace/file.txt");
+        assert!(!fs::metadata(&restored).unwrap().permissions().readonly());
+        fs::write(&restored, "edited").unwrap();
+        temp.child("cache/file.txt").assert("content");
+    }
+
     #[test]
     fn should_copy_directories_with_symlinks() {
         let temp = TempDir::new().unwrap();
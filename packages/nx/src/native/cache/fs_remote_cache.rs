@@ -0,0 +1,247 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use super::{
+    archive::{extract_archive, write_archive},
+    cache::CachedResult,
+    codec::Codec,
+    errors::{HttpRemoteCacheErrors, convert_anyhow_error},
+    integrity::SigningKey,
+};
+use tracing::trace;
+
+/// A remote cache that keeps its entries in a shared directory, such as a network mount.
+/// Entries use the same tarball format as the `HttpRemoteCache`.
+#[napi]
+pub struct FsRemoteCache {
+    directory: PathBuf,
+    codec: Codec,
+    signing_key: Option<SigningKey>,
+}
+
+#[napi]
+impl FsRemoteCache {
+    /// Uses `directory`, or `NX_SELF_HOSTED_REMOTE_CACHE_DIRECTORY` when it is not provided
+    #[napi(constructor)]
+    pub fn new(directory: Option<String>) -> napi::Result<Self> {
+        let directory = directory
+            .or_else(|| env::var("NX_SELF_HOSTED_REMOTE_CACHE_DIRECTORY").ok())
+            .ok_or_else(|| {
+                HttpRemoteCacheErrors::Misconfigured(
+                    "NX_SELF_HOSTED_REMOTE_CACHE_DIRECTORY must be set".to_string(),
+                )
+            })?;
+
+        Ok(FsRemoteCache {
+            directory: PathBuf::from(directory),
+            codec: Codec::from_env()?,
+            signing_key: SigningKey::from_env(),
+        })
+    }
+
+    #[napi]
+    pub async fn retrieve(
+        &self,
+        hash: String,
+        cache_directory: String,
+    ) -> napi::Result<Option<CachedResult>> {
+        let span = tracing::trace_span!("retrieve", hash = %hash);
+        let _guard = span.enter();
+
+        let entry_path = self.entry_path(&hash);
+        let signing_key = self.signing_key.clone();
+        tokio::task::spawn_blocking(move || {
+            let file = match File::open(&entry_path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            trace!("Extracting {}", entry_path.display());
+            extract_archive(
+                BufReader::new(file),
+                None,
+                signing_key.as_ref(),
+                &cache_directory,
+                &hash,
+            )
+            .map(Some)
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to extract tarball: {}", e))?
+        .map_err(convert_anyhow_error)
+    }
+
+    #[napi]
+    pub async fn store(
+        &self,
+        hash: String,
+        cache_directory: String,
+        terminal_output: String,
+        code: u32,
+    ) -> napi::Result<bool> {
+        let span = tracing::trace_span!("store", hash = %hash);
+        let _guard = span.enter();
+
+        let entry_path = self.entry_path(&hash);
+        let codec = self.codec;
+        let signing_key = self.signing_key.clone();
+        tokio::task::spawn_blocking(move || {
+            if entry_path.exists() {
+                trace!("{} is already stored", hash);
+                return Ok(false);
+            }
+            fs::create_dir_all(
+                entry_path
+                    .parent()
+                    .expect("Entries are inside of a directory"),
+            )?;
+
+            // Other machines can be reading from the directory, so the tarball is written
+            // to a temporary file first and only moved into place once it is complete
+            let tmp_path =
+                entry_path.with_file_name(format!(".{}.{}.tmp", hash, rand::random::<u32>()));
+            let result = write_entry(
+                &tmp_path,
+                &entry_path,
+                codec,
+                signing_key.as_ref(),
+                Path::new(&cache_directory),
+                &hash,
+                &terminal_output,
+                code,
+            );
+            if tmp_path.exists() {
+                fs::remove_file(&tmp_path)?;
+            }
+            result
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to create tarball: {}", e))?
+        .map_err(convert_anyhow_error)
+    }
+
+    fn entry_path(&self, hash: &str) -> PathBuf {
+        self.directory.join(hash)
+    }
+}
+
+/// Writes the tarball to `tmp_path` and moves it to `entry_path`.
+/// Returns false when another process stored the same entry first.
+#[allow(clippy::too_many_arguments)]
+fn write_entry(
+    tmp_path: &Path,
+    entry_path: &Path,
+    codec: Codec,
+    signing_key: Option<&SigningKey>,
+    cache_directory: &Path,
+    hash: &str,
+    terminal_output: &str,
+    code: u32,
+) -> anyhow::Result<bool> {
+    let mut writer = BufWriter::new(File::create(tmp_path)?);
+    write_archive(
+        &mut writer,
+        codec,
+        signing_key,
+        cache_directory,
+        hash,
+        terminal_output,
+        code,
+    )?;
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+
+    // Linking never replaces an existing entry, so the first of several concurrent stores wins
+    match fs::hard_link(tmp_path, entry_path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            trace!("{} was stored by another process", hash);
+            Ok(false)
+        }
+        Err(e) => {
+            // Some network filesystems do not support hardlinks.
+            // Renaming is still atomic, and concurrent stores of a hash write the same outputs.
+            trace!("Unable to link {}: {:?}", entry_path.display(), e);
+            fs::rename(tmp_path, entry_path)?;
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    fn fs_remote_cache(temp: &TempDir) -> FsRemoteCache {
+        FsRemoteCache {
+            directory: temp.join("shared"),
+            codec: Codec::default(),
+            signing_key: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn should_store_and_retrieve_entries() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/123/dist/file.txt")
+            .write_str("content")
+            .unwrap();
+        let remote_cache = fs_remote_cache(&temp);
+        let cache_directory = temp.join("cache").to_string_lossy().into_owned();
+
+        let stored = remote_cache
+            .store("123".into(), cache_directory.clone(), "output".into(), 0)
+            .await
+            .unwrap();
+        assert!(stored);
+        fs::remove_dir_all(temp.join("cache/123")).unwrap();
+
+        let result = remote_cache
+            .retrieve("123".into(), cache_directory.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.terminal_output, Some("output".into()));
+        temp.child("cache/123/dist/file.txt").assert("content");
+
+        let missing = remote_cache
+            .retrieve("456".into(), cache_directory)
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_keep_the_first_of_concurrent_stores() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/123/dist/file.txt")
+            .write_str("content")
+            .unwrap();
+        let remote_cache = fs_remote_cache(&temp);
+        let cache_directory = temp.join("cache").to_string_lossy().into_owned();
+
+        let stores = (0..8)
+            .map(|_| remote_cache.store("123".into(), cache_directory.clone(), "output".into(), 0));
+        let stored = futures::future::join_all(stores)
+            .await
+            .into_iter()
+            .map(|r| r.unwrap())
+            .filter(|stored| *stored)
+            .count();
+
+        assert_eq!(stored, 1);
+        let entries = fs::read_dir(temp.join("shared"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect::<Vec<_>>();
+        assert_eq!(entries, vec!["123"]);
+    }
+}
@@ -1,29 +1,68 @@
 use std::{
     env,
-    fs::{self},
-    io::Read,
+    io::{self, Write},
+    mem,
     path::Path,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use super::{
+    archive::{extract_archive, write_archive},
     cache::CachedResult,
-    errors::{HttpRemoteCacheErrors, convert_response_to_error, report_request_error},
+    codec::Codec,
+    errors::{
+        HttpRemoteCacheErrors, convert_anyhow_error, convert_response_to_error,
+        report_request_error,
+    },
+    integrity::SigningKey,
+    request_policy::RequestPolicy,
 };
-use flate2::Compression;
-use reqwest::{Client, ClientBuilder, StatusCode, header};
-use tar::{Archive, Builder};
+use futures::{StreamExt, stream};
+use reqwest::{Body, Client, ClientBuilder, StatusCode, header};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_util::io::{StreamReader, SyncIoBridge};
 use tracing::trace;
 
+/// Size of the chunks that the tarball is uploaded in
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Number of chunks that can be waiting to be uploaded before building the tarball is paused
+const MAX_BUFFERED_CHUNKS: usize = 16;
+/// `Content-Encoding` style header naming the compression of an artifact
+const CACHE_ENCODING_HEADER: &str = "x-nx-cache-encoding";
+/// `Accept-Encoding` style header listing the compressions that can be restored
+const CACHE_ACCEPT_ENCODING_HEADER: &str = "x-nx-cache-accept-encoding";
+/// Number of requests that are sent at once when checking or downloading several hashes
+const DEFAULT_CONCURRENCY: u32 = 8;
+
+/// Body of the bulk lookup request and its response
+#[derive(Serialize, Deserialize)]
+struct CacheQuery {
+    hashes: Vec<String>,
+}
+
+#[napi(object)]
+pub struct PrefetchedCacheResult {
+    pub hash: String,
+    pub result: CachedResult,
+}
+
 #[napi]
 pub struct HttpRemoteCache {
     client: Client,
     url: String,
+    codec: Codec,
+    signing_key: Option<SigningKey>,
+    /// Set once the server responds that it does not implement the bulk lookup endpoint
+    bulk_query_unsupported: AtomicBool,
+    policy: RequestPolicy,
 }
 
 #[napi]
 impl HttpRemoteCache {
     #[napi(constructor)]
-    pub fn new() -> Self {
+    pub fn new() -> napi::Result<Self> {
         let mut headers = header::HeaderMap::new();
         let auth_token = env::var("NX_SELF_HOSTED_REMOTE_CACHE_ACCESS_TOKEN");
         if let Ok(token) = auth_token {
@@ -39,7 +78,11 @@ impl HttpRemoteCache {
             header::HeaderValue::from_static("application/octet-stream"),
         );
 
-        let mut client_builder = ClientBuilder::new().default_headers(headers);
+        let policy = RequestPolicy::from_env()?;
+        let mut client_builder = ClientBuilder::new()
+            .default_headers(headers)
+            .connect_timeout(policy.connect_timeout)
+            .read_timeout(policy.read_timeout);
 
         let env_accept_unauthorized = env::var("NODE_TLS_REJECT_UNAUTHORIZED");
         if let Ok(env_accept_unauthorized) = env_accept_unauthorized {
@@ -48,13 +91,17 @@ impl HttpRemoteCache {
             }
         }
 
-        HttpRemoteCache {
+        Ok(HttpRemoteCache {
             client: client_builder
                 .build()
                 .expect("Failed to create HTTP client"),
             url: env::var("NX_SELF_HOSTED_REMOTE_CACHE_SERVER")
                 .expect("NX_REMOTE_CACHE_URL must be set"),
-        }
+            codec: Codec::from_env()?,
+            signing_key: SigningKey::from_env(),
+            bulk_query_unsupported: AtomicBool::new(false),
+            policy,
+        })
     }
 
     #[napi]
@@ -66,25 +113,40 @@ impl HttpRemoteCache {
         let span = tracing::trace_span!("retrieve", hash = %hash);
         let _guard = span.enter();
 
+        if self.policy.is_degraded() {
+            return Ok(None);
+        }
+
         let url: String = format!("{}/v1/cache/{}", self.url, hash);
-        let response = self.client.get(&url).send().await;
-        if let Ok(resp) = response {
-            trace!("HTTP response status: {}", resp.status());
-            let status = resp.status();
-
-            match status {
-                StatusCode::OK => {
-                    Ok(Some(
-                        // response is an application/octet-stream containing a tarball
-                        // we need to extract the tarball and return the path to the extracted files
-                        Self::download_and_extract_from_result(resp, cache_directory, hash).await?,
-                    ))
-                }
-                StatusCode::NOT_FOUND => Ok(None),
-                _ => Err(convert_response_to_error(resp).await.into()),
+        let resp = self
+            .policy
+            .send(|| {
+                self.client
+                    .get(&url)
+                    .header(CACHE_ACCEPT_ENCODING_HEADER, "zstd, gzip")
+                    .send()
+            })
+            .await?;
+        trace!("HTTP response status: {}", resp.status());
+        let status = resp.status();
+
+        match status {
+            StatusCode::OK => {
+                Ok(Some(
+                    // response is an application/octet-stream containing a tarball
+                    // we need to extract the tarball and return the path to the extracted files
+                    Self::download_and_extract_from_result(
+                        resp,
+                        cache_directory,
+                        hash,
+                        self.signing_key.clone(),
+                    )
+                    .await
+                    .map_err(convert_anyhow_error)?,
+                ))
             }
-        } else {
-            Err(HttpRemoteCacheErrors::RequestError(response.unwrap_err().to_string()).into())
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(convert_response_to_error(resp).await.into()),
         }
     }
 
@@ -105,62 +167,47 @@ impl HttpRemoteCache {
         // retrieving old hashes will not be affected, and new entries
         // will have distinct hashes.
 
-        // create a tarball in memory from the cache dir
-        let tar_gz: Vec<u8> = Vec::new();
-        let enc = flate2::write::GzEncoder::new(tar_gz, Compression::default());
-        let mut archive = Builder::new(enc);
-        archive.follow_symlinks(false);
-        trace!("Created tar file for writing");
-
-        let cache_path = Path::new(&cache_directory);
-        let outputs_path = cache_path.join(&hash);
-
-        trace!("Adding cache artifacts to tarball");
-        archive.append_dir_all("", &outputs_path)?;
-        trace!("Added cache directory to tarball");
-
-        trace!("Adding terminal output to tarball");
-        let mut terminal_output_header = tar::Header::new_old();
-        let terminal_output_bytes = terminal_output.as_bytes();
-        terminal_output_header.set_size(terminal_output_bytes.len() as u64);
-        terminal_output_header.set_cksum(); // Ensure the checksum is set correctly
-        archive.append_data(
-            &mut terminal_output_header,
-            "terminalOutput",
-            terminal_output_bytes,
-        )?;
-        trace!("Added terminal output to tarball");
-
-        trace!("Adding code to tarball");
-        let mut code_header = tar::Header::new_old();
-        code_header.set_size(4);
-        code_header.set_cksum(); // Ensure the checksum is set correctly
-        archive.append_data(&mut code_header, "code", &code.to_be_bytes()[..])?;
-        trace!("Added code to tarball");
-
-        trace!("Finishing tarball");
-        archive
-            .finish() // Finish the archive to get the inner bytes
-            .map_err(|e| anyhow::anyhow!(format!("Failed to finish tarball: {}", e)))?;
-        trace!("Finished tarball");
-
-        trace!("Reading tarball into memory");
-        let archive_bytes = archive.into_inner()?;
-        let buffer = archive_bytes.finish()?;
-        trace!("read tarball into memory");
+        if self.policy.is_degraded() {
+            return Ok(false);
+        }
 
+        // The tarball is streamed into the request, so it is built again for every attempt
+        let mut archive_task = None;
         let url: String = format!("{}/v1/cache/{}", self.url, hash);
         let response = self
-            .client
-            .put(&url)
-            .body(buffer) // Convert the bytes to a Vec<u8> for the request body
-            .send()
+            .policy
+            .send(|| {
+                let (body, task) =
+                    self.archive_body(&hash, &cache_directory, &terminal_output, code);
+                archive_task = Some(task);
+                self.client
+                    .put(&url)
+                    .header(CACHE_ENCODING_HEADER, self.codec.name())
+                    .body(body)
+                    .send()
+            })
+            .await;
+
+        let archive_result = archive_task
+            .expect("The request is sent at least once")
             .await
-            .map_err(|e| {
-                napi::Error::from(HttpRemoteCacheErrors::RequestError(report_request_error(
-                    &e,
-                )))
-            })?;
+            .map_err(|e| anyhow::anyhow!("Failed to create tarball: {}", e))?;
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                // A failed upload closes the body, which stops the tarball from being built.
+                // Only report the tarball when building it is what failed the upload.
+                return match archive_result {
+                    Err(archive_error)
+                        if !matches!(e, HttpRemoteCacheErrors::Degraded(_))
+                            && !is_closed_upload(&archive_error) =>
+                    {
+                        Err(convert_anyhow_error(archive_error))
+                    }
+                    _ => Err(e.into()),
+                };
+            }
+        };
 
         match response.status() {
             StatusCode::OK => Ok(true),
@@ -173,74 +220,327 @@ impl HttpRemoteCache {
         }
     }
 
-    async fn download_and_extract_from_result(
-        response: reqwest::Response,
+    /// Whether too many requests in a row failed, after which the remote cache is not used anymore
+    #[napi]
+    pub fn is_degraded(&self) -> bool {
+        self.policy.is_degraded()
+    }
+
+    /// Returns the hashes which are stored in the remote cache.
+    /// Servers which do not implement the bulk lookup are sent a `HEAD` request for each hash.
+    #[napi]
+    pub async fn query_many(&self, hashes: Vec<String>) -> napi::Result<Vec<String>> {
+        if hashes.is_empty() || self.policy.is_degraded() {
+            return Ok(vec![]);
+        }
+        if !self.bulk_query_unsupported.load(Ordering::Relaxed) {
+            if let Some(found) = self.bulk_query(&hashes).await? {
+                return Ok(found);
+            }
+            trace!("Remote cache does not support bulk lookups, falling back to HEAD requests");
+            self.bulk_query_unsupported.store(true, Ordering::Relaxed);
+        }
+
+        let found = stream::iter(hashes)
+            .map(|hash| async move {
+                let exists = self.exists(&hash).await?;
+                Ok::<_, HttpRemoteCacheErrors>(exists.then_some(hash))
+            })
+            .buffered(DEFAULT_CONCURRENCY as usize)
+            .collect::<Vec<_>>()
+            .await;
+        Ok(found
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// Downloads the hashes which are stored in the remote cache into `cache_directory`,
+    /// `concurrency` at a time.
+    /// The results should be passed to `NxCache.applyRemoteCacheResults` to add them to the local cache.
+    /// Downloads which fail are left out, they will be retried when the task is retrieved.
+    #[napi]
+    pub async fn prefetch(
+        &self,
+        hashes: Vec<String>,
         cache_directory: String,
-        hash: String,
-    ) -> anyhow::Result<CachedResult> {
-        let content = response.bytes().await.unwrap();
-        trace!("Downloaded {} bytes from remote cache", content.len());
-        let tar = flate2::read::GzDecoder::new(content.as_ref());
-        let mut archive = Archive::new(tar);
-        let entries = archive
-            .entries() // Get the entries in the archive
-            .map_err(|_| anyhow::anyhow!("Failed to read entries from tarball"))?;
-
-        let mut code: Option<i16> = None;
-        let mut terminal_output: Option<String> = None;
-        let mut size: i64 = 0;
-
-        let output_dir = Path::new(&cache_directory).join(&hash);
-
-        // Extract the archive to the specified cache directory
-        for entry in entries {
-            let mut entry =
-                entry.map_err(|_| anyhow::anyhow!("Failed to read entry from tarball"))?;
-
-            let entry_path = entry
-                .path()
-                .map(|p| p.to_string_lossy().into_owned())
-                .unwrap();
-
-            if entry_path == "code" {
-                let code_file_bytes = entry.bytes().collect::<Result<Vec<u8>, _>>()?;
-                code = Some(i16::from_be_bytes([code_file_bytes[0], code_file_bytes[1]]));
-                trace!("Retrieved exit code from cache: {}", code.unwrap());
-            } else if entry_path == "terminalOutput" {
-                let terminal_output_bytes = entry.bytes().collect::<Result<Vec<u8>, _>>()?;
-                let terminal_output_size = terminal_output_bytes.len();
-
-                terminal_output = Some(String::from_utf8(terminal_output_bytes)?);
-                size += terminal_output_size as i64;
-
-                trace!(
-                    "Retrieved terminal output from cache: {} bytes",
-                    terminal_output_size
-                );
-            } else {
-                let path_on_disk = output_dir.join(entry_path);
-                trace!("Extracting entry to {}", path_on_disk.display());
-                fs::create_dir_all(path_on_disk.parent().expect("This will have a parent, we just joined it above so there is at least one dir."))?;
-                // Ensure the directory exists before extracting
-                match entry.unpack(&path_on_disk) {
+        concurrency: Option<u32>,
+    ) -> napi::Result<Vec<PrefetchedCacheResult>> {
+        let found = self.query_many(hashes).await?;
+        trace!("Prefetching {} remote cache entries", found.len());
+
+        let cache_directory = &cache_directory;
+        let results = stream::iter(found)
+            .map(|hash| async move {
+                match self.retrieve(hash.clone(), cache_directory.clone()).await {
+                    Ok(result) => result.map(|result| PrefetchedCacheResult { hash, result }),
                     Err(e) => {
-                        return Err(anyhow::anyhow!("Failed to unpack entry: {}", e));
+                        trace!("Failed to prefetch {}: {}", hash, e);
+                        None
                     }
-                    Ok(f) => match f {
-                        tar::Unpacked::File(f) => size += f.metadata()?.len() as i64,
-                        _ => (),
-                    },
                 }
+            })
+            .buffer_unordered(concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1) as usize)
+            .filter_map(|result| async move { result })
+            .collect()
+            .await;
+        Ok(results)
+    }
+
+    /// Looks up all of the hashes with a single request.
+    /// Returns `None` when the server does not implement the endpoint.
+    async fn bulk_query(
+        &self,
+        hashes: &[String],
+    ) -> Result<Option<Vec<String>>, HttpRemoteCacheErrors> {
+        let url = format!("{}/v1/cache/query", self.url);
+        let body = serde_json::to_vec(&CacheQuery {
+            hashes: hashes.to_vec(),
+        })
+        .expect("Hashes can be serialized");
+        let response = self
+            .policy
+            .send(|| {
+                self.client
+                    .post(&url)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(body.clone())
+                    .send()
+            })
+            .await?;
+        trace!("HTTP response status: {}", response.status());
+
+        match response.status() {
+            StatusCode::OK => {
+                let body = response
+                    .bytes()
+                    .await
+                    .map_err(|e| HttpRemoteCacheErrors::RequestError(report_request_error(&e)))?;
+                let query: CacheQuery = serde_json::from_slice(&body).map_err(|e| {
+                    HttpRemoteCacheErrors::Misconfigured(format!(
+                        "Bulk lookups should respond with the hashes that were found: {}",
+                        e
+                    ))
+                })?;
+                Ok(Some(query.hashes))
             }
+            StatusCode::NOT_FOUND
+            | StatusCode::METHOD_NOT_ALLOWED
+            | StatusCode::NOT_IMPLEMENTED => Ok(None),
+            _ => Err(convert_response_to_error(response).await),
         }
+    }
 
-        trace!("Extracted tarball to {}", output_dir.display());
+    async fn exists(&self, hash: &str) -> Result<bool, HttpRemoteCacheErrors> {
+        let url = format!("{}/v1/cache/{}", self.url, hash);
+        let response = self.policy.send(|| self.client.head(&url).send()).await?;
+        match response.status() {
+            StatusCode::OK => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(convert_response_to_error(response).await),
+        }
+    }
+
+    /// Builds the tarball on a blocking thread while it is being uploaded,
+    /// so that memory usage does not depend on the size of the artifacts
+    fn archive_body(
+        &self,
+        hash: &str,
+        cache_directory: &str,
+        terminal_output: &str,
+        code: u32,
+    ) -> (Body, JoinHandle<anyhow::Result<()>>) {
+        let (sender, receiver) = mpsc::channel::<io::Result<Vec<u8>>>(MAX_BUFFERED_CHUNKS);
+        let codec = self.codec;
+        let signing_key = self.signing_key.clone();
+        let hash = hash.to_string();
+        let cache_directory = cache_directory.to_string();
+        let terminal_output = terminal_output.to_string();
+        let archive_task = tokio::task::spawn_blocking(move || {
+            let error_sender = sender.clone();
+            let result = write_archive(
+                ChannelWriter::new(sender),
+                codec,
+                signing_key.as_ref(),
+                Path::new(&cache_directory),
+                &hash,
+                &terminal_output,
+                code,
+            );
+            if let Err(e) = &result {
+                // Abort the upload, it would otherwise send an incomplete tarball
+                let _ = error_sender.blocking_send(Err(io::Error::other(e.to_string())));
+            }
+            result
+        });
+
+        let body = Body::wrap_stream(stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|chunk| (chunk, receiver))
+        }));
+        (body, archive_task)
+    }
+
+    async fn download_and_extract_from_result(
+        response: reqwest::Response,
+        cache_directory: String,
+        hash: String,
+        signing_key: Option<SigningKey>,
+    ) -> anyhow::Result<CachedResult> {
+        // Entries stored before compression was configurable do not name their encoding
+        let encoding = response
+            .headers()
+            .get(CACHE_ENCODING_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        trace!("Remote cache artifact encoding: {:?}", encoding);
+
+        // Stream the response through the decoder instead of buffering the whole tarball
+        let body = StreamReader::new(response.bytes_stream().map(|r| r.map_err(io::Error::other)));
+        let reader = SyncIoBridge::new(body);
+
+        tokio::task::spawn_blocking(move || {
+            extract_archive(
+                reader,
+                encoding.as_deref(),
+                signing_key.as_ref(),
+                &cache_directory,
+                &hash,
+            )
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to extract tarball: {}", e))?
+    }
+}
+/// Whether building the tarball failed because the upload it was streamed into was closed
+fn is_closed_upload(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+    })
+}
+
+/// Sends everything written to it through a bounded channel in chunks of [`CHUNK_SIZE`].
+/// Writes block while the channel is full, which keeps the tarball from being built faster than it is uploaded.
+struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Vec<u8>>>,
+    buffer: Vec<u8>,
+}
 
-        Ok(CachedResult {
-            terminal_output,
-            code: code.expect("Exit code not found in cache"),
-            outputs_path: output_dir.to_string_lossy().into_owned(),
-            size: Some(size),
+impl ChannelWriter {
+    fn new(sender: mpsc::Sender<io::Result<Vec<u8>>>) -> Self {
+        Self {
+            sender,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        }
+    }
+
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE));
+        self.sender.blocking_send(Ok(chunk)).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "The upload was closed before the tarball was finished",
+            )
         })
     }
 }
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+    use std::fs;
+
+    #[test]
+    fn should_tell_closed_uploads_from_archive_failures() {
+        let temp = TempDir::new().unwrap();
+        temp.child("cache/123/large.bin")
+            .write_binary(&vec![0; CHUNK_SIZE * 4])
+            .unwrap();
+        let archive = |hash: &str| {
+            let (sender, receiver) = mpsc::channel(1);
+            // The upload failed before anything was sent
+            drop(receiver);
+            write_archive(
+                ChannelWriter::new(sender),
+                Codec::default(),
+                None,
+                &temp.join("cache"),
+                hash,
+                "",
+                0,
+            )
+            .unwrap_err()
+        };
+
+        assert!(is_closed_upload(&archive("123")));
+        assert!(!is_closed_upload(&archive("missing")));
+    }
+
+    #[test]
+    fn should_stream_archives_in_bounded_chunks() {
+        let temp = TempDir::new().unwrap();
+        let contents = (0..CHUNK_SIZE * 4)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        temp.child("cache/123/large.bin")
+            .write_binary(&contents)
+            .unwrap();
+
+        let (sender, mut receiver) = mpsc::channel(1);
+        let cache_directory = temp.join("cache");
+        let writer = std::thread::spawn(move || {
+            write_archive(
+                ChannelWriter::new(sender),
+                Codec::default(),
+                None,
+                &cache_directory,
+                "123",
+                "",
+                0,
+            )
+        });
+
+        let mut tarball = vec![];
+        while let Some(chunk) = receiver.blocking_recv() {
+            let chunk = chunk.unwrap();
+            assert!(chunk.len() < CHUNK_SIZE * 2);
+            tarball.extend(chunk);
+        }
+        writer.join().unwrap().unwrap();
+
+        extract_archive(
+            tarball.as_slice(),
+            None,
+            None,
+            &temp.join("extracted").to_string_lossy(),
+            "123",
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read(temp.join("extracted/123/large.bin")).unwrap(),
+            contents
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, Read};
+use std::path::{Component, Path};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::native::cache::errors::HttpRemoteCacheErrors;
+
+/// Tarball entry listing the content hashes of every other entry
+pub const MANIFEST_ENTRY: &str = "integrityManifest";
+/// Tarball entry holding the HMAC-SHA256 of the manifest
+pub const SIGNATURE_ENTRY: &str = "integritySignature";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// What a remote cache artifact contains, used to verify it before it is restored
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ArtifactManifest {
+    /// Task hash the artifact was stored for, so that a signed artifact cannot be replayed for another task
+    pub hash: String,
+    pub entries: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ManifestEntry {
+    /// SHA-256 of the contents of a file
+    File(String),
+    /// Target of a symlink
+    Symlink(String),
+}
+
+impl ArtifactManifest {
+    pub fn new(hash: impl Into<String>) -> Self {
+        Self {
+            hash: hash.into(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_file(&mut self, path: impl Into<String>, contents: &[u8]) {
+        self.entries
+            .insert(path.into(), ManifestEntry::File(sha256(contents)));
+    }
+
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HttpRemoteCacheErrors> {
+        serde_json::from_slice(bytes).map_err(|e| {
+            HttpRemoteCacheErrors::IntegrityError(format!("Invalid artifact manifest: {}", e))
+        })
+    }
+
+    /// Checks that the entries that were extracted are exactly the ones in the manifest
+    pub fn verify(&self, extracted: &ArtifactManifest) -> Result<(), HttpRemoteCacheErrors> {
+        if self.hash != extracted.hash {
+            return Err(HttpRemoteCacheErrors::IntegrityError(format!(
+                "The artifact was stored for {} instead of {}",
+                self.hash, extracted.hash
+            )));
+        }
+        for (path, entry) in &extracted.entries {
+            match self.entries.get(path) {
+                None => {
+                    return Err(HttpRemoteCacheErrors::IntegrityError(format!(
+                        "{} is not listed in the artifact manifest",
+                        path
+                    )));
+                }
+                Some(expected) if expected != entry => {
+                    return Err(HttpRemoteCacheErrors::IntegrityError(format!(
+                        "{} does not match the artifact manifest",
+                        path
+                    )));
+                }
+                _ => {}
+            }
+        }
+        if let Some(path) = self
+            .entries
+            .keys()
+            .find(|path| !extracted.entries.contains_key(*path))
+        {
+            return Err(HttpRemoteCacheErrors::IntegrityError(format!(
+                "{} is missing from the artifact",
+                path
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Shared key used to sign and verify artifact manifests
+#[derive(Clone)]
+pub struct SigningKey(Vec<u8>);
+
+impl SigningKey {
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        SigningKey(key.into())
+    }
+
+    /// Reads the key from `NX_SELF_HOSTED_REMOTE_CACHE_SIGNING_KEY`
+    pub fn from_env() -> Option<Self> {
+        env::var("NX_SELF_HOSTED_REMOTE_CACHE_SIGNING_KEY")
+            .ok()
+            .filter(|key| !key.is_empty())
+            .map(SigningKey::new)
+    }
+
+    pub fn sign(&self, manifest: &[u8]) -> String {
+        to_hex(&self.mac(manifest).finalize().into_bytes())
+    }
+
+    pub fn verify(&self, manifest: &[u8], signature: &str) -> Result<(), HttpRemoteCacheErrors> {
+        from_hex(signature.trim())
+            .and_then(|signature| self.mac(manifest).verify_slice(&signature).ok())
+            .ok_or_else(|| {
+                HttpRemoteCacheErrors::IntegrityError(
+                    "The artifact manifest signature is invalid".to_string(),
+                )
+            })
+    }
+
+    fn mac(&self, manifest: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        mac.update(manifest);
+        mac
+    }
+}
+
+/// Hashes everything that is read through it
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    pub fn finish(self) -> String {
+        to_hex(&self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+pub fn sha256(contents: &[u8]) -> String {
+    to_hex(&Sha256::digest(contents))
+}
+
+/// Rejects entry paths that would be extracted outside of the task directory
+pub fn validate_entry_path(path: &Path) -> Result<(), HttpRemoteCacheErrors> {
+    let escapes = path.components().any(|c| {
+        matches!(
+            c,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+        )
+    });
+    if escapes || path.as_os_str().is_empty() {
+        return Err(HttpRemoteCacheErrors::IntegrityError(format!(
+            "Refusing to extract {} outside of the cache directory",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_verify_signatures() {
+        let key = SigningKey::new("secret");
+        let signature = key.sign(b"manifest");
+
+        assert!(key.verify(b"manifest", &signature).is_ok());
+        assert!(key.verify(b"tampered", &signature).is_err());
+        assert!(
+            SigningKey::new("other")
+                .verify(b"manifest", &signature)
+                .is_err()
+        );
+        assert!(key.verify(b"manifest", "not hex").is_err());
+    }
+
+    #[test]
+    fn should_reject_paths_outside_of_the_task_directory() {
+        assert!(validate_entry_path(Path::new("dist/file.txt")).is_ok());
+        assert!(validate_entry_path(Path::new("./dist/file.txt")).is_ok());
+        assert!(validate_entry_path(Path::new("../file.txt")).is_err());
+        assert!(validate_entry_path(Path::new("dist/../../file.txt")).is_err());
+        assert!(validate_entry_path(Path::new("/etc/passwd")).is_err());
+    }
+
+    #[test]
+    fn should_compare_extracted_entries_with_the_manifest() {
+        let mut manifest = ArtifactManifest::new("123");
+        manifest.add_file("a.txt", b"a");
+        manifest.add_file("b.txt", b"b");
+
+        let mut extracted = ArtifactManifest::new("123");
+        extracted.add_file("a.txt", b"a");
+        assert!(manifest.verify(&extracted).is_err());
+
+        extracted.add_file("b.txt", b"b");
+        assert!(manifest.verify(&extracted).is_ok());
+
+        extracted.add_file("b.txt", b"poisoned");
+        assert!(manifest.verify(&extracted).is_err());
+
+        extracted.add_file("b.txt", b"b");
+        extracted.add_file("c.txt", b"c");
+        assert!(manifest.verify(&extracted).is_err());
+
+        manifest.add_file("c.txt", b"c");
+        assert!(manifest.verify(&extracted).is_ok());
+        extracted.hash = "456".into();
+        assert!(manifest.verify(&extracted).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use rusqlite::types::Value;
+use rusqlite::{Row, params_from_iter};
+
+use crate::native::db::connection::NxDbConnection;
+
+/// A cache entry together with the task it was stored for
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheEntry {
+    pub hash: String,
+    pub code: i16,
+    /// Size of the outputs and terminal output, including outputs which are shared with other entries
+    pub size: i64,
+    /// How many times the entry was read from the cache
+    pub hit_count: i64,
+    /// Milliseconds since the epoch
+    pub created_at: i64,
+    /// Milliseconds since the epoch
+    pub accessed_at: i64,
+    /// Missing when the task details were not recorded
+    pub project: Option<String>,
+    pub target: Option<String>,
+    pub configuration: Option<String>,
+}
+
+#[napi(string_enum)]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum CacheEntrySort {
+    #[default]
+    #[allow(non_camel_case_types)]
+    accessedAt,
+    #[allow(non_camel_case_types)]
+    createdAt,
+    #[allow(non_camel_case_types)]
+    hitCount,
+    #[allow(non_camel_case_types)]
+    size,
+}
+
+/// Narrows down the entries listed from the cache. Every field is optional.
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct CacheEntryFilter {
+    pub project: Option<String>,
+    pub target: Option<String>,
+    pub configuration: Option<String>,
+    /// Only list entries which have not been accessed since this time, in milliseconds since the epoch
+    pub accessed_before: Option<i64>,
+    /// Entries are listed in descending order, defaults to the most recently accessed first
+    pub sort_by: Option<CacheEntrySort>,
+    pub limit: Option<u32>,
+}
+
+/// Totals over the cache entries of a project, or of a target when `target` is set
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheStats {
+    pub project: Option<String>,
+    pub target: Option<String>,
+    pub entries: i64,
+    pub size: i64,
+    pub hit_count: i64,
+    /// Milliseconds since the epoch
+    pub last_accessed_at: i64,
+}
+
+const ENTRY_COLUMNS: &str = "cache_outputs.hash,
+    cache_outputs.code,
+    cache_outputs.size,
+    cache_outputs.hit_count,
+    CAST(strftime('%s', cache_outputs.created_at) AS INTEGER) * 1000,
+    CAST(strftime('%s', cache_outputs.accessed_at) AS INTEGER) * 1000,
+    task_details.project,
+    task_details.target,
+    task_details.configuration";
+
+pub fn list_entries(
+    db: &NxDbConnection,
+    filter: CacheEntryFilter,
+) -> anyhow::Result<Vec<CacheEntry>> {
+    let mut conditions = vec![];
+    let mut values: Vec<Value> = vec![];
+    let mut condition = |column: &str, operator: &str, value: Value| {
+        values.push(value);
+        conditions.push(format!("{} {} ?{}", column, operator, values.len()));
+    };
+    if let Some(project) = filter.project {
+        condition("task_details.project", "=", project.into());
+    }
+    if let Some(target) = filter.target {
+        condition("task_details.target", "=", target.into());
+    }
+    if let Some(configuration) = filter.configuration {
+        condition("task_details.configuration", "=", configuration.into());
+    }
+    if let Some(accessed_before) = filter.accessed_before {
+        condition(
+            "CAST(strftime('%s', cache_outputs.accessed_at) AS INTEGER) * 1000",
+            "<",
+            accessed_before.into(),
+        );
+    }
+
+    let order_by = match filter.sort_by.unwrap_or_default() {
+        CacheEntrySort::accessedAt => "cache_outputs.accessed_at",
+        CacheEntrySort::createdAt => "cache_outputs.created_at",
+        CacheEntrySort::hitCount => "cache_outputs.hit_count",
+        CacheEntrySort::size => "cache_outputs.size",
+    };
+    let query = format!(
+        "SELECT {} FROM cache_outputs {} {} ORDER BY {} DESC, cache_outputs.hash {}",
+        ENTRY_COLUMNS,
+        task_details_join(db)?,
+        if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        },
+        order_by,
+        filter
+            .limit
+            .map(|limit| format!("LIMIT {}", limit))
+            .unwrap_or_default()
+    );
+
+    db.prepare(&query)?
+        .query_map(params_from_iter(values), entry_from_row)?
+        .map(|r| r.map_err(anyhow::Error::from))
+        .collect()
+}
+
+/// Groups the cache entries by project, and by target as well with `by_target`.
+/// The largest groups come first.
+pub fn stats(db: &NxDbConnection, by_target: bool) -> anyhow::Result<Vec<CacheStats>> {
+    let (target_column, group_by) = if by_target {
+        (
+            "task_details.target",
+            "task_details.project, task_details.target",
+        )
+    } else {
+        ("NULL", "task_details.project")
+    };
+    let query = format!(
+        "SELECT task_details.project,
+            {},
+            COUNT(*),
+            SUM(cache_outputs.size),
+            SUM(cache_outputs.hit_count),
+            CAST(strftime('%s', MAX(cache_outputs.accessed_at)) AS INTEGER) * 1000
+        FROM cache_outputs {}
+        GROUP BY {}
+        ORDER BY SUM(cache_outputs.size) DESC, {}",
+        target_column,
+        task_details_join(db)?,
+        group_by,
+        group_by
+    );
+
+    db.prepare(&query)?
+        .query_map([], |row| {
+            Ok(CacheStats {
+                project: row.get(0)?,
+                target: row.get(1)?,
+                entries: row.get(2)?,
+                size: row.get(3)?,
+                hit_count: row.get(4)?,
+                last_accessed_at: row.get(5)?,
+            })
+        })?
+        .map(|r| r.map_err(anyhow::Error::from))
+        .collect()
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<CacheEntry> {
+    Ok(CacheEntry {
+        hash: row.get(0)?,
+        code: row.get(1)?,
+        size: row.get(2)?,
+        hit_count: row.get(3)?,
+        created_at: row.get(4)?,
+        accessed_at: row.get(5)?,
+        project: row.get(6)?,
+        target: row.get(7)?,
+        configuration: row.get(8)?,
+    })
+}
+
+/// The task details are recorded by `TaskDetails`, so the table does not exist when the cache is used without it
+fn task_details_join(db: &NxDbConnection) -> anyhow::Result<&'static str> {
+    let has_task_details = db
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'task_details')",
+            [],
+            |row| row.get::<_, bool>(0),
+        )?
+        .unwrap_or(false);
+    Ok(if has_task_details {
+        "LEFT JOIN task_details ON task_details.hash = cache_outputs.hash"
+    } else {
+        "LEFT JOIN (SELECT NULL AS hash, NULL AS project, NULL AS target, NULL AS configuration)
+            AS task_details ON task_details.hash = cache_outputs.hash"
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn db(with_task_details: bool) -> NxDbConnection {
+        let db = NxDbConnection::new(Connection::open_in_memory().unwrap());
+        db.execute_batch(
+            "CREATE TABLE cache_outputs (
+                hash TEXT PRIMARY KEY NOT NULL,
+                code INTEGER NOT NULL,
+                size INTEGER NOT NULL,
+                terminal_output_size INTEGER NOT NULL DEFAULT 0,
+                hit_count INTEGER NOT NULL DEFAULT 0,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                accessed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );
+            INSERT INTO cache_outputs (hash, code, size, hit_count, accessed_at) VALUES
+                ('1', 0, 100, 2, '2024-01-01 00:00:00'),
+                ('2', 0, 300, 0, '2024-01-02 00:00:00'),
+                ('3', 1, 50, 5, '2024-01-03 00:00:00');",
+        )
+        .unwrap();
+        if with_task_details {
+            db.execute_batch(
+                "CREATE TABLE task_details (
+                    hash TEXT PRIMARY KEY NOT NULL,
+                    project TEXT NOT NULL,
+                    target TEXT NOT NULL,
+                    configuration TEXT
+                );
+                INSERT INTO task_details (hash, project, target, configuration) VALUES
+                    ('1', 'app', 'build', 'production'),
+                    ('2', 'app', 'test', NULL),
+                    ('3', 'lib', 'build', NULL);",
+            )
+            .unwrap();
+        }
+        db
+    }
+
+    fn hashes(entries: Vec<CacheEntry>) -> Vec<String> {
+        entries.into_iter().map(|e| e.hash).collect()
+    }
+
+    #[test]
+    fn should_list_entries_with_their_task_details() {
+        let db = db(true);
+
+        let entries = list_entries(&db, CacheEntryFilter::default()).unwrap();
+        assert_eq!(hashes(entries.clone()), vec!["3", "2", "1"]);
+        assert_eq!(
+            entries[2],
+            CacheEntry {
+                hash: "1".into(),
+                code: 0,
+                size: 100,
+                hit_count: 2,
+                created_at: entries[2].created_at,
+                accessed_at: 1704067200000,
+                project: Some("app".into()),
+                target: Some("build".into()),
+                configuration: Some("production".into()),
+            }
+        );
+
+        let filter = CacheEntryFilter {
+            project: Some("app".into()),
+            sort_by: Some(CacheEntrySort::size),
+            ..Default::default()
+        };
+        assert_eq!(hashes(list_entries(&db, filter).unwrap()), vec!["2", "1"]);
+
+        let filter = CacheEntryFilter {
+            accessed_before: Some(1704153600000),
+            ..Default::default()
+        };
+        assert_eq!(hashes(list_entries(&db, filter).unwrap()), vec!["1"]);
+
+        let filter = CacheEntryFilter {
+            sort_by: Some(CacheEntrySort::hitCount),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(hashes(list_entries(&db, filter).unwrap()), vec!["3"]);
+    }
+
+    #[test]
+    fn should_list_entries_without_task_details() {
+        let db = db(false);
+
+        let entries = list_entries(&db, CacheEntryFilter::default()).unwrap();
+        assert_eq!(hashes(entries.clone()), vec!["3", "2", "1"]);
+        assert!(entries.iter().all(|e| e.project.is_none()));
+
+        let filter = CacheEntryFilter {
+            project: Some("app".into()),
+            ..Default::default()
+        };
+        assert!(list_entries(&db, filter).unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_group_stats_by_project_and_target() {
+        let db = db(true);
+
+        let by_project = stats(&db, false).unwrap();
+        assert_eq!(
+            by_project,
+            vec![
+                CacheStats {
+                    project: Some("app".into()),
+                    target: None,
+                    entries: 2,
+                    size: 400,
+                    hit_count: 2,
+                    last_accessed_at: 1704153600000,
+                },
+                CacheStats {
+                    project: Some("lib".into()),
+                    target: None,
+                    entries: 1,
+                    size: 50,
+                    hit_count: 5,
+                    last_accessed_at: 1704240000000,
+                },
+            ]
+        );
+
+        let by_target = stats(&db, true).unwrap();
+        assert_eq!(
+            by_target
+                .iter()
+                .map(|s| (s.project.as_deref(), s.target.as_deref(), s.size))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some("app"), Some("test"), 300),
+                (Some("app"), Some("build"), 100),
+                (Some("lib"), Some("build"), 50),
+            ]
+        );
+    }
+}
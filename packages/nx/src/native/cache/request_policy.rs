@@ -0,0 +1,298 @@
+use std::env;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+
+use reqwest::{Response, StatusCode};
+use tracing::{debug, trace};
+
+use crate::native::cache::errors::{HttpRemoteCacheErrors, report_request_error};
+
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(60);
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(100);
+
+/// Timeouts, retries and the circuit breaker used for requests to the remote cache.
+///
+/// Requests that fail to connect, time out or receive a 5xx response are retried with a jittered exponential backoff.
+/// Once enough requests in a row have failed after all of their retries, or could not be sent at all, the remote cache is
+/// considered degraded and is not used for the rest of the process. Unexpected client errors, like 401s,
+/// neither count as failures nor reset the count.
+pub struct RequestPolicy {
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    retries: u32,
+    failure_threshold: u32,
+    base_delay: Duration,
+    consecutive_failures: AtomicU32,
+    degraded: AtomicBool,
+}
+
+impl RequestPolicy {
+    pub fn new(
+        connect_timeout: Duration,
+        read_timeout: Duration,
+        retries: u32,
+        failure_threshold: u32,
+    ) -> Self {
+        Self {
+            connect_timeout,
+            read_timeout,
+            retries,
+            failure_threshold: failure_threshold.max(1),
+            base_delay: DEFAULT_BASE_DELAY,
+            consecutive_failures: AtomicU32::new(0),
+            degraded: AtomicBool::new(false),
+        }
+    }
+
+    /// Reads the policy from `NX_SELF_HOSTED_REMOTE_CACHE_CONNECT_TIMEOUT`, `NX_SELF_HOSTED_REMOTE_CACHE_READ_TIMEOUT`
+    /// (both in milliseconds), `NX_SELF_HOSTED_REMOTE_CACHE_RETRIES` and `NX_SELF_HOSTED_REMOTE_CACHE_FAILURE_THRESHOLD`
+    pub fn from_env() -> Result<Self, HttpRemoteCacheErrors> {
+        let millis = |name, default: Duration| {
+            read_env(name, default.as_millis() as u64).map(Duration::from_millis)
+        };
+        Ok(Self::new(
+            millis(
+                "NX_SELF_HOSTED_REMOTE_CACHE_CONNECT_TIMEOUT",
+                DEFAULT_CONNECT_TIMEOUT,
+            )?,
+            millis(
+                "NX_SELF_HOSTED_REMOTE_CACHE_READ_TIMEOUT",
+                DEFAULT_READ_TIMEOUT,
+            )?,
+            read_env("NX_SELF_HOSTED_REMOTE_CACHE_RETRIES", DEFAULT_RETRIES)?,
+            read_env(
+                "NX_SELF_HOSTED_REMOTE_CACHE_FAILURE_THRESHOLD",
+                DEFAULT_FAILURE_THRESHOLD,
+            )?,
+        ))
+    }
+
+    /// Whether the circuit breaker has tripped, after which requests should not be sent anymore
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    /// Sends the request created by `send`, retrying it when the server could not be reached or failed.
+    /// Responses with other statuses are returned as they are.
+    pub async fn send<F, Fut>(&self, mut send: F) -> Result<Response, HttpRemoteCacheErrors>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = reqwest::Result<Response>>,
+    {
+        let mut attempts = 0;
+        // Generate a random number between 2 and 4 to raise to the power of attempts
+        let base_exponent = rand::random::<f64>() * 2.0 + 2.0;
+
+        loop {
+            attempts += 1;
+            let result = send().await;
+            let retryable = match &result {
+                Ok(response) if is_retryable_status(response.status()) => true,
+                Ok(response) => {
+                    // Client errors like 401s do not show that the remote cache is healthy
+                    if !is_unexpected_client_error(response.status()) {
+                        self.consecutive_failures.store(0, Ordering::Relaxed);
+                    }
+                    false
+                }
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            if !retryable {
+                return result.map_err(|e| {
+                    // Requests which could not be sent for other reasons would fail the same way again,
+                    // they are not retried but still count towards degrading the remote cache
+                    let failure = report_request_error(&e);
+                    match self.record_failure(&failure) {
+                        Ok(()) => HttpRemoteCacheErrors::RequestError(failure),
+                        Err(degraded) => degraded,
+                    }
+                });
+            }
+
+            if attempts > self.retries {
+                let failure = match &result {
+                    Ok(response) => format!("Unexpected response status: {}", response.status()),
+                    Err(e) => report_request_error(e),
+                };
+                self.record_failure(&failure)?;
+                return result.map_err(|_| HttpRemoteCacheErrors::RequestError(failure));
+            }
+
+            let delay = self.base_delay.mul_f64(base_exponent.powi(attempts as i32));
+            trace!(
+                "Remote cache request failed (attempt {}), retrying in {:?}",
+                attempts, delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Counts a request that failed after all of its retries and trips the circuit breaker when there were too many
+    fn record_failure(&self, failure: &str) -> Result<(), HttpRemoteCacheErrors> {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures < self.failure_threshold || self.degraded.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+        debug!("Remote cache degraded after {} failed requests", failures);
+        Err(HttpRemoteCacheErrors::Degraded(format!(
+            "{} requests in a row failed. {}",
+            failures, failure
+        )))
+    }
+}
+
+/// Client errors other than the statuses the remote cache protocol uses to answer requests
+fn is_unexpected_client_error(status: StatusCode) -> bool {
+    status.is_client_error() && status != StatusCode::NOT_FOUND && status != StatusCode::CONFLICT
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    // Servers respond with 501 when they do not implement an optional endpoint
+    status.is_server_error() && status != StatusCode::NOT_IMPLEMENTED
+}
+
+fn read_env<T: FromStr>(name: &str, default: T) -> Result<T, HttpRemoteCacheErrors> {
+    match env::var(name) {
+        Ok(value) => value.trim().parse().map_err(|_| {
+            HttpRemoteCacheErrors::Misconfigured(format!(
+                "{} should be a positive number, got {}",
+                name, value
+            ))
+        }),
+        Err(_) => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    /// Responds to every request with `status` and counts the requests
+    fn serve(status: &'static str) -> (String, Arc<AtomicU32>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                counter.fetch_add(1, Ordering::Relaxed);
+                let _ = stream.write_all(
+                    format!(
+                        "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                        status
+                    )
+                    .as_bytes(),
+                );
+            }
+        });
+        (url, requests)
+    }
+
+    fn policy(retries: u32, failure_threshold: u32) -> RequestPolicy {
+        RequestPolicy {
+            base_delay: Duration::from_millis(1),
+            ..RequestPolicy::new(
+                DEFAULT_CONNECT_TIMEOUT,
+                DEFAULT_READ_TIMEOUT,
+                retries,
+                failure_threshold,
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn should_retry_server_errors() {
+        let (url, requests) = serve("503 Service Unavailable");
+        let client = reqwest::Client::new();
+        let policy = policy(2, 5);
+
+        let response = policy.send(|| client.get(&url).send()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(requests.load(Ordering::Relaxed), 3);
+        assert!(!policy.is_degraded());
+    }
+
+    #[tokio::test]
+    async fn should_not_retry_client_errors() {
+        let (url, requests) = serve("404 Not Found");
+        let client = reqwest::Client::new();
+        let policy = policy(2, 1);
+
+        let response = policy.send(|| client.get(&url).send()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+        assert!(!policy.is_degraded());
+    }
+
+    #[tokio::test]
+    async fn should_keep_counting_failures_across_client_errors() {
+        let unreachable = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let (unauthorized, _) = serve("401 Unauthorized");
+        let client = reqwest::Client::new();
+        let policy = policy(0, 2);
+
+        assert!(
+            policy
+                .send(|| client.get(&unreachable).send())
+                .await
+                .is_err()
+        );
+        let response = policy
+            .send(|| client.get(&unauthorized).send())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let failed = policy.send(|| client.get(&unreachable).send()).await;
+        assert!(matches!(failed, Err(HttpRemoteCacheErrors::Degraded(_))));
+    }
+
+    #[tokio::test]
+    async fn should_count_requests_which_cannot_be_sent() {
+        let client = reqwest::Client::new();
+        let policy = policy(2, 1);
+
+        let failed = policy.send(|| client.get("not a url").send()).await;
+
+        assert!(matches!(failed, Err(HttpRemoteCacheErrors::Degraded(_))));
+        assert!(policy.is_degraded());
+    }
+
+    #[tokio::test]
+    async fn should_degrade_after_consecutive_failures() {
+        // Nothing is listening on the port once the listener is dropped
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let client = reqwest::Client::new();
+        let policy = policy(1, 2);
+
+        let first = policy.send(|| client.get(&url).send()).await;
+        assert!(matches!(first, Err(HttpRemoteCacheErrors::RequestError(_))));
+        assert!(!policy.is_degraded());
+
+        let second = policy.send(|| client.get(&url).send()).await;
+        assert!(matches!(second, Err(HttpRemoteCacheErrors::Degraded(_))));
+        assert!(policy.is_degraded());
+    }
+}
@@ -1,23 +1,29 @@
+#[cfg(test)]
+mod glob_bench;
+#[cfg(test)]
+mod glob_conformance;
 mod glob_group;
+pub mod glob_lint;
+mod glob_matcher;
 mod glob_parser;
 pub mod glob_transform;
 
-use crate::native::glob::glob_transform::convert_glob;
+use crate::native::glob::glob_matcher::{GlobMatcher, with_path_chars};
 use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use std::fmt::Debug;
 use std::path::Path;
 use tracing::trace;
 
 pub struct NxGlobSetBuilder {
-    included_globs: GlobSetBuilder,
-    excluded_globs: GlobSetBuilder,
+    included_globs: Vec<String>,
+    excluded_globs: Vec<String>,
 }
 
 impl NxGlobSetBuilder {
     pub fn new<S: AsRef<str>>(globs: &[S]) -> anyhow::Result<Self> {
         let mut glob_set_builder = NxGlobSetBuilder {
-            included_globs: GlobSetBuilder::new(),
-            excluded_globs: GlobSetBuilder::new(),
+            included_globs: vec![],
+            excluded_globs: vec![],
         };
         let mut globs: Vec<&str> = globs.iter().map(|s| s.as_ref()).collect();
         globs.sort();
@@ -28,84 +34,120 @@ impl NxGlobSetBuilder {
     }
 
     pub fn add(&mut self, glob: &str) -> anyhow::Result<&mut NxGlobSetBuilder> {
-        let negated = glob.starts_with('!');
-        let glob_string = glob.strip_prefix('!').unwrap_or(glob).to_string();
+        // `!(a|b)` is a negated group, not a negated glob
+        let negated = glob.starts_with('!') && !glob.starts_with("!(");
+        let glob_string = if negated { &glob[1..] } else { glob };
 
+        // a trailing slash matches everything within the directory, but not the directory itself
         let glob_string = if glob_string.ends_with('/') {
-            format!("{}**", glob_string)
+            format!("{}**/*", glob_string)
         } else {
-            glob_string
+            glob_string.to_string()
         };
 
-        let glob = GlobBuilder::new(&glob_string)
-            .literal_separator(true)
-            .build()
-            .map_err(anyhow::Error::from)?;
-
         if negated {
-            self.excluded_globs.add(glob);
+            self.excluded_globs.push(glob_string);
         } else {
-            self.included_globs.add(glob);
+            self.included_globs.push(glob_string);
         }
 
         Ok(self)
     }
 
-    pub fn build(&self) -> anyhow::Result<NxGlobSet> {
+    pub fn build(self) -> anyhow::Result<NxGlobSet> {
         Ok(NxGlobSet {
-            excluded_globs: self.excluded_globs.build()?,
-            included_globs: self.included_globs.build()?,
+            excluded_globs: Globs::new(&self.excluded_globs)?,
+            included_globs: Globs::new(&self.included_globs)?,
         })
     }
 }
 
 #[derive(Debug)]
 pub struct NxGlobSet {
-    included_globs: GlobSet,
-    excluded_globs: GlobSet,
+    included_globs: Globs,
+    excluded_globs: Globs,
 }
 impl NxGlobSet {
     pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
-        if self.included_globs.is_empty() {
-            !self.excluded_globs.is_match(path.as_ref())
-        } else if self.excluded_globs.is_empty() {
-            self.included_globs.is_match(path.as_ref())
-        } else {
-            self.included_globs.is_match(path.as_ref())
-                && !self.excluded_globs.is_match(path.as_ref())
-        }
+        let path = path.as_ref();
+        (self.included_globs.is_empty() || self.included_globs.is_match(path))
+            && !self.excluded_globs.is_match(path)
     }
 }
 
-fn potential_glob_split(
-    glob: &str,
-) -> itertools::Either<std::str::Split<char>, std::iter::Once<&str>> {
-    use itertools::Either::*;
-    if glob.starts_with('{') && glob.ends_with('}') {
-        Left(glob.trim_matches('{').trim_end_matches('}').split(','))
-    } else {
-        Right(std::iter::once(glob))
-    }
+/// Plain globs are matched with `globset`, the others with a single automaton for all of them
+#[derive(Debug)]
+struct Globs {
+    plain: GlobSet,
+    extended: Option<GlobMatcher>,
 }
 
-pub(crate) fn build_glob_set<S: AsRef<str> + Debug>(globs: &[S]) -> anyhow::Result<NxGlobSet> {
-    let result = globs
-        .iter()
-        .flat_map(|s| potential_glob_split(s.as_ref()))
-        .map(|glob| {
-            if glob.contains('!') || glob.contains('|') || glob.contains('(') || glob.contains("{,")
-            {
-                convert_glob(glob)
-            } else {
-                Ok(vec![glob.to_string()])
-            }
+impl Globs {
+    fn new(globs: &[String]) -> anyhow::Result<Self> {
+        let (plain, extended): (Vec<&String>, Vec<&String>) =
+            globs.iter().partition(|glob| is_plain(glob));
+
+        let mut plain_globs = GlobSetBuilder::new();
+        for glob in plain {
+            plain_globs.add(GlobBuilder::new(glob).literal_separator(true).build()?);
+        }
+        let extended = if extended.is_empty() {
+            None
+        } else {
+            Some(GlobMatcher::any(&extended)?)
+        };
+        Ok(Self {
+            plain: plain_globs.build()?,
+            extended,
         })
-        .collect::<anyhow::Result<Vec<_>>>()?
-        .concat();
+    }
 
-    trace!(?globs, ?result, "converted globs");
+    fn is_empty(&self) -> bool {
+        self.plain.is_empty() && self.extended.is_none()
+    }
 
-    NxGlobSetBuilder::new(&result)?.build()
+    fn is_match(&self, path: &Path) -> bool {
+        self.plain.is_match(path)
+            || self.extended.as_ref().is_some_and(|extended| {
+                with_path_chars(&path.to_string_lossy(), |path| extended.is_match(path))
+            })
+    }
+}
+
+/// Whether `globset` matches the same paths as [`GlobMatcher`] for the glob, which is a lot faster.
+/// Extglobs, classes, escapes, ranges, nested or empty braces, `**` within a segment
+/// and `/**` at the end are left to [`GlobMatcher`].
+fn is_plain(glob: &str) -> bool {
+    if glob.contains(['(', ')', '|', '[', ']', '\\']) || glob.ends_with("/**") {
+        return false;
+    }
+    let mut brace_start = None;
+    for (index, c) in glob.char_indices() {
+        match (c, brace_start) {
+            ('{', None) => brace_start = Some(index + 1),
+            ('}', Some(start)) => {
+                let contents = &glob[start..index];
+                if !contents.contains(',')
+                    || contents.contains("..")
+                    || contents.split(',').any(str::is_empty)
+                {
+                    return false;
+                }
+                brace_start = None;
+            }
+            ('{' | '}', _) => return false,
+            _ => {}
+        }
+    }
+    brace_start.is_none()
+        && glob
+            .split('/')
+            .all(|segment| segment == "**" || !segment.contains("**"))
+}
+
+pub(crate) fn build_glob_set<S: AsRef<str> + Debug>(globs: &[S]) -> anyhow::Result<NxGlobSet> {
+    trace!(?globs, "building glob set");
+    NxGlobSetBuilder::new(globs)?.build()
 }
 
 pub(crate) fn contains_glob_pattern(value: &str) -> bool {
@@ -354,11 +396,54 @@ mod test {
         assert!(glob_set.is_match("pnpm-lock.yaml"));
 
         let glob_set =
-            build_glob_set(&["{packages/!(package-a)*/package.json,packages/*/package.json}"])
-                .unwrap();
+            build_glob_set(&["{packages/!(package-a)*/package.json,apps/*/package.json}"]).unwrap();
         assert!(glob_set.is_match("packages/package-b/package.json"));
         assert!(glob_set.is_match("packages/package-c/package.json"));
+        assert!(glob_set.is_match("apps/app-a/package.json"));
         assert!(!glob_set.is_match("packages/package-a/package.json"));
+
+        // a negated group only applies to its own alternative, like micromatch.
+        // Nx used to exclude files matching any negated group, see "Source Files" in docs/shared/reference/inputs.md
+        let glob_set =
+            build_glob_set(&["{packages/!(package-a)*/package.json,packages/*/package.json}"])
+                .unwrap();
+        assert!(glob_set.is_match("packages/package-b/package.json"));
+        assert!(glob_set.is_match("packages/package-a/package.json"));
+    }
+
+    #[test]
+    fn should_only_match_plain_globs_with_globset() {
+        assert!(is_plain("packages/*/package.json"));
+        assert!(is_plain("**/*.{ts,tsx}"));
+        assert!(is_plain("{packages,apps}/**/*"));
+        assert!(!is_plain("**/*.spec.ts{,.snap}"));
+        assert!(!is_plain("file{1..3}"));
+        assert!(!is_plain("{a,{b,c}}"));
+        assert!(!is_plain("{a}"));
+        assert!(!is_plain("a/**"));
+        assert!(!is_plain("a**/c"));
+        assert!(!is_plain("src/[abc].ts"));
+        assert!(!is_plain("dist/*.(js|ts)"));
+        assert!(!is_plain("packages/!(package-a)*"));
+    }
+
+    #[test]
+    fn should_combine_plain_and_extended_globs() {
+        let glob_set = build_glob_set(&[
+            "**/*.json",
+            "dist/*.(js|ts)",
+            "**/!(*.module).ts",
+            "!**/*.spec.ts",
+            "!dist/!(main).js",
+        ])
+        .unwrap();
+        assert!(glob_set.is_match("package.json"));
+        assert!(glob_set.is_match("dist/main.js"));
+        assert!(glob_set.is_match("src/app.ts"));
+        assert!(!glob_set.is_match("dist/other.js"));
+        assert!(!glob_set.is_match("src/app.module.ts"));
+        assert!(!glob_set.is_match("src/app.spec.ts"));
+        assert!(!glob_set.is_match("src/app.js"));
     }
 
     #[test]
@@ -0,0 +1,127 @@
+//! Compares matching workspace files with `NxGlobSet` to how Nx matched them before,
+//! by converting extglobs into several `globset` globs.
+//!
+//! The library only builds as a Node.js addon, so the benchmark runs as an ignored test:
+//! `cargo test --release glob_bench -- --ignored --nocapture`
+
+use super::NxGlobSetBuilder;
+use super::glob_transform::convert_glob;
+use criterion::Criterion;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::hint::black_box;
+
+const PLAIN_GLOBS: &[&str] = &[
+    "{projectRoot}/**/*",
+    "!{projectRoot}/**/*.spec.{ts,tsx}",
+    "!{projectRoot}/tsconfig.spec.json",
+    "!{projectRoot}/jest.config.ts",
+];
+
+const EXTGLOBS: &[&str] = &[
+    "{projectRoot}/**/*",
+    "!{projectRoot}/**/?(*.)+(spec|test).[jt]s?(x)?(.snap)",
+    "!{projectRoot}/**/!(*.module).css",
+    "!{projectRoot}/tsconfig.spec.json",
+];
+
+/// The files of a workspace with 100 projects
+fn workspace_files() -> Vec<String> {
+    let mut files = vec![];
+    for project in 0..100 {
+        for directory in ["src", "src/lib", "src/lib/components", "src/assets"] {
+            for file in 0..10 {
+                for extension in ["ts", "spec.ts", "tsx", "module.css", "css", "json"] {
+                    files.push(format!(
+                        "libs/project-{project}/{directory}/file-{file}.{extension}"
+                    ));
+                }
+            }
+        }
+        files.push(format!("libs/project-{project}/tsconfig.spec.json"));
+        files.push(format!("libs/project-{project}/jest.config.ts"));
+    }
+    files
+}
+
+fn project_globs(globs: &[&str]) -> Vec<String> {
+    globs
+        .iter()
+        .map(|glob| glob.replace("{projectRoot}", "libs/project-42"))
+        .collect()
+}
+
+/// Matches the way Nx did before globs were matched natively
+struct BaselineGlobSet {
+    included_globs: GlobSet,
+    excluded_globs: GlobSet,
+}
+
+impl BaselineGlobSet {
+    fn new(globs: &[String]) -> Self {
+        let converted = globs
+            .iter()
+            .flat_map(|glob| {
+                if glob.contains('!') || glob.contains('|') || glob.contains('(') {
+                    convert_glob(glob).unwrap()
+                } else {
+                    vec![glob.to_string()]
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut included_globs = GlobSetBuilder::new();
+        let mut excluded_globs = GlobSetBuilder::new();
+        for glob in converted {
+            let (builder, glob) = match glob.strip_prefix('!') {
+                Some(glob) => (&mut excluded_globs, glob.to_string()),
+                None => (&mut included_globs, glob),
+            };
+            builder.add(
+                GlobBuilder::new(&glob)
+                    .literal_separator(true)
+                    .build()
+                    .unwrap(),
+            );
+        }
+        Self {
+            included_globs: included_globs.build().unwrap(),
+            excluded_globs: excluded_globs.build().unwrap(),
+        }
+    }
+
+    fn is_match(&self, path: &str) -> bool {
+        self.included_globs.is_match(path) && !self.excluded_globs.is_match(path)
+    }
+}
+
+#[test]
+#[ignore]
+fn match_globs() {
+    let mut c = Criterion::default();
+    let files = workspace_files();
+    let mut group = c.benchmark_group("match workspace files");
+    for (name, globs) in [("plain globs", PLAIN_GLOBS), ("extglobs", EXTGLOBS)] {
+        let globs = project_globs(globs);
+
+        let glob_set = NxGlobSetBuilder::new(&globs).unwrap().build().unwrap();
+        let baseline = BaselineGlobSet::new(&globs);
+
+        group.bench_function(format!("{name}/NxGlobSet"), |b| {
+            b.iter(|| {
+                files
+                    .iter()
+                    .filter(|file| glob_set.is_match(black_box(file)))
+                    .count()
+            })
+        });
+        group.bench_function(format!("{name}/baseline"), |b| {
+            b.iter(|| {
+                files
+                    .iter()
+                    .filter(|file| baseline.is_match(black_box(file)))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+    c.final_summary();
+}
@@ -0,0 +1,200 @@
+//! Cases ported from the micromatch and picomatch test suites, matched with `{ dot: true }` like Nx does
+
+use super::Globs;
+use super::glob_matcher::{GlobMatcher, path_chars};
+use std::path::Path;
+
+/// Checks the automaton, and the glob set which matches plain globs with `globset` instead
+fn assert_glob(glob: &str, matching: &[&str], not_matching: &[&str]) {
+    let matcher = GlobMatcher::new(glob).unwrap();
+    let globs = Globs::new(&[glob.to_string()]).unwrap();
+    for path in matching {
+        assert!(
+            matcher.is_match(&path_chars(path)),
+            "{} should match {}",
+            glob,
+            path
+        );
+        assert!(
+            globs.is_match(Path::new(path)),
+            "{} should match {} in a glob set",
+            glob,
+            path
+        );
+    }
+    for path in not_matching {
+        assert!(
+            !matcher.is_match(&path_chars(path)),
+            "{} should not match {}",
+            glob,
+            path
+        );
+        assert!(
+            !globs.is_match(Path::new(path)),
+            "{} should not match {} in a glob set",
+            glob,
+            path
+        );
+    }
+}
+
+#[test]
+fn stars() {
+    assert_glob("*", &["a", "a.b", ".dotfile"], &["a/b"]);
+    assert_glob("a/*", &["a/b", "a/.b"], &["a", "a/b/c", "b/a"]);
+    assert_glob("*.js", &["a.js", ".js", "a.b.js"], &["a.jsx", "a/b.js"]);
+    assert_glob("a/*/c", &["a/b/c", "a/bb/c"], &["a/c", "a/b/b/c"]);
+    assert_glob("a*b*c", &["abc", "aXbYc", "abbc"], &["ab", "a/b/c"]);
+    assert_glob("*/*", &["a/b", "a/.b"], &["a", "a/b/c"]);
+}
+
+#[test]
+fn question_marks() {
+    assert_glob("a?c", &["abc", "a.c"], &["ac", "abbc", "a/c"]);
+    assert_glob("??", &["ab", ".a"], &["a", "abc", "a/"]);
+    assert_glob("a/?/c", &["a/b/c"], &["a/bb/c", "a//c"]);
+}
+
+#[test]
+fn globstars() {
+    assert_glob("**", &["a", "a/b/c", ".git/config"], &[]);
+    assert_glob("a/**", &["a", "a/b", "a/b/c", "a/.b"], &["ab", "b/a"]);
+    assert_glob(
+        "a/**/c",
+        &["a/c", "a/b/c", "a/b/d/c"],
+        &["a/bc", "ac", "a/b/cd"],
+    );
+    assert_glob("**/c", &["c", "a/c", "a/b/c", ".a/c"], &["ac", "a/bc"]);
+    assert_glob(
+        "**/*.js",
+        &["a.js", "a/b.js", "a/b/c.js"],
+        &["a.ts", "a/b.jsx"],
+    );
+    assert_glob("a/**/*", &["a/b", "a/b/c"], &["b/c"]);
+    // `**` is only a globstar on its own in a segment
+    assert_glob("a**/c", &["a/c", "ab/c"], &["a/b/c"]);
+    assert_glob("a/**b", &["a/b", "a/bb"], &["a/c/b"]);
+}
+
+#[test]
+fn character_classes() {
+    assert_glob("[abc].js", &["a.js", "c.js"], &["d.js", "ab.js"]);
+    assert_glob("[a-c]x", &["ax", "bx"], &["dx", "Bx"]);
+    assert_glob("[!a-c]x", &["dx", "zx"], &["ax", "cx"]);
+    assert_glob("[^a-c]x", &["dx"], &["bx"]);
+    assert_glob("a[xyz]b[0-9]", &["ayb5"], &["ab5", "ayb"]);
+    assert_glob("[[:digit:]].txt", &["1.txt"], &["a.txt", "12.txt"]);
+    assert_glob("[[:alpha:][:digit:]]", &["a", "Z", "1"], &["-", "_"]);
+    assert_glob("[[:upper:]]*", &["Abc"], &["abc"]);
+    assert_glob("[]a]", &["]", "a"], &["b"]);
+    assert_glob("[a\\]]", &["a", "]"], &["\\"]);
+    assert_glob("[a-]", &["a", "-"], &["b"]);
+    // classes never match separators
+    assert_glob("a[!b]c", &["axc"], &["abc", "a/c"]);
+    // unclosed classes are literal
+    assert_glob("a[", &["a["], &["a"]);
+}
+
+#[test]
+fn braces() {
+    assert_glob("a/{b,c}/d", &["a/b/d", "a/c/d"], &["a/e/d", "a/bc/d"]);
+    assert_glob("a/{b,c{d,e}}/f", &["a/b/f", "a/cd/f", "a/ce/f"], &["a/c/f"]);
+    assert_glob("{a,b/c}/d", &["a/d", "b/c/d"], &["b/d"]);
+    assert_glob("a{,b}c", &["ac", "abc"], &["abbc"]);
+    assert_glob(
+        "{a,b}*.{js,ts}",
+        &["a.js", "abc.ts", "b.ts"],
+        &["c.js", "a.md"],
+    );
+    assert_glob(
+        "{**/*.js,*.md}",
+        &["a.js", "a/b.js", "README.md"],
+        &["a/README.md"],
+    );
+    assert_glob(
+        "file{1..3}.txt",
+        &["file1.txt", "file3.txt"],
+        &["file4.txt", "file.txt"],
+    );
+    assert_glob("file{08..10}", &["file08", "file09", "file10"], &["file8"]);
+    assert_glob("{a..c}", &["a", "b", "c"], &["d"]);
+    assert_glob("{c..a}", &["b"], &["d"]);
+    // braces without alternatives are literal
+    assert_glob("a{b}c", &["a{b}c"], &["abc"]);
+    assert_glob("a{b", &["a{b"], &["ab"]);
+}
+
+#[test]
+fn extglobs() {
+    assert_glob("@(a|b)", &["a", "b"], &["ab", "c"]);
+    assert_glob("?(a|b)", &["a", "b"], &["ab", "c"]);
+    assert_glob("x?(a|b)y", &["xy", "xay", "xby"], &["xaby", "xcy"]);
+    assert_glob("*(a|b)", &["a", "abba"], &["abc"]);
+    assert_glob("x*(a|b)y", &["xy", "xababy"], &["xcy"]);
+    assert_glob("+(a|b)", &["a", "abab"], &["abc"]);
+    assert_glob("x+(a|b)y", &["xay", "xbay"], &["xy"]);
+    assert_glob(
+        "*(foo|bar).js",
+        &[".js", "foobar.js", "barfoo.js"],
+        &["baz.js"],
+    );
+    assert_glob(
+        "?(*.)spec.ts",
+        &["spec.ts", "a.spec.ts", "a.b.spec.ts"],
+        &["aspec.ts"],
+    );
+    assert_glob("+([0-9]).txt", &["1.txt", "123.txt"], &[".txt", "1a.txt"]);
+    assert_glob("@(a|@(b|c))d", &["ad", "bd", "cd"], &["ed", "abd"]);
+    assert_glob("+(a|b*(c|d))", &["a", "bcd", "abdcb"], &["c", "bce"]);
+    assert_glob("{a,@(b|c)}x", &["ax", "bx", "cx"], &["dx"]);
+    assert_glob("a/@(b|c)/d", &["a/b/d", "a/c/d"], &["a/bc/d", "a/b/c/d"]);
+    // groups do not match separators
+    assert_glob("a*(b)c", &["ac", "abbc"], &["a/c"]);
+    // an opening parenthesis without a closing one is literal
+    assert_glob("a(b", &["a(b"], &["ab"]);
+}
+
+#[test]
+fn negated_extglobs() {
+    assert_glob("!(a)", &["b", "aa", "ba"], &["a", "a/b", "b/a"]);
+    assert_glob("!(a|b)", &["c", "ab"], &["a", "b"]);
+    assert_glob("!(*.js)", &["a.md", "a.jsx", "a.js.md"], &["a.js", ".js"]);
+    assert_glob("*.!(js)", &["a.md", "a.jsx"], &["a.js"]);
+    assert_glob("a/!(b)/c", &["a/c/c", "a/xb/c"], &["a/b/c", "a/b/b/c"]);
+    assert_glob("!(*.d).ts", &["a.ts", "d.ts"], &["a.d.ts", "a.ts.d.ts"]);
+    assert_glob(
+        "!(*.spec).(ts|js)",
+        &["a.ts", "a.js"],
+        &["a.spec.ts", "a.spec.js"],
+    );
+    assert_glob(
+        "**/!(*.module).ts",
+        &["a.ts", "a/b.ts"],
+        &["a.module.ts", "a/b.module.ts"],
+    );
+    assert_glob("@(!(a)|b)", &["b", "c"], &["a"]);
+}
+
+/// Unlike bash, micromatch rejects the rest of the segment when it starts with a negated pattern which is followed by more of the glob
+#[test]
+fn negated_extglobs_followed_by_patterns() {
+    assert_glob("!(foo)*", &["bar", "baz", "fo"], &["foo", "foobar"]);
+    assert_glob("a!(b)*", &["a", "ac", "acb"], &["ab", "abc"]);
+    assert_glob("a/!(b)/c", &["a/c/c"], &["a/bb/c"]);
+    assert_glob(
+        "!(README|LICENSE).md",
+        &["CHANGELOG.md", "a.md"],
+        &["README.md", "LICENSE.md", "README2.md"],
+    );
+}
+
+#[test]
+fn escaped_and_literal_chars() {
+    assert_glob("a\\*b", &["a*b"], &["axb", "ab"]);
+    assert_glob("a\\?", &["a?"], &["ab"]);
+    assert_glob("\\{a,b\\}", &["{a,b}"], &["a"]);
+    assert_glob("a!b", &["a!b"], &["ab"]);
+    assert_glob("c++/*.h", &["c++/a.h"], &["c/a.h"]);
+    assert_glob("@scope/*", &["@scope/a"], &["scope/a"]);
+    assert_glob("a.b", &["a.b"], &["axb"]);
+}
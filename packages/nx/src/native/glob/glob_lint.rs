@@ -0,0 +1,493 @@
+use crate::native::glob::glob_matcher::GlobMatcher;
+
+#[napi(string_enum)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum GlobDiagnosticSeverity {
+    /// The glob does not mean what it looks like it means
+    #[allow(non_camel_case_types)]
+    error,
+    /// The glob works, but probably not as intended
+    #[allow(non_camel_case_types)]
+    warning,
+}
+
+/// A problem with a glob, found by `lint_globs`
+#[napi(object)]
+#[derive(Debug, PartialEq, Eq)]
+pub struct GlobDiagnostic {
+    pub glob: String,
+    /// Byte offset in the glob where the problem starts
+    pub start: u32,
+    /// Byte offset in the glob where the problem ends, exclusive
+    pub end: u32,
+    pub severity: GlobDiagnosticSeverity,
+    pub message: String,
+    /// The whole glob with the problem fixed, when there is an obvious fix
+    pub fix: Option<String>,
+}
+
+impl GlobDiagnostic {
+    pub(crate) fn warning(glob: &str, start: usize, end: usize, message: String) -> Self {
+        GlobDiagnostic {
+            glob: glob.to_string(),
+            start: start as u32,
+            end: end as u32,
+            severity: GlobDiagnosticSeverity::warning,
+            message,
+            fix: None,
+        }
+    }
+}
+
+/// Finds problems in globs, like unbalanced braces, `**` within a path segment or negations which exclude every file.
+/// Interpolations like `{projectRoot}` are allowed, so the `inputs` and `outputs` of targets can be linted as they are.
+#[napi]
+pub fn lint_globs(globs: Vec<String>) -> Vec<GlobDiagnostic> {
+    globs.iter().flat_map(|glob| lint_glob(glob)).collect()
+}
+
+pub(crate) fn lint_glob(glob: &str) -> Vec<GlobDiagnostic> {
+    let mut lint = GlobLint {
+        glob,
+        diagnostics: vec![],
+    };
+    lint.check_whitespace();
+    let pattern_start = if glob.starts_with('!') && !glob.starts_with("!(") {
+        1
+    } else {
+        0
+    };
+    let pattern = &glob[pattern_start..];
+    if pattern.trim().is_empty() {
+        lint.error(0, glob.len(), "The glob is empty".into(), None);
+        return lint.diagnostics;
+    }
+    if let Some(relative) = pattern.strip_prefix("./") {
+        lint.warning(
+            pattern_start,
+            pattern_start + 2,
+            "Paths are relative to the workspace root and never start with `./`".into(),
+            Some(format!("{}{}", &glob[..pattern_start], relative)),
+        );
+    }
+    lint.check_groups();
+    lint.check_globstars();
+    lint.check_backslashes();
+    if pattern_start == 1 {
+        lint.check_negation(pattern);
+    }
+    // only report errors of the matcher which were not reported with a span already
+    let matcher_error = if lint.has_errors() {
+        None
+    } else {
+        GlobMatcher::new(pattern).err()
+    };
+    if let Some(e) = matcher_error {
+        lint.error(0, glob.len(), e.to_string(), None);
+    }
+    lint.diagnostics.sort_by_key(|d| (d.start, d.end));
+    lint.diagnostics
+}
+
+/// Whether the glob contains interpolations like `{projectRoot}`, which are replaced before it is matched
+pub(crate) fn contains_interpolation(glob: &str) -> bool {
+    glob.match_indices('{')
+        .any(|(start, _)| interpolation_len(&glob[start..]).is_some())
+}
+
+/// The length of an interpolation like `{projectRoot}` or `{options.outputPath}` at the start of `value`
+fn interpolation_len(value: &str) -> Option<usize> {
+    let end = value.find('}')?;
+    let name = &value[1..end];
+    // ranges like `{1..3}` are not interpolations
+    (!name.is_empty()
+        && !name.contains("..")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+    .then_some(end + 1)
+}
+
+struct GlobLint<'a> {
+    glob: &'a str,
+    diagnostics: Vec<GlobDiagnostic>,
+}
+
+/// A group which was opened but not closed yet
+struct OpenGroup {
+    open: char,
+    start: usize,
+    has_comma: bool,
+}
+
+impl GlobLint<'_> {
+    fn error(&mut self, start: usize, end: usize, message: String, fix: Option<String>) {
+        self.diagnostics.push(GlobDiagnostic {
+            severity: GlobDiagnosticSeverity::error,
+            fix,
+            ..GlobDiagnostic::warning(self.glob, start, end, message)
+        });
+    }
+
+    fn warning(&mut self, start: usize, end: usize, message: String, fix: Option<String>) {
+        self.diagnostics.push(GlobDiagnostic {
+            fix,
+            ..GlobDiagnostic::warning(self.glob, start, end, message)
+        });
+    }
+
+    fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == GlobDiagnosticSeverity::error)
+    }
+
+    /// The glob with the chars from `start` to `end` replaced
+    fn replaced(&self, start: usize, end: usize, replacement: &str) -> String {
+        format!(
+            "{}{}{}",
+            &self.glob[..start],
+            replacement,
+            &self.glob[end..]
+        )
+    }
+
+    fn check_whitespace(&mut self) {
+        let trimmed_start = self.glob.len() - self.glob.trim_start().len();
+        let trimmed_end = self.glob.trim_end().len();
+        if trimmed_start > 0 || trimmed_end < self.glob.len() {
+            let (start, end) = if trimmed_start > 0 {
+                (0, trimmed_start)
+            } else {
+                (trimmed_end, self.glob.len())
+            };
+            self.warning(
+                start,
+                end,
+                "Whitespace around globs is matched literally".into(),
+                Some(self.glob.trim().to_string()),
+            );
+        }
+    }
+
+    /// Checks that braces, parentheses and brackets are balanced, and that braces contain alternatives
+    fn check_groups(&mut self) {
+        let glob = self.glob;
+        let mut open_groups: Vec<OpenGroup> = vec![];
+        let mut chars = glob.char_indices().peekable();
+        while let Some((pos, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '{' => {
+                    if let Some(len) = interpolation_len(&glob[pos..]) {
+                        while chars.peek().is_some_and(|(next, _)| *next < pos + len) {
+                            chars.next();
+                        }
+                    } else {
+                        open_groups.push(OpenGroup {
+                            open: c,
+                            start: pos,
+                            has_comma: false,
+                        });
+                    }
+                }
+                '(' => open_groups.push(OpenGroup {
+                    open: c,
+                    start: pos,
+                    has_comma: false,
+                }),
+                ',' => {
+                    if let Some(group) = open_groups.last_mut() {
+                        group.has_comma = true;
+                    }
+                }
+                '[' => match class_len(&glob[pos..]) {
+                    Some(len) => {
+                        while chars.peek().is_some_and(|(next, _)| *next < pos + len) {
+                            chars.next();
+                        }
+                    }
+                    None => self.warning(
+                        pos,
+                        pos + 1,
+                        "`[` is matched literally because it is never closed".into(),
+                        Some(self.replaced(pos, pos + 1, "\\[")),
+                    ),
+                },
+                '}' | ')' => {
+                    let open = if c == '}' { '{' } else { '(' };
+                    match open_groups.last() {
+                        Some(group) if group.open == open => {
+                            let group = open_groups.pop().unwrap();
+                            if open == '{' {
+                                self.check_braces(group, pos);
+                            }
+                        }
+                        _ => self.error(
+                            pos,
+                            pos + 1,
+                            format!("`{}` does not close a group", c),
+                            Some(self.replaced(pos, pos + 1, "")),
+                        ),
+                    }
+                }
+                _ => {}
+            }
+        }
+        for group in open_groups {
+            let close = if group.open == '{' { '}' } else { ')' };
+            self.error(
+                group.start,
+                glob.len(),
+                format!("`{}` is never closed", group.open),
+                Some(format!("{}{}", glob, close)),
+            );
+        }
+    }
+
+    fn check_braces(&mut self, group: OpenGroup, close: usize) {
+        let contents = &self.glob[group.start + 1..close];
+        if !group.has_comma && !contents.contains("..") {
+            self.warning(
+                group.start,
+                close + 1,
+                "Braces without a `,` or a range like `1..3` are matched literally".into(),
+                Some(self.replaced(group.start, close + 1, contents)),
+            );
+        }
+    }
+
+    /// Checks for `**` which is not a whole path segment, so it only matches within a segment like `*`
+    fn check_globstars(&mut self) {
+        let glob = self.glob;
+        let bytes = glob.as_bytes();
+        let mut pos = 0;
+        while let Some(offset) = glob[pos..].find("**") {
+            let start = pos + offset;
+            let mut end = start;
+            while bytes.get(end) == Some(&b'*') {
+                end += 1;
+            }
+            pos = end;
+            if bytes.get(end) == Some(&b'(') || (start > 0 && bytes[start - 1] == b'\\') {
+                continue;
+            }
+            let starts_segment =
+                start == 0 || matches!(bytes[start - 1], b'/' | b'{' | b',' | b'!');
+            let ends_segment = matches!(bytes.get(end), None | Some(b'/' | b'}' | b','));
+            let fix = match (starts_segment, ends_segment) {
+                (true, true) => continue,
+                // `**.ts` is probably meant to be `**/*.ts`
+                (true, false) => self.replaced(start, end, "**/*"),
+                (false, true) => self.replaced(start, end, "*/**"),
+                (false, false) => self.replaced(start, end, "*"),
+            };
+            self.warning(
+                start,
+                end,
+                "`**` only matches across directories as a whole path segment, here it is the same as `*`".into(),
+                Some(fix),
+            );
+        }
+    }
+
+    /// Checks for backslashes which were probably meant as path separators
+    fn check_backslashes(&mut self) {
+        let glob = self.glob;
+        let separators = glob
+            .char_indices()
+            .zip(glob.chars().skip(1))
+            .filter(|((_, c), next)| *c == '\\' && next.is_ascii_alphanumeric())
+            .map(|((pos, _), _)| pos)
+            .collect::<Vec<_>>();
+        if let Some(first) = separators.first() {
+            let fix = separators
+                .iter()
+                .rev()
+                .fold(glob.to_string(), |fixed, pos| {
+                    format!("{}/{}", &fixed[..*pos], &fixed[pos + 1..])
+                });
+            self.warning(
+                *first,
+                first + 1,
+                "`\\` escapes the next character, paths are always separated by `/`".into(),
+                Some(fix),
+            );
+        }
+    }
+
+    fn check_negation(&mut self, pattern: &str) {
+        // the directory of an interpolation like `{projectRoot}/**/*` is not known yet
+        let (directory, rest) = match interpolation_len(pattern) {
+            Some(len) if pattern[len..].starts_with('/') => (&pattern[..len], &pattern[len + 1..]),
+            _ => ("the workspace", pattern),
+        };
+        let segments = rest.split('/').collect::<Vec<_>>();
+        let (last, directories) = segments.split_last().unwrap();
+        if directories.iter().all(|s| *s == "**")
+            && (*last == "**" || (*last == "*" && !directories.is_empty()))
+        {
+            self.warning(
+                0,
+                self.glob.len(),
+                format!("This negation excludes every file in {}", directory),
+                None,
+            );
+        }
+    }
+}
+
+/// The length of a closed character class like `[a-z]` at the start of `value`
+fn class_len(value: &str) -> Option<usize> {
+    let mut chars = value.char_indices().skip(1).peekable();
+    chars.next_if(|(_, c)| *c == '!' || *c == '^');
+    // a `]` right after the opening bracket is part of the class
+    chars.next_if(|(_, c)| *c == ']');
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            ']' => return Some(pos + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lint(glob: &str) -> Vec<(u32, u32, GlobDiagnosticSeverity, Option<String>)> {
+        lint_glob(glob)
+            .into_iter()
+            .map(|d| (d.start, d.end, d.severity, d.fix))
+            .collect()
+    }
+
+    #[test]
+    fn should_accept_valid_globs() {
+        for glob in [
+            "**/*",
+            "{projectRoot}/**/*.ts",
+            "!{projectRoot}/**/?(*.)+(spec|test).[jt]s?(x)?(.snap)",
+            "{workspaceRoot}/dist/{projectRoot}",
+            "{options.outputPath}",
+            "**/*.{js,ts}",
+            "file{1..3}.txt",
+            "[[]*",
+            "!(*.d).ts",
+        ] {
+            assert_eq!(lint(glob), vec![], "{}", glob);
+        }
+    }
+
+    #[test]
+    fn should_report_unbalanced_groups() {
+        assert_eq!(
+            lint("src/{a,b/**"),
+            vec![(
+                4,
+                11,
+                GlobDiagnosticSeverity::error,
+                Some("src/{a,b/**}".into())
+            )]
+        );
+        assert_eq!(
+            lint("src/*.ts)"),
+            vec![(8, 9, GlobDiagnosticSeverity::error, Some("src/*.ts".into()))]
+        );
+        assert_eq!(
+            lint("src/{*.ts}"),
+            vec![(
+                4,
+                10,
+                GlobDiagnosticSeverity::warning,
+                Some("src/*.ts".into())
+            )]
+        );
+        assert_eq!(
+            lint("src/[a-z"),
+            vec![(
+                4,
+                5,
+                GlobDiagnosticSeverity::warning,
+                Some("src/\\[a-z".into())
+            )]
+        );
+    }
+
+    #[test]
+    fn should_report_globstars_within_segments() {
+        assert_eq!(
+            lint("{projectRoot}/**.ts"),
+            vec![(
+                14,
+                16,
+                GlobDiagnosticSeverity::warning,
+                Some("{projectRoot}/**/*.ts".into())
+            )]
+        );
+        assert_eq!(
+            lint("src**"),
+            vec![(
+                3,
+                5,
+                GlobDiagnosticSeverity::warning,
+                Some("src*/**".into())
+            )]
+        );
+    }
+
+    #[test]
+    fn should_report_negations_which_exclude_everything() {
+        let diagnostics = lint_glob("!{projectRoot}/**/*");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "This negation excludes every file in {projectRoot}"
+        );
+        assert_eq!(lint_glob("!**").len(), 1);
+        assert_eq!(lint_glob("!*").len(), 0);
+        assert_eq!(lint_glob("!{projectRoot}/**/*.md").len(), 0);
+    }
+
+    #[test]
+    fn should_report_other_mistakes() {
+        assert_eq!(
+            lint(" src/**/*.ts"),
+            vec![(
+                0,
+                1,
+                GlobDiagnosticSeverity::warning,
+                Some("src/**/*.ts".into())
+            )]
+        );
+        assert_eq!(
+            lint("!./src/*.ts"),
+            vec![(
+                1,
+                3,
+                GlobDiagnosticSeverity::warning,
+                Some("!src/*.ts".into())
+            )]
+        );
+        assert_eq!(
+            lint("src\\lib\\index.ts"),
+            vec![(
+                3,
+                4,
+                GlobDiagnosticSeverity::warning,
+                Some("src/lib/index.ts".into())
+            )]
+        );
+        assert_eq!(lint("!"), vec![(0, 1, GlobDiagnosticSeverity::error, None)]);
+        assert_eq!(
+            lint_glob("file{1..100000}")[0].severity,
+            GlobDiagnosticSeverity::error
+        );
+    }
+}
@@ -0,0 +1,748 @@
+use anyhow::bail;
+use std::cell::RefCell;
+
+/// Ranges like `{1..1000}` become one alternative per value, so they are kept reasonably small
+const MAX_RANGE_LENGTH: usize = 10_000;
+
+thread_local! {
+    /// States and path chars are reused across matches, so that matching a path does not allocate
+    static STATES: RefCell<Vec<States>> = const { RefCell::new(Vec::new()) };
+    static PATH_CHARS: RefCell<Vec<char>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A single glob, compiled into an automaton which matches paths the same way micromatch does with `dot: true`.
+///
+/// Supported syntax:
+/// - `*`, `?` and `**` as a whole path segment
+/// - character classes like `[a-z]`, `[!a-z]`, `[^a-z]` and `[[:alpha:]]`
+/// - nested braces like `{a,b{c,d}}` and ranges like `{1..5}`
+/// - extglobs `?(a|b)`, `*(a|b)`, `+(a|b)`, `@(a|b)` and `!(a|b)`, which can be nested
+///
+/// Like the globs Nx has always accepted, `(a|b)` without a prefix is the same as `@(a|b)`,
+/// alternatives within parentheses may also be separated by `,`,
+/// and a `+` or `@` which directly follows a group without starting one itself is ignored.
+#[derive(Debug)]
+pub struct GlobMatcher {
+    program: Program,
+}
+
+impl GlobMatcher {
+    pub fn new(glob: &str) -> anyhow::Result<Self> {
+        let nodes = Parser::new(glob).parse()?;
+        Ok(Self {
+            program: Program::compile(&nodes),
+        })
+    }
+
+    /// Compiles several globs into a single automaton, which matches paths matching any of them.
+    /// There has to be at least one glob.
+    pub fn any<S: AsRef<str>>(globs: &[S]) -> anyhow::Result<Self> {
+        let alternatives = globs
+            .iter()
+            .map(|glob| Parser::new(glob.as_ref()).parse())
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            program: Program::compile(&[Node::Group(Repetition::ExactlyOne, alternatives)]),
+        })
+    }
+
+    /// Matches a path which was split into chars, see [`path_chars`]
+    pub fn is_match(&self, path: &[char]) -> bool {
+        STATES.with(|pool| {
+            self.program
+                .is_match(path, 0, MatchKind::Whole, &mut pool.borrow_mut())
+        })
+    }
+}
+
+fn normalize_separator(c: char) -> char {
+    if cfg!(windows) && c == '\\' { '/' } else { c }
+}
+
+/// The chars of a path, with `/` as separator on every platform
+#[cfg(test)]
+pub fn path_chars(path: &str) -> Vec<char> {
+    path.chars().map(normalize_separator).collect()
+}
+
+/// Same as [`path_chars`], without allocating a new buffer for every path
+pub fn with_path_chars<T>(path: &str, f: impl FnOnce(&[char]) -> T) -> T {
+    PATH_CHARS.with(|chars| {
+        let mut chars = chars.borrow_mut();
+        chars.clear();
+        chars.extend(path.chars().map(normalize_separator));
+        f(&chars)
+    })
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Literal(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    Star,
+    /// `**` on its own: any path
+    AnyPath,
+    /// `**/`: nothing, or any number of directories
+    GlobStarPrefix,
+    /// `/**` at the end of a glob: nothing, or anything within the directory
+    GlobStarSuffix,
+    Class(CharClass),
+    Group(Repetition, Vec<Vec<Node>>),
+    /// `!(a|b)`, which matches any chars within a path segment where the group does not match
+    Negated(Vec<Vec<Node>>, Lookahead),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Repetition {
+    ExactlyOne,
+    ZeroOrOne,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+/// What a negated group must not match, which depends on what follows it in the glob
+#[derive(Debug, Clone)]
+enum Lookahead {
+    /// At the end of the glob, the group must not match the rest of the path
+    Rest,
+    /// The group must not match the start of the rest of the path
+    Prefix,
+    /// A group containing `*` followed by an extension, like `!(*.d).ts`,
+    /// must not match the start of the rest of the path together with the extension
+    PrefixWith(Vec<Node>),
+}
+
+#[derive(Debug, Clone)]
+struct CharClass {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+    Posix(fn(&char) -> bool),
+}
+
+impl CharClass {
+    /// Classes never match path separators
+    fn matches(&self, c: char) -> bool {
+        c != '/'
+            && self.items.iter().any(|item| match item {
+                ClassItem::Char(expected) => c == *expected,
+                ClassItem::Range(start, end) => (*start..=*end).contains(&c),
+                ClassItem::Posix(is_match) => is_match(&c),
+            }) != self.negated
+    }
+}
+
+fn posix_class(name: &str) -> Option<fn(&char) -> bool> {
+    let is_match: fn(&char) -> bool = match name {
+        "alnum" => char::is_ascii_alphanumeric,
+        "alpha" => char::is_ascii_alphabetic,
+        "ascii" => |c| c.is_ascii(),
+        "blank" => |c| *c == ' ' || *c == '\t',
+        "cntrl" => char::is_ascii_control,
+        "digit" => char::is_ascii_digit,
+        "graph" => char::is_ascii_graphic,
+        "lower" => char::is_ascii_lowercase,
+        "print" => |c| c.is_ascii_graphic() || *c == ' ',
+        "punct" => char::is_ascii_punctuation,
+        "space" => char::is_ascii_whitespace,
+        "upper" => char::is_ascii_uppercase,
+        "word" => |c| c.is_ascii_alphanumeric() || *c == '_',
+        "xdigit" => char::is_ascii_hexdigit,
+        _ => return None,
+    };
+    Some(is_match)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(glob: &str) -> Self {
+        Self {
+            chars: glob.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn parse(mut self) -> anyhow::Result<Vec<Node>> {
+        let end = self.chars.len();
+        let mut alternatives = self.parse_alternatives(end, &[])?;
+        Ok(alternatives.remove(0))
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    /// Parses the chars up to `end` into alternatives split by any of the `separators`
+    fn parse_alternatives(
+        &mut self,
+        end: usize,
+        separators: &[char],
+    ) -> anyhow::Result<Vec<Vec<Node>>> {
+        let mut alternatives = vec![];
+        let mut nodes = vec![];
+        while self.pos < end {
+            let c = self.chars[self.pos];
+            if separators.contains(&c) {
+                alternatives.push(std::mem::take(&mut nodes));
+                self.pos += 1;
+            } else {
+                self.parse_node(c, &mut nodes)?;
+            }
+        }
+        alternatives.push(nodes);
+        Ok(alternatives)
+    }
+
+    fn parse_node(&mut self, c: char, nodes: &mut Vec<Node>) -> anyhow::Result<()> {
+        match c {
+            '\\' => {
+                let escaped = self.peek(1);
+                self.pos += if escaped.is_some() { 2 } else { 1 };
+                nodes.push(Node::Literal(escaped.unwrap_or('\\')));
+            }
+            '?' | '*' | '+' | '@' | '!' if self.peek(1) == Some('(') => {
+                match self.find_group_end(self.pos + 1, '(', ')') {
+                    Some(close) => nodes.push(self.parse_extglob(c, close)?),
+                    None => self.parse_special_char(c, nodes),
+                }
+            }
+            '(' => match self.find_group_end(self.pos, '(', ')') {
+                Some(close) => {
+                    self.pos += 1;
+                    let alternatives = self.parse_alternatives(close, &['|', ','])?;
+                    self.pos = close + 1;
+                    nodes.push(Node::Group(Repetition::ExactlyOne, alternatives));
+                }
+                None => self.literal(nodes),
+            },
+            '{' => self.parse_braces(nodes)?,
+            '[' => match self.parse_class() {
+                Some(class) => nodes.push(Node::Class(class)),
+                None => self.literal(nodes),
+            },
+            _ => self.parse_special_char(c, nodes),
+        }
+        Ok(())
+    }
+
+    fn literal(&mut self, nodes: &mut Vec<Node>) {
+        nodes.push(Node::Literal(self.chars[self.pos]));
+        self.pos += 1;
+    }
+
+    fn parse_special_char(&mut self, c: char, nodes: &mut Vec<Node>) {
+        match c {
+            '?' => {
+                self.pos += 1;
+                nodes.push(Node::AnyChar);
+            }
+            '*' => self.parse_stars(nodes),
+            '+' | '@' if self.pos > 0 && self.chars[self.pos - 1] == ')' => {
+                // `?(*.)+spec.ts` was always treated like `?(*.)spec.ts`
+                self.pos += 1;
+            }
+            _ => self.literal(nodes),
+        }
+    }
+
+    fn parse_stars(&mut self, nodes: &mut Vec<Node>) {
+        let start = self.pos;
+        while self.peek(0) == Some('*') {
+            self.pos += 1;
+        }
+        let starts_segment = start == 0 || matches!(self.chars[start - 1], '/' | '{' | ',');
+        let ends_segment = matches!(self.peek(0), None | Some('/' | '}' | ','));
+        if self.pos - start < 2 || !starts_segment || !ends_segment {
+            nodes.push(Node::Star);
+        } else if self.peek(0) == Some('/') {
+            self.pos += 1;
+            nodes.push(Node::GlobStarPrefix);
+        } else if self.pos == self.chars.len() && matches!(nodes.last(), Some(Node::Literal('/'))) {
+            nodes.pop();
+            nodes.push(Node::GlobStarSuffix);
+        } else {
+            nodes.push(Node::AnyPath);
+        }
+    }
+
+    /// Parses the group of an extglob like `+(a|b)`, which ends at `close`
+    fn parse_extglob(&mut self, kind: char, close: usize) -> anyhow::Result<Node> {
+        let inner_start = self.pos + 2;
+        self.pos = inner_start;
+        let alternatives = self.parse_alternatives(close, &['|', ','])?;
+        self.pos = close + 1;
+
+        let repetition = match kind {
+            '?' => Repetition::ZeroOrOne,
+            '*' => Repetition::ZeroOrMore,
+            '+' => Repetition::OneOrMore,
+            '@' => Repetition::ExactlyOne,
+            _ => {
+                let rest = self.chars[close + 1..].iter().collect::<String>();
+                let inner_has_star = self.chars[inner_start..close].contains(&'*');
+                let lookahead = if rest.chars().all(|c| c == ')') {
+                    Lookahead::Rest
+                } else if inner_has_star && is_extension(&rest) {
+                    Lookahead::PrefixWith(Parser::new(&rest).parse()?)
+                } else {
+                    Lookahead::Prefix
+                };
+                return Ok(Node::Negated(alternatives, lookahead));
+            }
+        };
+        Ok(Node::Group(repetition, alternatives))
+    }
+
+    fn parse_braces(&mut self, nodes: &mut Vec<Node>) -> anyhow::Result<()> {
+        let Some(close) = self.find_group_end(self.pos, '{', '}') else {
+            self.literal(nodes);
+            return Ok(());
+        };
+        let start = self.pos;
+        self.pos += 1;
+        let alternatives = self.parse_alternatives(close, &[','])?;
+        if alternatives.len() > 1 {
+            self.pos = close + 1;
+            nodes.push(Node::Group(Repetition::ExactlyOne, alternatives));
+            return Ok(());
+        }
+
+        let contents = self.chars[start + 1..close].iter().collect::<String>();
+        if let Some(alternatives) = expand_range(&contents)? {
+            self.pos = close + 1;
+            nodes.push(Node::Group(Repetition::ExactlyOne, alternatives));
+        } else {
+            // braces without alternatives, like `{a}`, are matched literally
+            self.pos = start;
+            self.literal(nodes);
+        }
+        Ok(())
+    }
+
+    /// Parses a character class starting at the current `[`, if it is closed
+    fn parse_class(&mut self) -> Option<CharClass> {
+        let mut pos = self.pos + 1;
+        let negated = matches!(self.chars.get(pos), Some('!' | '^'));
+        if negated {
+            pos += 1;
+        }
+        let first = pos;
+        let mut items = vec![];
+        loop {
+            let c = *self.chars.get(pos)?;
+            if c == ']' && pos > first {
+                break;
+            }
+            if c == '[' && self.chars.get(pos + 1) == Some(&':') {
+                let name_start = pos + 2;
+                if let Some(length) = self.chars[name_start..]
+                    .windows(2)
+                    .position(|w| w == [':', ']'])
+                {
+                    let name = self.chars[name_start..name_start + length]
+                        .iter()
+                        .collect::<String>();
+                    if let Some(is_match) = posix_class(&name) {
+                        items.push(ClassItem::Posix(is_match));
+                        pos = name_start + length + 2;
+                        continue;
+                    }
+                }
+            }
+            let c = if c == '\\' {
+                pos += 1;
+                *self.chars.get(pos)?
+            } else {
+                c
+            };
+            match (self.chars.get(pos + 1), self.chars.get(pos + 2)) {
+                (Some('-'), Some(&end)) if end != ']' => {
+                    items.push(ClassItem::Range(c, end));
+                    pos += 3;
+                }
+                _ => {
+                    items.push(ClassItem::Char(c));
+                    pos += 1;
+                }
+            }
+        }
+        self.pos = pos + 1;
+        Some(CharClass { negated, items })
+    }
+
+    /// Finds the position of the `close` char which ends the group opened at `start`
+    fn find_group_end(&self, start: usize, open: char, close: char) -> Option<usize> {
+        let mut depth = 0;
+        let mut pos = start;
+        while let Some(&c) = self.chars.get(pos) {
+            if c == '\\' {
+                pos += 1;
+            } else if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+            pos += 1;
+        }
+        None
+    }
+}
+
+/// Whether the rest of a glob is a plain extension, like `.ts` or `.(js|ts)`
+fn is_extension(rest: &str) -> bool {
+    rest.strip_prefix('.')
+        .is_some_and(|extension| !extension.is_empty() && !extension.contains(['\\', '/', '.']))
+}
+
+/// Expands the contents of a brace range, like `1..5` or `a..e`, into literal alternatives
+fn expand_range(contents: &str) -> anyhow::Result<Option<Vec<Vec<Node>>>> {
+    let Some((start, end)) = contents.split_once("..") else {
+        return Ok(None);
+    };
+    let literal = |value: String| value.chars().map(Node::Literal).collect::<Vec<_>>();
+
+    if let (Ok(first), Ok(last)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        if first.abs_diff(last) as usize >= MAX_RANGE_LENGTH {
+            bail!("The range {{{}}} is too large", contents);
+        }
+        // `{01..10}` pads every value to the same width
+        let width = if start.starts_with('0') || end.starts_with('0') {
+            start.len().max(end.len())
+        } else {
+            0
+        };
+        let values: Box<dyn Iterator<Item = i64>> = if first <= last {
+            Box::new(first..=last)
+        } else {
+            Box::new((last..=first).rev())
+        };
+        return Ok(Some(
+            values
+                .map(|value| literal(format!("{:0width$}", value, width = width)))
+                .collect(),
+        ));
+    }
+
+    let mut start_chars = start.chars();
+    let mut end_chars = end.chars();
+    match (
+        start_chars.next(),
+        start_chars.next(),
+        end_chars.next(),
+        end_chars.next(),
+    ) {
+        (Some(first), None, Some(last), None) => {
+            let (low, high) = if first <= last {
+                (first, last)
+            } else {
+                (last, first)
+            };
+            let mut values = (low..=high)
+                .map(|c| vec![Node::Literal(c)])
+                .collect::<Vec<_>>();
+            if first > last {
+                values.reverse();
+            }
+            Ok(Some(values))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MatchKind {
+    /// The program has to match the whole rest of the input
+    Whole,
+    /// The program has to match the start of the rest of the input
+    Prefix,
+}
+
+#[derive(Debug)]
+enum Inst {
+    Char(char),
+    /// Any char within a path segment
+    AnyChar,
+    /// Any char, including path separators
+    AnyCharOrSlash,
+    Class(CharClass),
+    Split(usize, usize),
+    Jmp(usize),
+    /// Only continues when the lookahead program does not match at the current position
+    NotAhead(Box<Program>, MatchKind),
+    Match,
+}
+
+/// A non-deterministic automaton, which is simulated over all of its states at once,
+/// so matching takes time proportional to the length of the path, without backtracking
+#[derive(Debug)]
+struct Program {
+    insts: Vec<Inst>,
+}
+
+impl Program {
+    fn compile(nodes: &[Node]) -> Self {
+        let mut program = Program { insts: vec![] };
+        program.sequence(nodes);
+        program.push(Inst::Match);
+        program
+    }
+
+    fn push(&mut self, inst: Inst) -> usize {
+        self.insts.push(inst);
+        self.insts.len() - 1
+    }
+
+    fn next(&self) -> usize {
+        self.insts.len()
+    }
+
+    fn sequence(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            self.node(node);
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Literal(c) => {
+                self.push(Inst::Char(*c));
+            }
+            Node::AnyChar => {
+                self.push(Inst::AnyChar);
+            }
+            Node::Class(class) => {
+                self.push(Inst::Class(class.clone()));
+            }
+            Node::Star => self.any_number_of(Inst::AnyChar),
+            Node::AnyPath => self.any_number_of(Inst::AnyCharOrSlash),
+            Node::GlobStarPrefix => {
+                let split = self.push(Inst::Split(0, 0));
+                self.any_number_of(Inst::AnyCharOrSlash);
+                self.push(Inst::Char('/'));
+                self.insts[split] = Inst::Split(split + 1, self.next());
+            }
+            Node::GlobStarSuffix => {
+                let split = self.push(Inst::Split(0, 0));
+                self.push(Inst::Char('/'));
+                self.any_number_of(Inst::AnyCharOrSlash);
+                self.insts[split] = Inst::Split(split + 1, self.next());
+            }
+            Node::Group(repetition, alternatives) => match repetition {
+                Repetition::ExactlyOne => self.alternation(alternatives),
+                Repetition::ZeroOrOne => {
+                    let split = self.push(Inst::Split(0, 0));
+                    self.alternation(alternatives);
+                    self.insts[split] = Inst::Split(split + 1, self.next());
+                }
+                Repetition::ZeroOrMore => {
+                    let split = self.push(Inst::Split(0, 0));
+                    self.alternation(alternatives);
+                    self.push(Inst::Jmp(split));
+                    self.insts[split] = Inst::Split(split + 1, self.next());
+                }
+                Repetition::OneOrMore => {
+                    let start = self.next();
+                    self.alternation(alternatives);
+                    self.push(Inst::Split(start, self.next() + 1));
+                }
+            },
+            Node::Negated(alternatives, lookahead) => {
+                let group = Node::Group(Repetition::ExactlyOne, alternatives.clone());
+                let (lookahead, kind) = match lookahead {
+                    Lookahead::Rest => (Program::compile(&[group]), MatchKind::Whole),
+                    Lookahead::Prefix => (Program::compile(&[group]), MatchKind::Prefix),
+                    Lookahead::PrefixWith(suffix) => {
+                        let mut nodes = vec![group];
+                        nodes.extend(suffix.iter().cloned());
+                        (Program::compile(&nodes), MatchKind::Prefix)
+                    }
+                };
+                self.push(Inst::NotAhead(Box::new(lookahead), kind));
+                self.any_number_of(Inst::AnyChar);
+            }
+        }
+    }
+
+    fn any_number_of(&mut self, inst: Inst) {
+        let split = self.push(Inst::Split(0, 0));
+        self.push(inst);
+        self.push(Inst::Jmp(split));
+        self.insts[split] = Inst::Split(split + 1, self.next());
+    }
+
+    fn alternation(&mut self, alternatives: &[Vec<Node>]) {
+        let mut jumps_to_end = vec![];
+        for (index, alternative) in alternatives.iter().enumerate() {
+            if index == alternatives.len() - 1 {
+                self.sequence(alternative);
+            } else {
+                let split = self.push(Inst::Split(0, 0));
+                self.sequence(alternative);
+                jumps_to_end.push(self.push(Inst::Jmp(0)));
+                self.insts[split] = Inst::Split(split + 1, self.next());
+            }
+        }
+        let end = self.next();
+        for jump in jumps_to_end {
+            self.insts[jump] = Inst::Jmp(end);
+        }
+    }
+
+    /// Matches the input from `start`, taking the states it needs from `pool` and returning them afterwards
+    fn is_match(
+        &self,
+        input: &[char],
+        start: usize,
+        kind: MatchKind,
+        pool: &mut Vec<States>,
+    ) -> bool {
+        let mut current = pool.pop().unwrap_or_default();
+        let mut next = pool.pop().unwrap_or_default();
+        current.reset(self.insts.len());
+        next.reset(self.insts.len());
+
+        let is_match = self.run(&mut current, &mut next, input, start, kind, pool);
+
+        pool.push(current);
+        pool.push(next);
+        is_match
+    }
+
+    fn run(
+        &self,
+        current: &mut States,
+        next: &mut States,
+        input: &[char],
+        start: usize,
+        kind: MatchKind,
+        pool: &mut Vec<States>,
+    ) -> bool {
+        self.add_state(current, 0, input, start, pool);
+
+        for pos in start..=input.len() {
+            if current.pcs.is_empty() {
+                return false;
+            }
+            let c = input.get(pos).copied();
+            for index in 0..current.pcs.len() {
+                let pc = current.pcs[index];
+                let matches = match (&self.insts[pc], c) {
+                    (Inst::Match, _) => {
+                        if kind == MatchKind::Prefix || c.is_none() {
+                            return true;
+                        }
+                        false
+                    }
+                    (Inst::Char(expected), Some(c)) => *expected == c,
+                    (Inst::AnyChar, Some(c)) => c != '/',
+                    (Inst::AnyCharOrSlash, Some(_)) => true,
+                    (Inst::Class(class), Some(c)) => class.matches(c),
+                    _ => false,
+                };
+                if matches {
+                    self.add_state(next, pc + 1, input, pos + 1, pool);
+                }
+            }
+            std::mem::swap(current, next);
+            next.clear();
+        }
+        false
+    }
+
+    /// Adds the state at `pc`, following every transition which does not consume a char
+    fn add_state(
+        &self,
+        states: &mut States,
+        pc: usize,
+        input: &[char],
+        pos: usize,
+        pool: &mut Vec<States>,
+    ) {
+        let mut stack = std::mem::take(&mut states.stack);
+        stack.push(pc);
+        while let Some(pc) = stack.pop() {
+            if states.seen[pc] {
+                continue;
+            }
+            states.seen[pc] = true;
+            states.visited.push(pc);
+            match &self.insts[pc] {
+                Inst::Jmp(target) => stack.push(*target),
+                Inst::Split(first, second) => {
+                    stack.push(*second);
+                    stack.push(*first);
+                }
+                Inst::NotAhead(lookahead, kind) => {
+                    if !lookahead.is_match(input, pos, *kind, pool) {
+                        stack.push(pc + 1);
+                    }
+                }
+                _ => states.pcs.push(pc),
+            }
+        }
+        states.stack = stack;
+    }
+}
+
+/// The states of the automaton at a position in the input
+#[derive(Debug, Default)]
+struct States {
+    pcs: Vec<usize>,
+    seen: Vec<bool>,
+    /// Every state which was seen, so that clearing does not have to go over all of them
+    visited: Vec<usize>,
+    stack: Vec<usize>,
+}
+
+impl States {
+    /// Prepares the states for a program with `size` instructions
+    fn reset(&mut self, size: usize) {
+        self.pcs.clear();
+        self.visited.clear();
+        self.seen.clear();
+        self.seen.resize(size, false);
+    }
+
+    fn clear(&mut self) {
+        self.pcs.clear();
+        for pc in self.visited.drain(..) {
+            self.seen[pc] = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn is_match(glob: &str, path: &str) -> bool {
+        GlobMatcher::new(glob).unwrap().is_match(&path_chars(path))
+    }
+
+    #[test]
+    fn should_not_backtrack_exponentially() {
+        let path = "a".repeat(100);
+        assert!(!is_match("*(a|aa)*(a|aa)*(a|aa)*(a|aa)b", &path));
+        assert!(!is_match("*a*a*a*a*a*a*a*a*b", &path));
+    }
+
+    #[test]
+    fn should_reject_huge_ranges() {
+        assert!(GlobMatcher::new("file{1..100000}").is_err());
+        assert!(GlobMatcher::new("file{1..100}").is_ok());
+    }
+}
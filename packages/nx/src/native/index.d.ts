@@ -41,6 +41,17 @@ export declare class FileLock {
   lock(): void
 }
 
+/**
+ * A remote cache that keeps its entries in a shared directory, such as a network mount.
+ * Entries use the same tarball format as the `HttpRemoteCache`.
+ */
+export declare class FsRemoteCache {
+  /** Uses `directory`, or `NX_SELF_HOSTED_REMOTE_CACHE_DIRECTORY` when it is not provided */
+  constructor(directory?: string | undefined | null)
+  retrieve(hash: string, cacheDirectory: string): Promise<CachedResult | null>
+  store(hash: string, cacheDirectory: string, terminalOutput: string, code: number): Promise<boolean>
+}
+
 export declare class HashPlanner {
   constructor(nxJson: NxJson, projectGraph: ExternalObject<ProjectGraph>)
   getPlans(taskIds: Array<string>, taskGraph: TaskGraph): Record<string, string[]>
@@ -51,6 +62,20 @@ export declare class HttpRemoteCache {
   constructor()
   retrieve(hash: string, cacheDirectory: string): Promise<CachedResult | null>
   store(hash: string, cacheDirectory: string, terminalOutput: string, code: number): Promise<boolean>
+  /** Whether too many requests in a row failed, after which the remote cache is not used anymore */
+  isDegraded(): boolean
+  /**
+   * Returns the hashes which are stored in the remote cache.
+   * Servers which do not implement the bulk lookup are sent a `HEAD` request for each hash.
+   */
+  queryMany(hashes: Array<string>): Promise<Array<string>>
+  /**
+   * Downloads the hashes which are stored in the remote cache into `cache_directory`,
+   * `concurrency` at a time.
+   * The results should be passed to `NxCache.applyRemoteCacheResults` to add them to the local cache.
+   * Downloads which fail are left out, they will be retried when the task is retrieved.
+   */
+  prefetch(hashes: Array<string>, cacheDirectory: string, concurrency?: number | undefined | null): Promise<Array<PrefetchedCacheResult>>
 }
 
 export declare class ImportResult {
@@ -62,13 +87,42 @@ export declare class ImportResult {
 
 export declare class NxCache {
   cacheDirectory: string
-  constructor(workspaceRoot: string, cachePath: string, dbConnection: ExternalObject<NxDbConnection>, linkTaskDetails?: boolean | undefined | null, maxCacheSize?: number | undefined | null)
+  constructor(workspaceRoot: string, cachePath: string, dbConnection: ExternalObject<NxDbConnection>, linkTaskDetails?: boolean | undefined | null, maxCacheSize?: number | undefined | null, restoreMode?: CacheRestoreMode | undefined | null, eviction?: EvictionOptions | undefined | null)
   get(hash: string): CachedResult | null
+  /** Returns the hashes which are not in the cache. Unlike `get`, this does not count as accessing the entries. */
+  missingHashes(hashes: Array<string>): Array<string>
   put(hash: string, terminalOutput: string, outputs: Array<string>, code: number): void
   applyRemoteCacheResults(hash: string, result: CachedResult, outputs?: Array<string> | undefined | null): void
   getTaskOutputsPath(hash: string): string
+  /**
+   * Returns the number of bytes the cache takes up on disk.
+   * Outputs which are shared between entries are only counted once.
+   */
   getCacheSize(): number
+  /** Lists the entries in the cache along with the project, target and configuration of their tasks */
+  listEntries(filter?: CacheEntryFilter | undefined | null): Array<CacheEntry>
+  /** Sums up the entries in the cache per project, largest first */
+  statsByProject(): Array<CacheStats>
+  /** Sums up the entries in the cache per project and target, largest first */
+  statsByTarget(): Array<CacheStats>
+  /**
+   * Evicts the entries which have not been accessed within the TTL and, when the cache is over its
+   * maximum size, the entries picked by the eviction policy until it is back under the limit.
+   * Pinned entries are never evicted.
+   * With `dry_run`, nothing is removed and the report lists what would have been evicted.
+   */
+  evict(dryRun?: boolean | undefined | null): EvictionReport
+  /** Prevents the entries for `hashes` from being evicted, including ones which are not cached yet */
+  pin(hashes: Array<string>): void
+  unpin(hashes: Array<string>): void
+  getPinnedHashes(): Array<string>
   copyFilesFromCache(cachedResult: CachedResult, outputs: Array<string>): number
+  /**
+   * Gives outputs which were restored as hardlinks into the cache copies of their own,
+   * so that a task which writes its outputs in place cannot change the cache
+   */
+  breakOutputLinks(outputs: Array<string>): void
+  /** Evicts the entries which have not been accessed within the TTL */
   removeOldCacheRecords(): void
   checkCacheFsInSync(): boolean
 }
@@ -87,6 +141,16 @@ export declare class RunningTasksService {
   removeRunningTask(taskId: string): void
 }
 
+/**
+ * Persists the hashes of runtime inputs with `persist` across processes,
+ * keyed by `runtime_cache_key`
+ */
+export declare class RuntimeInputHashes {
+  constructor(db: ExternalObject<NxDbConnection>)
+  getRuntimeHashes(): Record<string, string>
+  recordRuntimeHashes(hashes: Record<string, string>): void
+}
+
 export declare class RustPseudoTerminal {
   constructor()
   runCommand(command: string, commandDir?: string | undefined | null, jsEnv?: Record<string, string> | undefined | null, execArgv?: Array<string> | undefined | null, quiet?: boolean | undefined | null, tty?: boolean | undefined | null, commandLabel?: string | undefined | null): ChildProcess
@@ -100,10 +164,19 @@ export declare class RustPseudoTerminal {
 export declare class TaskDetails {
   constructor(db: ExternalObject<NxDbConnection>)
   recordTaskDetails(tasks: Array<HashedTask>): void
+  /**
+   * Reports which instructions hashed differently for `hash_a` and `hash_b`,
+   * for example to explain why a task did not hit the cache
+   */
+  explainHashDiff(hashA: string, hashB: string): Array<HashInstructionChange>
 }
 
 export declare class TaskHasher {
   constructor(workspaceRoot: string, projectGraph: ExternalObject<ProjectGraph>, projectFileMap: ExternalObject<ProjectFiles>, allWorkspaceFiles: ExternalObject<Array<FileData>>, tsConfig: Buffer, tsConfigPaths: Record<string, Array<string>>, options?: HasherOptions | undefined | null)
+  /** Reuses hashes of runtime inputs which were persisted by other processes, keyed by `runtime_cache_key` */
+  seedRuntimeHashes(hashes: Record<string, string>): void
+  /** Takes the hashes of runtime inputs with `persist` which were computed since the last call */
+  takeRuntimeHashesToPersist(): Record<string, string>
   hashPlans(hashPlans: ExternalObject<Record<string, Array<HashInstruction>>>, jsEnv: Record<string, string>): NapiDashMap
 }
 
@@ -115,16 +188,48 @@ export declare class Watcher {
    * * .git/
    * * node_modules/
    * * .nx/
+   *
+   * Changes are debounced and coalesced, so a burst of changes like a branch switch
+   * is passed to the callback as a single batch with at most one event per file.
    */
-  constructor(origin: string, additionalGlobs?: Array<string> | undefined | null, useIgnore?: boolean | undefined | null)
+  constructor(origin: string, additionalGlobs?: Array<string> | undefined | null, useIgnore?: boolean | undefined | null, options?: WatcherOptions | undefined | null)
+  /** Passes every change to the callback */
   watch(callback: (err: string | null, events: WatchEvent[]) => void): void
+  /**
+   * Passes the changes which match the options to the callback, and returns the id of the subscription.
+   * All subscriptions share the same watcher, which is started by the first one.
+   */
+  subscribe(callback: (err: string | null, events: WatchEvent[]) => void, options?: WatchSubscriptionOptions | undefined | null): number
+  /** Stops passing changes to the subscription. Returns false if there is no such subscription. */
+  unsubscribe(id: number): boolean
+  /** Sets the roots of the projects, by project name, which subscriptions filter projects with */
+  setProjectRoots(projectRoots: Record<string, string>): void
+  /**
+   * Passes the statuses of the watcher to the callback: when it started, when it could not watch every directory,
+   * when changes were lost and the workspace was rescanned, and when the workspace was deleted.
+   * Has to be called before watching to be told that the watcher started, and to recover from overflows,
+   * for which the files which are not ignored are indexed when the watcher starts.
+   */
+  onStatus(callback: (err: string | null, status: WatchStatus) => void): void
   stop(): Promise<void>
 }
 
 export declare class WorkspaceContext {
   workspaceRoot: string
-  constructor(workspaceRoot: string, cacheDir: string)
+  constructor(workspaceRoot: string, cacheDir: string, options?: WorkspaceContextOptions | undefined | null)
+  /**
+   * Errors with `FilesArchiveMismatch` when the file hashes archived in the cache directory
+   * could not be reused, so every file was hashed again. The error is only reported once.
+   */
+  checkFilesArchive(): void
   getWorkspaceFiles(projectRootMap: Record<string, string>): NxWorkspaceFiles
+  /**
+   * Same as `get_workspace_files`, but also keeps the project file map so that
+   * `update_project_files` can update it in place.
+   * Only the project graph computation which owns the context should track the project file map,
+   * e.g. the daemon's, files requested on behalf of others must not replace the map it updates.
+   */
+  trackWorkspaceFiles(projectRootMap: Record<string, string>): NxWorkspaceFiles
   glob(globs: Array<string>, exclude?: Array<string> | undefined | null): Array<string>
   /**
    * Performs multiple glob pattern matches against workspace files in parallel
@@ -136,9 +241,38 @@ export declare class WorkspaceContext {
   hashFilesMatchingGlobs(globGroups: Array<Array<string>>): Array<string>
   hashFilesMatchingGlob(globs: Array<string>, exclude?: Array<string> | undefined | null): string
   incrementalUpdate(updatedFiles: Array<string>, deletedFiles: Array<string>): Record<string, string>
-  updateProjectFiles(projectRootMappings: ProjectRootMappings, projectFiles: ExternalObject<ProjectFiles>, globalFiles: ExternalObject<Array<FileData>>, updatedFiles: Record<string, string>, deletedFiles: Array<string>): UpdatedWorkspaceFiles
+  /**
+   * Updates the project file map kept by the last `track_workspace_files` call in place,
+   * with the files updated and deleted by `incremental_update`.
+   * Returns how the files of each project changed, or nothing when there is no project file map yet.
+   */
+  updateProjectFiles(updatedFiles: Record<string, string>, deletedFiles: Array<string>): ProjectFileMapUpdate | null
+  /**
+   * Copies the project file map kept by `track_workspace_files` for the task hasher.
+   * This copies every file in the workspace, so it should only be called once a hasher needs them.
+   * Returns nothing when the project file map changed since the `generation` of an update,
+   * since the files would not match the project file map of that update anymore.
+   */
+  getProjectFileMapReferences(generation: number): NxWorkspaceFilesExternals | null
   allFileData(): Array<FileData>
   getFilesInDirectory(directory: string): Array<string>
+  /**
+   * Finds the files matching every filter of the query, sorted by path.
+   * Only the page selected by `offset` and `limit` is copied into the result.
+   */
+  queryFiles(query: FilesQuery): FilesQueryResult
+  /** Counts the files matching every filter of the query, ignoring `offset` and `limit` */
+  countFiles(query: FilesQuery): number
+  /**
+   * Groups the files matching the query which have the same content hash.
+   * `offset` and `limit` select groups rather than files.
+   */
+  findDuplicateFiles(query?: FilesQuery | undefined | null): Array<DuplicateFiles>
+  /**
+   * Lints globs like `lint_globs`, and also warns about globs which do not match any file in the workspace.
+   * Globs with interpolations like `{projectRoot}` are only checked against the files once they are interpolated.
+   */
+  lintGlobs(globs: Array<string>): Array<GlobDiagnostic>
 }
 
 export interface CachedResult {
@@ -148,6 +282,65 @@ export interface CachedResult {
   size?: number
 }
 
+/** How cached outputs are restored into the workspace */
+export interface CacheEntry {
+  hash: string
+  code: number
+  /** Size of the outputs and terminal output, including outputs which are shared with other entries */
+  size: number
+  /** How many times the entry was read from the cache */
+  hitCount: number
+  /** Milliseconds since the epoch */
+  createdAt: number
+  /** Milliseconds since the epoch */
+  accessedAt: number
+  /** Missing when the task details were not recorded */
+  project?: string
+  target?: string
+  configuration?: string
+}
+
+/** Narrows down the entries listed from the cache. Every field is optional. */
+export interface CacheEntryFilter {
+  project?: string
+  target?: string
+  configuration?: string
+  /** Only list entries which have not been accessed since this time, in milliseconds since the epoch */
+  accessedBefore?: number
+  /** Entries are listed in descending order, defaults to the most recently accessed first */
+  sortBy?: CacheEntrySort
+  limit?: number
+}
+
+export declare const enum CacheEntrySort {
+  accessedAt = 'accessedAt',
+  createdAt = 'createdAt',
+  hitCount = 'hitCount',
+  size = 'size'
+}
+
+export declare const enum CacheRestoreMode {
+  /** Copy every file out of the cache */
+  copy = 'copy',
+  /**
+   * Try a copy-on-write reflink, then a hardlink, and fall back to a copy.
+   * Hardlinked outputs share their contents with the cache until they are given copies
+   * of their own, see `NxCache::break_output_links`.
+   */
+  link = 'link'
+}
+
+/** Totals over the cache entries of a project, or of a target when `target` is set */
+export interface CacheStats {
+  project?: string
+  target?: string
+  entries: number
+  size: number
+  hitCount: number
+  /** Milliseconds since the epoch */
+  lastAccessedAt: number
+}
+
 export declare export declare function closeDbConnection(connection: ExternalObject<NxDbConnection>): void
 
 export declare export declare function connectToNxDb(cacheDir: string, nxVersion: string, dbName?: string | undefined | null): ExternalObject<NxDbConnection>
@@ -159,6 +352,12 @@ export interface DepsOutputsInput {
   transitive?: boolean
 }
 
+/** Files which have the same contents */
+export interface DuplicateFiles {
+  hash: string
+  files: Array<string>
+}
+
 export interface EnvironmentInput {
   env: string
 }
@@ -166,7 +365,36 @@ export interface EnvironmentInput {
 export declare const enum EventType {
   delete = 'delete',
   update = 'update',
-  create = 'create'
+  create = 'create',
+  rename = 'rename'
+}
+
+export interface EvictionOptions {
+  /** Defaults to `lru` */
+  policy?: EvictionPolicy
+  /**
+   * Entries which have not been accessed for this many seconds are evicted. Defaults to 7 days,
+   * negative values are rejected.
+   */
+  ttlSeconds?: number
+}
+
+/** How cache entries are picked for eviction once the cache grows over its maximum size */
+export declare const enum EvictionPolicy {
+  /** Evict the entries which were accessed the longest time ago first */
+  lru = 'lru',
+  /**
+   * Evict large entries which have not been accessed for a while first,
+   * weighing the size of an entry by the time since it was last accessed
+   */
+  sizeWeighted = 'sizeWeighted'
+}
+
+/** The entries which were, or in a dry run would be, evicted */
+export interface EvictionReport {
+  hashes: Array<string>
+  /** Bytes freed on disk. Outputs which are shared with entries that are kept do not free anything. */
+  freedBytes: number
 }
 
 export declare export declare function expandOutputs(directory: string, entries: Array<string>): Array<string>
@@ -181,20 +409,51 @@ export interface ExternalNode {
   hash?: string
 }
 
+export interface FileChange {
+  path: string
+  change: HashChangeType
+}
+
 export interface FileData {
   file: string
   hash: string
 }
 
-export interface FileMap {
-  projectFileMap: ProjectFiles
-  nonProjectFiles: Array<FileData>
+/** Files which were added, changed or removed in a project, or outside of projects */
+export interface FilesDelta {
+  added: Array<FileData>
+  changed: Array<FileData>
+  removed: Array<string>
 }
 
 export interface FileSetInput {
   fileset: string
 }
 
+/** Filters files of a `WorkspaceContext`. Files have to match every filter which is set. */
+export interface FilesQuery {
+  /** Globs which files have to match, no files match an empty list */
+  include?: Array<string>
+  /** Globs which files must not match */
+  exclude?: Array<string>
+  /** The project which owns the files, according to the last `track_workspace_files` call */
+  project?: string
+  /** The directory which contains the files, relative to the workspace root */
+  directory?: string
+  /** The content hash of the files */
+  hash?: string
+  /** The number of results to skip */
+  offset?: number
+  /** The maximum number of results to return */
+  limit?: number
+}
+
+export interface FilesQueryResult {
+  files: Array<FileData>
+  /** The number of files which matched the query, regardless of `offset` and `limit` */
+  total: number
+}
+
 export declare export declare function findImports(projectFileMap: Record<string, Array<string>>): Array<ImportResult>
 
 export declare export declare function getBinaryTarget(): string
@@ -209,11 +468,46 @@ export declare export declare function getFilesForOutputs(directory: string, ent
 
 export declare export declare function getTransformableOutputs(outputs: Array<string>): Array<string>
 
+/** A problem with a glob, found by `lint_globs` */
+export interface GlobDiagnostic {
+  glob: string
+  /** Byte offset in the glob where the problem starts */
+  start: number
+  /** Byte offset in the glob where the problem ends, exclusive */
+  end: number
+  severity: GlobDiagnosticSeverity
+  message: string
+  /** The whole glob with the problem fixed, when there is an obvious fix */
+  fix?: string
+}
+
+export declare const enum GlobDiagnosticSeverity {
+  /** The glob does not mean what it looks like it means */
+  error = 'error',
+  /** The glob works, but probably not as intended */
+  warning = 'warning'
+}
+
 export declare export declare function hashArray(input: Array<string | undefined | null>): string
 
+export declare const enum HashChangeType {
+  added = 'added',
+  removed = 'removed',
+  changed = 'changed'
+}
+
 export interface HashDetails {
   value: string
   details: Record<string, string>
+  /** Kind of each instruction in `details`, keyed by instruction */
+  kinds: Record<string, HashInstructionKind>
+  /**
+   * Files matched by each file set instruction in detailed mode, keyed by instruction.
+   * Every file is encoded as a `<hash> <path>` line.
+   */
+  files?: Record<string, string>
+  /** Variables matched by each env pattern instruction, like `env:VITE_*`, keyed by instruction */
+  env?: Record<string, Array<string>>
 }
 
 export interface HashedTask {
@@ -221,14 +515,49 @@ export interface HashedTask {
   project: string
   target: string
   configuration?: string
+  /** Hashes of the instructions the task hash was assembled from, keyed by instruction */
+  details?: Record<string, string>
+  /** Kind of each instruction in `details`, see `HashDetails::kinds` */
+  kinds?: Record<string, HashInstructionKind>
+  /** Files matched by file set instructions, see `HashDetails::files` */
+  files?: Record<string, string>
 }
 
 export interface HasherOptions {
   selectivelyHashTsConfig: boolean
+  /** Records the files matched by file set instructions in `HashDetails::files` */
+  detailedFileSets?: boolean
 }
 
 export declare export declare function hashFile(file: string): string | null
 
+/** An instruction which hashed differently for two task hashes */
+export interface HashInstructionChange {
+  instruction: string
+  /** Not known for the command of the task and for details of custom hashers */
+  kind?: HashInstructionKind
+  change: HashChangeType
+  /** Hash of the instruction for the first task hash */
+  before?: string
+  /** Hash of the instruction for the second task hash */
+  after?: string
+  /** Files which changed, when the files matched by a file set were recorded for both task hashes */
+  files?: Array<FileChange>
+}
+
+/** What a hash instruction covers, recorded with its hash when the task is hashed */
+export declare const enum HashInstructionKind {
+  projectFileSet = 'projectFileSet',
+  workspaceFileSet = 'workspaceFileSet',
+  jsonPath = 'jsonPath',
+  projectConfiguration = 'projectConfiguration',
+  tsConfiguration = 'tsConfiguration',
+  environment = 'environment',
+  runtime = 'runtime',
+  external = 'external',
+  taskOutput = 'taskOutput'
+}
+
 export interface InputsInput {
   input: string
   dependencies?: boolean
@@ -237,11 +566,25 @@ export interface InputsInput {
 
 export const IS_WASM: boolean
 
+/**
+ * Finds problems in globs, like unbalanced braces, `**` within a path segment or negations which exclude every file.
+ * Interpolations like `{projectRoot}` are allowed, so the `inputs` and `outputs` of targets can be linted as they are.
+ */
+export declare export declare function lintGlobs(globs: Array<string>): Array<GlobDiagnostic>
+
 export declare export declare function logError(message: string): void
 
 export declare export declare function logInfo(message: string): void
 
 /** Stripped version of the NxJson interface for use in rust */
+/**
+ * Hashes a single value of a JSON file, selected by a JSON pointer after `#`.
+ * For example `{projectRoot}/package.json#/dependencies`.
+ */
+export interface JsonInput {
+  json: string
+}
+
 export interface NxJson {
   namedInputs?: Record<string, Array<JsInputs>>
 }
@@ -260,6 +603,11 @@ export interface NxWorkspaceFilesExternals {
 
 export declare export declare function parseTaskStatus(stringStatus: string): TaskStatus
 
+export interface PrefetchedCacheResult {
+  hash: string
+  result: CachedResult
+}
+
 export interface Project {
   root: string
   namedInputs?: Record<string, Array<JsInputs>>
@@ -267,6 +615,15 @@ export interface Project {
   targets: Record<string, Target>
 }
 
+export interface ProjectFileMapUpdate {
+  /** Identifies the project file map after the update, to copy its files with `get_project_file_map_references` */
+  generation: number
+  /** Projects which had files added, changed or removed, sorted by name */
+  affectedProjects: Array<string>
+  projectFiles: Record<string, FilesDelta>
+  nonProjectFiles: FilesDelta
+}
+
 export interface ProjectGraph {
   nodes: Record<string, Project>
   dependencies: Record<string, Array<string>>
@@ -284,6 +641,20 @@ export declare const enum RunMode {
 
 export interface RuntimeInput {
   runtime: string
+  /** Milliseconds after which the command is killed and hashing fails */
+  timeout?: number
+  /**
+   * Names of the environment variables passed to the command, besides `PATH` and `HOME`.
+   * No other environment variables are passed when this is not set.
+   */
+  env?: Array<string>
+  /** Directory to run the command in, relative to the project root. Defaults to the workspace root. */
+  cwd?: string
+  /**
+   * Keeps the hash in the Nx database so that other processes can reuse it.
+   * Only use this for commands whose output does not change for the same environment.
+   */
+  persist?: boolean
 }
 
 export interface Target {
@@ -362,21 +733,92 @@ export interface TuiConfig {
   autoExit?: boolean | number | undefined
 }
 
-export interface UpdatedWorkspaceFiles {
-  fileMap: FileMap
-  externalReferences: NxWorkspaceFilesExternals
-}
-
 export declare export declare function validateOutputs(outputs: Array<string>): void
 
+export interface WatcherOptions {
+  /**
+   * How long to wait for more changes before calling back, in milliseconds. Defaults to 100.
+   * Events keep being collected while changes come in, but for at most 10 seconds.
+   */
+  debounceMs?: number
+  /** The maximum number of events passed to a single callback */
+  maxBatchCount?: number
+  /** The maximum total length of the paths passed to a single callback, in bytes */
+  maxBatchSize?: number
+  /**
+   * Polls for changes instead of relying on native file system events.
+   * Defaults to polling when the workspace is on a file system which does not report changes natively, like NFS or SSHFS.
+   * Every poll stats each file which is not ignored, so polling costs more with `useIgnore` set to false,
+   * which includes build outputs. Setting `roots` or a longer `pollIntervalMs` limits the cost.
+   */
+  polling?: boolean
+  /** How often to poll for changes, in milliseconds. Defaults to 2000. */
+  pollIntervalMs?: number
+  /**
+   * The directories to watch, relative to the origin. Defaults to the whole origin.
+   * Paths of events stay relative to the origin.
+   */
+  roots?: Array<string>
+  /** How many native events are buffered before changes are lost and the workspace is rescanned. Defaults to 4096. */
+  eventChannelSize?: number
+}
+
 export interface WatchEvent {
   path: string
   type: EventType
+  /** The path a renamed file was moved from, it was moved to `path` */
+  from?: string
+}
+
+export interface WatchStatus {
+  type: WatchStatusType
+  /** The maximum number of watched directories, when the watch limit was reached */
+  limit?: number
+}
+
+export declare const enum WatchStatusType {
+  started = 'started',
+  /** The OS limit of watched directories was reached, so changes in some directories are not reported */
+  watchLimitReached = 'watchLimitReached',
+  /**
+   * Changes were lost because they came in faster than they could be read.
+   * The affected directories are rescanned and the changes found are reported, when there is a status callback.
+   * The files are indexed when a watcher with a status callback starts, so deleted files are found as well.
+   */
+  overflow = 'overflow',
+  rootDeleted = 'rootDeleted'
+}
+
+/** Filters the events passed to a subscription. Files have to match every filter which is set. */
+export interface WatchSubscriptionOptions {
+  /** The directory which contains the files, relative to the workspace root */
+  root?: string
+  /** Globs which files have to match */
+  include?: Array<string>
+  /** Globs which files must not match */
+  exclude?: Array<string>
+  /** The projects which own the files, according to the roots passed to `Watcher.setProjectRoots` */
+  projects?: Array<string>
+  /**
+   * Drops changes to files ignored by `.gitignore` files, which a watcher created with `useIgnore` set to false passes on.
+   * Lets subscriptions to sources and to outputs share one watcher.
+   */
+  useIgnore?: boolean
+}
+
+export interface WorkspaceContextOptions {
+  /**
+   * Reuses the blob ids of files which are unchanged in the git index instead of hashing them,
+   * so that only untracked and modified files are hashed.
+   * Every file is then hashed like git hashes blobs, so file hashes differ from the default mode.
+   */
+  seedFromGitIndex?: boolean
 }
 
 /** Public NAPI error codes that are for Node */
 export declare const enum WorkspaceErrors {
   ParseError = 'ParseError',
-  Generic = 'Generic'
+  Generic = 'Generic',
+  FilesArchiveMismatch = 'FilesArchiveMismatch'
 }
 
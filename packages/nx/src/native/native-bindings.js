@@ -364,30 +364,39 @@ if (!nativeBinding) {
 module.exports.AppLifeCycle = nativeBinding.AppLifeCycle
 module.exports.ChildProcess = nativeBinding.ChildProcess
 module.exports.FileLock = nativeBinding.FileLock
+module.exports.FsRemoteCache = nativeBinding.FsRemoteCache
 module.exports.HashPlanner = nativeBinding.HashPlanner
 module.exports.HttpRemoteCache = nativeBinding.HttpRemoteCache
 module.exports.ImportResult = nativeBinding.ImportResult
 module.exports.NxCache = nativeBinding.NxCache
 module.exports.NxTaskHistory = nativeBinding.NxTaskHistory
 module.exports.RunningTasksService = nativeBinding.RunningTasksService
+module.exports.RuntimeInputHashes = nativeBinding.RuntimeInputHashes
 module.exports.RustPseudoTerminal = nativeBinding.RustPseudoTerminal
 module.exports.TaskDetails = nativeBinding.TaskDetails
 module.exports.TaskHasher = nativeBinding.TaskHasher
 module.exports.Watcher = nativeBinding.Watcher
 module.exports.WorkspaceContext = nativeBinding.WorkspaceContext
+module.exports.CacheEntrySort = nativeBinding.CacheEntrySort
+module.exports.CacheRestoreMode = nativeBinding.CacheRestoreMode
 module.exports.closeDbConnection = nativeBinding.closeDbConnection
 module.exports.connectToNxDb = nativeBinding.connectToNxDb
 module.exports.copy = nativeBinding.copy
 module.exports.EventType = nativeBinding.EventType
+module.exports.EvictionPolicy = nativeBinding.EvictionPolicy
 module.exports.expandOutputs = nativeBinding.expandOutputs
 module.exports.findImports = nativeBinding.findImports
 module.exports.getBinaryTarget = nativeBinding.getBinaryTarget
 module.exports.getDefaultMaxCacheSize = nativeBinding.getDefaultMaxCacheSize
 module.exports.getFilesForOutputs = nativeBinding.getFilesForOutputs
 module.exports.getTransformableOutputs = nativeBinding.getTransformableOutputs
+module.exports.GlobDiagnosticSeverity = nativeBinding.GlobDiagnosticSeverity
 module.exports.hashArray = nativeBinding.hashArray
+module.exports.HashChangeType = nativeBinding.HashChangeType
 module.exports.hashFile = nativeBinding.hashFile
+module.exports.HashInstructionKind = nativeBinding.HashInstructionKind
 module.exports.IS_WASM = nativeBinding.IS_WASM
+module.exports.lintGlobs = nativeBinding.lintGlobs
 module.exports.logError = nativeBinding.logError
 module.exports.logInfo = nativeBinding.logInfo
 module.exports.parseTaskStatus = nativeBinding.parseTaskStatus
@@ -398,4 +407,5 @@ module.exports.TaskStatus = nativeBinding.TaskStatus
 module.exports.testOnlyTransferFileMap = nativeBinding.testOnlyTransferFileMap
 module.exports.transferProjectGraph = nativeBinding.transferProjectGraph
 module.exports.validateOutputs = nativeBinding.validateOutputs
+module.exports.WatchStatusType = nativeBinding.WatchStatusType
 module.exports.WorkspaceErrors = nativeBinding.WorkspaceErrors
@@ -1,6 +1,10 @@
 use crate::native::db::connection::NxDbConnection;
+use crate::native::tasks::hash_diff::{
+    HashInstructionChange, HashInstructionKind, RecordedInstruction, diff_hash_details,
+};
 use napi::bindgen_prelude::*;
-use rusqlite::params;
+use rusqlite::{Connection, params};
+use std::collections::HashMap;
 use tracing::trace;
 
 #[napi(object)]
@@ -10,6 +14,12 @@ pub struct HashedTask {
     pub project: String,
     pub target: String,
     pub configuration: Option<String>,
+    /// Hashes of the instructions the task hash was assembled from, keyed by instruction
+    pub details: Option<HashMap<String, String>>,
+    /// Kind of each instruction in `details`, see `HashDetails::kinds`
+    pub kinds: Option<HashMap<String, HashInstructionKind>>,
+    /// Files matched by file set instructions, see `HashDetails::files`
+    pub files: Option<HashMap<String, String>>,
 }
 
 #[napi]
@@ -29,17 +39,7 @@ impl TaskDetails {
     }
 
     fn setup(&self) -> anyhow::Result<()> {
-        self.db.execute(
-            "CREATE TABLE IF NOT EXISTS task_details (
-                hash    TEXT PRIMARY KEY NOT NULL,
-                project  TEXT NOT NULL,
-                target  TEXT NOT NULL,
-                configuration  TEXT
-            );",
-            params![],
-        )?;
-
-        Ok(())
+        create_task_details_tables(&self.db)
     }
 
     #[napi]
@@ -47,14 +47,105 @@ impl TaskDetails {
         trace!("Recording task details");
         self.db.transaction(|conn| {
             let mut stmt = conn.prepare("INSERT OR REPLACE INTO task_details (hash, project, target, configuration) VALUES (?1, ?2, ?3, ?4)")?;
+            let mut delete_details = conn.prepare("DELETE FROM task_hash_details WHERE hash = ?1")?;
+            let mut insert_detail = conn.prepare(
+                "INSERT INTO task_hash_details (hash, instruction, kind, value, files) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
             for task in tasks.iter() {
                 stmt.execute(
                     params![task.hash, task.project, task.target, task.configuration],
                 )?;
+                if let Some(details) = &task.details {
+                    delete_details.execute(params![task.hash])?;
+                    for (instruction, value) in details {
+                        let kind = task
+                            .kinds
+                            .as_ref()
+                            .and_then(|kinds| kinds.get(instruction))
+                            .map(|kind| kind.as_str());
+                        let files = task.files.as_ref().and_then(|files| files.get(instruction));
+                        insert_detail
+                            .execute(params![task.hash, instruction, kind, value, files])?;
+                    }
+                }
             }
             Ok(())
         })?;
 
         Ok(())
     }
+
+    /// Reports which instructions hashed differently for `hash_a` and `hash_b`,
+    /// for example to explain why a task did not hit the cache
+    #[napi]
+    pub fn explain_hash_diff(
+        &self,
+        hash_a: String,
+        hash_b: String,
+    ) -> anyhow::Result<Vec<HashInstructionChange>> {
+        let before = self.get_hash_details(&hash_a)?;
+        let after = self.get_hash_details(&hash_b)?;
+        Ok(diff_hash_details(&before, &after))
+    }
+
+    fn get_hash_details(&self, hash: &str) -> anyhow::Result<HashMap<String, RecordedInstruction>> {
+        let details = self
+            .db
+            .prepare(
+                "SELECT instruction, kind, value, files FROM task_hash_details WHERE hash = ?1",
+            )?
+            .query_map(params![hash], |row| {
+                Ok((
+                    row.get(0)?,
+                    RecordedInstruction {
+                        kind: row
+                            .get::<_, Option<String>>(1)?
+                            .and_then(|kind| kind.parse().ok()),
+                        value: row.get(2)?,
+                        files: row.get(3)?,
+                    },
+                ))
+            })?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+        if details.is_empty() {
+            anyhow::bail!("No hash details were recorded for {}", hash);
+        }
+        Ok(details)
+    }
+}
+
+/// Creates the tables of `TaskDetails`, which are also set up by `NxCache` when it links
+/// its entries to task details
+pub(crate) fn create_task_details_tables(db: &NxDbConnection) -> anyhow::Result<()> {
+    db.execute(
+        "CREATE TABLE IF NOT EXISTS task_details (
+            hash    TEXT PRIMARY KEY NOT NULL,
+            project  TEXT NOT NULL,
+            target  TEXT NOT NULL,
+            configuration  TEXT
+        );",
+        params![],
+    )?;
+    db.execute(
+        "CREATE TABLE IF NOT EXISTS task_hash_details (
+            hash    TEXT NOT NULL,
+            instruction  TEXT NOT NULL,
+            kind  TEXT,
+            value  TEXT NOT NULL,
+            files  TEXT,
+            PRIMARY KEY (hash, instruction),
+            FOREIGN KEY (hash) REFERENCES task_details (hash)
+        );",
+        params![],
+    )?;
+
+    Ok(())
+}
+
+/// Deletes the hash details of a task hash, for example when its cache entry is evicted
+pub(crate) fn delete_hash_details(conn: &Connection, hash: &str) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM task_hash_details WHERE hash = ?1",
+        params![hash],
+    )
 }
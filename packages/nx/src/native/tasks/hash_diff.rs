@@ -0,0 +1,380 @@
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+use crate::native::tasks::hashers::decode_file_list;
+use crate::native::tasks::types::HashInstruction;
+
+/// What a hash instruction covers, recorded with its hash when the task is hashed
+#[napi(string_enum)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum HashInstructionKind {
+    #[allow(non_camel_case_types)]
+    projectFileSet,
+    #[allow(non_camel_case_types)]
+    workspaceFileSet,
+    #[allow(non_camel_case_types)]
+    jsonPath,
+    #[allow(non_camel_case_types)]
+    projectConfiguration,
+    #[allow(non_camel_case_types)]
+    tsConfiguration,
+    #[allow(non_camel_case_types)]
+    environment,
+    #[allow(non_camel_case_types)]
+    runtime,
+    #[allow(non_camel_case_types)]
+    external,
+    #[allow(non_camel_case_types)]
+    taskOutput,
+}
+
+impl From<&HashInstruction> for HashInstructionKind {
+    fn from(instruction: &HashInstruction) -> Self {
+        match instruction {
+            HashInstruction::ProjectFileSet(..) => HashInstructionKind::projectFileSet,
+            HashInstruction::WorkspaceFileSet(_) => HashInstructionKind::workspaceFileSet,
+            HashInstruction::JsonPath(..) => HashInstructionKind::jsonPath,
+            HashInstruction::ProjectConfiguration(_) => HashInstructionKind::projectConfiguration,
+            HashInstruction::TsConfiguration(_) => HashInstructionKind::tsConfiguration,
+            HashInstruction::Environment(_) | HashInstruction::EnvironmentPattern(_) => {
+                HashInstructionKind::environment
+            }
+            HashInstruction::Runtime(_) => HashInstructionKind::runtime,
+            HashInstruction::External(_) | HashInstruction::AllExternalDependencies => {
+                HashInstructionKind::external
+            }
+            HashInstruction::TaskOutput(..) => HashInstructionKind::taskOutput,
+        }
+    }
+}
+
+impl HashInstructionKind {
+    /// The name of the kind, as it is stored in `task_hash_details`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashInstructionKind::projectFileSet => "projectFileSet",
+            HashInstructionKind::workspaceFileSet => "workspaceFileSet",
+            HashInstructionKind::jsonPath => "jsonPath",
+            HashInstructionKind::projectConfiguration => "projectConfiguration",
+            HashInstructionKind::tsConfiguration => "tsConfiguration",
+            HashInstructionKind::environment => "environment",
+            HashInstructionKind::runtime => "runtime",
+            HashInstructionKind::external => "external",
+            HashInstructionKind::taskOutput => "taskOutput",
+        }
+    }
+}
+
+impl FromStr for HashInstructionKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "projectFileSet" => Ok(HashInstructionKind::projectFileSet),
+            "workspaceFileSet" => Ok(HashInstructionKind::workspaceFileSet),
+            "jsonPath" => Ok(HashInstructionKind::jsonPath),
+            "projectConfiguration" => Ok(HashInstructionKind::projectConfiguration),
+            "tsConfiguration" => Ok(HashInstructionKind::tsConfiguration),
+            "environment" => Ok(HashInstructionKind::environment),
+            "runtime" => Ok(HashInstructionKind::runtime),
+            "external" => Ok(HashInstructionKind::external),
+            "taskOutput" => Ok(HashInstructionKind::taskOutput),
+            _ => Err(anyhow::anyhow!("Unknown hash instruction kind: {}", s)),
+        }
+    }
+}
+
+#[napi(string_enum)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum HashChangeType {
+    #[allow(non_camel_case_types)]
+    added,
+    #[allow(non_camel_case_types)]
+    removed,
+    #[allow(non_camel_case_types)]
+    changed,
+}
+
+/// An instruction which hashed differently for two task hashes
+#[napi(object)]
+#[derive(Debug, PartialEq, Eq)]
+pub struct HashInstructionChange {
+    pub instruction: String,
+    /// Not known for the command of the task and for details of custom hashers
+    pub kind: Option<HashInstructionKind>,
+    pub change: HashChangeType,
+    /// Hash of the instruction for the first task hash
+    pub before: Option<String>,
+    /// Hash of the instruction for the second task hash
+    pub after: Option<String>,
+    /// Files which changed, when the files matched by a file set were recorded for both task hashes
+    pub files: Option<Vec<FileChange>>,
+}
+
+#[napi(object)]
+#[derive(Debug, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: String,
+    pub change: HashChangeType,
+}
+
+/// The hash of an instruction as it was recorded for a task hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedInstruction {
+    pub value: String,
+    pub kind: Option<HashInstructionKind>,
+    /// Files matched by a file set instruction, encoded with `encode_file_list`
+    pub files: Option<String>,
+}
+
+/// Compares the hash details of two task hashes, keyed by instruction
+pub fn diff_hash_details(
+    before: &HashMap<String, RecordedInstruction>,
+    after: &HashMap<String, RecordedInstruction>,
+) -> Vec<HashInstructionChange> {
+    before
+        .keys()
+        .chain(after.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|instruction| {
+            let before = before.get(instruction);
+            let after = after.get(instruction);
+            let change = match (before, after) {
+                (Some(before), Some(after)) if before.value == after.value => return None,
+                (Some(_), Some(_)) => HashChangeType::changed,
+                (None, _) => HashChangeType::added,
+                (_, None) => HashChangeType::removed,
+            };
+            let files = match (
+                before.and_then(|b| b.files.as_deref()),
+                after.and_then(|a| a.files.as_deref()),
+            ) {
+                (Some(before), Some(after)) => Some(diff_file_lists(before, after)),
+                _ => None,
+            };
+            Some(HashInstructionChange {
+                instruction: instruction.clone(),
+                kind: after.or(before).and_then(|recorded| recorded.kind),
+                change,
+                before: before.map(|b| b.value.clone()),
+                after: after.map(|a| a.value.clone()),
+                files,
+            })
+        })
+        .collect()
+}
+
+fn diff_file_lists(before: &str, after: &str) -> Vec<FileChange> {
+    let before = decode_file_list(before).collect::<HashMap<_, _>>();
+    let after = decode_file_list(after).collect::<HashMap<_, _>>();
+    before
+        .keys()
+        .chain(after.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|path| {
+            let change = match (before.get(path), after.get(path)) {
+                (Some(before), Some(after)) if before == after => return None,
+                (Some(_), Some(_)) => HashChangeType::changed,
+                (None, _) => HashChangeType::added,
+                (_, None) => HashChangeType::removed,
+            };
+            Some(FileChange {
+                path: path.to_string(),
+                change,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_get_the_kind_of_instructions() {
+        let kinds = [
+            (
+                HashInstruction::ProjectFileSet(
+                    "app".into(),
+                    vec!["{projectRoot}/**/*".into(), "!{projectRoot}/**/*.md".into()],
+                ),
+                HashInstructionKind::projectFileSet,
+            ),
+            (
+                HashInstruction::ProjectConfiguration("npm".into()),
+                HashInstructionKind::projectConfiguration,
+            ),
+            (
+                HashInstruction::ProjectFileSet("env".into(), vec!["{projectRoot}/**/*".into()]),
+                HashInstructionKind::projectFileSet,
+            ),
+            (
+                HashInstruction::WorkspaceFileSet(vec!["{workspaceRoot}/nx.json".into()]),
+                HashInstructionKind::workspaceFileSet,
+            ),
+            (
+                HashInstruction::JsonPath("apps/app/package.json".into(), "/dependencies".into()),
+                HashInstructionKind::jsonPath,
+            ),
+            (
+                HashInstruction::ProjectConfiguration("app".into()),
+                HashInstructionKind::projectConfiguration,
+            ),
+            (
+                HashInstruction::TsConfiguration("app".into()),
+                HashInstructionKind::tsConfiguration,
+            ),
+            (
+                HashInstruction::Environment("CI".into()),
+                HashInstructionKind::environment,
+            ),
+            (
+                HashInstruction::EnvironmentPattern("VITE_*".into()),
+                HashInstructionKind::environment,
+            ),
+            (
+                HashInstruction::Runtime("node -v".into()),
+                HashInstructionKind::runtime,
+            ),
+            (
+                HashInstruction::External("npm:react".into()),
+                HashInstructionKind::external,
+            ),
+            (
+                HashInstruction::AllExternalDependencies,
+                HashInstructionKind::external,
+            ),
+            (
+                HashInstruction::TaskOutput("dist/**/*.d.ts".into(), vec!["dist/libs/lib".into()]),
+                HashInstructionKind::taskOutput,
+            ),
+        ];
+
+        for (instruction, kind) in kinds {
+            assert_eq!(
+                HashInstructionKind::from(&instruction),
+                kind,
+                "{}",
+                instruction
+            );
+            assert_eq!(
+                kind.as_str().parse::<HashInstructionKind>().ok(),
+                Some(kind)
+            );
+        }
+    }
+
+    fn recorded(
+        value: &str,
+        kind: Option<HashInstructionKind>,
+        files: Option<&str>,
+    ) -> RecordedInstruction {
+        RecordedInstruction {
+            value: value.into(),
+            kind,
+            files: files.map(String::from),
+        }
+    }
+
+    #[test]
+    fn should_diff_hash_details() {
+        let before = HashMap::from([
+            (
+                "env:CI".to_string(),
+                recorded("1", Some(HashInstructionKind::environment), None),
+            ),
+            (
+                "app:ProjectConfiguration".to_string(),
+                recorded("2", Some(HashInstructionKind::projectConfiguration), None),
+            ),
+            (
+                "npm:react".to_string(),
+                recorded("3", Some(HashInstructionKind::external), None),
+            ),
+        ]);
+        let after = HashMap::from([
+            (
+                "env:CI".to_string(),
+                recorded("4", Some(HashInstructionKind::environment), None),
+            ),
+            (
+                "app:ProjectConfiguration".to_string(),
+                recorded("2", Some(HashInstructionKind::projectConfiguration), None),
+            ),
+            ("command".to_string(), recorded("5", None, None)),
+        ]);
+
+        assert_eq!(
+            diff_hash_details(&before, &after),
+            vec![
+                HashInstructionChange {
+                    instruction: "command".into(),
+                    kind: None,
+                    change: HashChangeType::added,
+                    before: None,
+                    after: Some("5".into()),
+                    files: None,
+                },
+                HashInstructionChange {
+                    instruction: "env:CI".into(),
+                    kind: Some(HashInstructionKind::environment),
+                    change: HashChangeType::changed,
+                    before: Some("1".into()),
+                    after: Some("4".into()),
+                    files: None,
+                },
+                HashInstructionChange {
+                    instruction: "npm:react".into(),
+                    kind: Some(HashInstructionKind::external),
+                    change: HashChangeType::removed,
+                    before: Some("3".into()),
+                    after: None,
+                    files: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_diff_the_files_of_file_sets() {
+        let instruction = "app:{projectRoot}/**/*".to_string();
+        let before = HashMap::from([(
+            instruction.clone(),
+            recorded(
+                "1",
+                Some(HashInstructionKind::projectFileSet),
+                Some("1 app/a.ts\n2 app/b.ts\n3 app/c.ts\n"),
+            ),
+        )]);
+        let after = HashMap::from([(
+            instruction.clone(),
+            recorded(
+                "2",
+                Some(HashInstructionKind::projectFileSet),
+                Some("1 app/a.ts\n4 app/b.ts\n5 app/d.ts\n"),
+            ),
+        )]);
+
+        let changes = diff_hash_details(&before, &after);
+
+        assert_eq!(
+            changes[0].files,
+            Some(vec![
+                FileChange {
+                    path: "app/b.ts".into(),
+                    change: HashChangeType::changed,
+                },
+                FileChange {
+                    path: "app/c.ts".into(),
+                    change: HashChangeType::removed,
+                },
+                FileChange {
+                    path: "app/d.ts".into(),
+                    change: HashChangeType::added,
+                },
+            ])
+        );
+    }
+}
@@ -1,9 +1,9 @@
 use crate::native::logger::enable_logger;
 use crate::native::tasks::{
     dep_outputs::get_dep_output,
-    types::{HashInstruction, TaskGraph},
+    types::{HashInstruction, RuntimeCommand, TaskGraph},
 };
-use crate::native::types::{Input, NxJson};
+use crate::native::types::{Input, NxJson, RuntimeInput};
 use crate::native::{
     project_graph::types::ProjectGraph,
     tasks::{inputs::SplitInputs, types::Task},
@@ -13,6 +13,7 @@ use rayon::prelude::*;
 use std::collections::HashMap;
 use tracing::trace;
 
+use crate::native::tasks::hashers::is_env_pattern;
 use crate::native::tasks::inputs::{
     expand_single_project_inputs, get_inputs, get_inputs_for_dependency, get_named_inputs,
 };
@@ -359,8 +360,14 @@ impl HashPlanner {
             )]
         };
         let runtime_and_env_inputs = self_inputs.iter().filter_map(|i| match i {
-            Input::Runtime(runtime) => Some(HashInstruction::Runtime(runtime.to_string())),
+            Input::Runtime(runtime) => Some(HashInstruction::Runtime(
+                self.runtime_command(project_name, runtime),
+            )),
+            Input::Environment(env) if is_env_pattern(env) => {
+                Some(HashInstruction::EnvironmentPattern(env.to_string()))
+            }
             Input::Environment(env) => Some(HashInstruction::Environment(env.to_string())),
+            Input::Json(json) => Some(self.json_path(project_name, json)),
             _ => None,
         });
 
@@ -371,6 +378,50 @@ impl HashPlanner {
             .collect()
     }
 
+    fn json_path(&self, project_name: &str, json: &str) -> HashInstruction {
+        let (file, pointer) = json.split_once('#').unwrap_or((json, ""));
+        let project_root = self
+            .project_graph
+            .nodes
+            .get(project_name)
+            .map(|project| project.root.as_str())
+            .unwrap_or(".");
+        let file = match file.strip_prefix("{workspaceRoot}/") {
+            Some(file) => file.to_string(),
+            None if project_root == "." => file.replace("{projectRoot}/", ""),
+            None => file.replace("{projectRoot}", project_root),
+        };
+        HashInstruction::JsonPath(file, pointer.to_string())
+    }
+
+    fn runtime_command(&self, project_name: &str, runtime: &RuntimeInput) -> RuntimeCommand {
+        let cwd = runtime.cwd.as_deref().map(|cwd| {
+            let project_root = self
+                .project_graph
+                .nodes
+                .get(project_name)
+                .map(|project| project.root.as_str())
+                .unwrap_or(".");
+            match cwd.trim_start_matches("./").trim_end_matches('/') {
+                "" | "." => project_root.to_string(),
+                cwd => format!("{}/{}", project_root, cwd),
+            }
+        });
+        let env = runtime.env.as_ref().map(|env| {
+            let mut env = env.clone();
+            env.sort();
+            env.dedup();
+            env
+        });
+        RuntimeCommand {
+            command: runtime.runtime.clone(),
+            cwd,
+            env,
+            timeout: runtime.timeout,
+            persist: runtime.persist.unwrap_or(false),
+        }
+    }
+
     fn gather_dependency_outputs(
         &self,
         task: &Task,
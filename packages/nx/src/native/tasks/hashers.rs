@@ -1,5 +1,7 @@
+mod file_set;
 mod hash_env;
 mod hash_external;
+mod hash_json;
 mod hash_project_config;
 mod hash_project_files;
 mod hash_runtime;
@@ -7,8 +9,10 @@ mod hash_task_output;
 mod hash_tsconfig;
 mod hash_workspace_files;
 
+pub use file_set::*;
 pub use hash_env::*;
 pub use hash_external::*;
+pub use hash_json::*;
 pub use hash_project_config::*;
 pub use hash_project_files::*;
 pub use hash_runtime::*;
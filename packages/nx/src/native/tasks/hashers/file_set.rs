@@ -0,0 +1,63 @@
+use crate::native::types::FileData;
+
+/// The hash of a file set instruction, along with the files it matched when they are recorded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSetHash {
+    pub hash: String,
+    /// See [`encode_file_list`]
+    pub files: Option<String>,
+}
+
+/// Encodes files compactly as one `<hash> <path>` line per file.
+/// File hashes never contain spaces, so everything after the first space is the path.
+pub fn encode_file_list<'a>(files: impl IntoIterator<Item = &'a FileData>) -> String {
+    let mut encoded = String::new();
+    for file in files {
+        encoded.push_str(&file.hash);
+        encoded.push(' ');
+        encoded.push_str(&file.file);
+        encoded.push('\n');
+    }
+    encoded
+}
+
+/// Decodes a file list created by [`encode_file_list`] into `(path, hash)` pairs
+pub fn decode_file_list(encoded: &str) -> impl Iterator<Item = (&str, &str)> {
+    encoded.lines().filter_map(|line| {
+        let (hash, path) = line.split_once(' ')?;
+        Some((path, hash))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_round_trip_file_lists() {
+        let files = [
+            FileData {
+                file: "libs/lib/src/index.ts".into(),
+                hash: "123".into(),
+            },
+            FileData {
+                file: "libs/lib/src/a file with spaces.ts".into(),
+                hash: "456".into(),
+            },
+        ];
+
+        let encoded = encode_file_list(&files);
+
+        assert_eq!(
+            encoded,
+            "123 libs/lib/src/index.ts\n456 libs/lib/src/a file with spaces.ts\n"
+        );
+        assert_eq!(
+            decode_file_list(&encoded).collect::<Vec<_>>(),
+            vec![
+                ("libs/lib/src/index.ts", "123"),
+                ("libs/lib/src/a file with spaces.ts", "456")
+            ]
+        );
+    }
+}
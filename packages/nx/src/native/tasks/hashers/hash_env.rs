@@ -1,11 +1,57 @@
 use crate::native::hasher::hash;
+use globset::{Glob, GlobMatcher};
 use std::collections::HashMap;
+use xxhash_rust::xxh3::Xxh3;
 
 pub fn hash_env(env_name: &str, env: &HashMap<String, String>) -> String {
     let env_value = env.get(env_name).map(|s| s.as_str()).unwrap_or("");
     hash(env_value.as_bytes())
 }
 
+/// Whether an env input matches a family of variables, like `VITE_*`, rather than a single one
+pub fn is_env_pattern(env_name: &str) -> bool {
+    env_name.contains(['*', '?', '['])
+}
+
+pub fn env_pattern_matcher(pattern: &str) -> anyhow::Result<GlobMatcher> {
+    Ok(Glob::new(pattern)
+        .map_err(|e| anyhow::anyhow!("\"{}\" is an invalid env pattern: {}", pattern, e))?
+        .compile_matcher())
+}
+
+pub struct EnvPatternHash {
+    pub hash: String,
+    /// The variables matched by the pattern, sorted by name
+    pub variables: Vec<String>,
+}
+
+/// Hashes the names and values of every variable matching `pattern`, in sorted order
+pub fn hash_env_pattern(
+    pattern: &str,
+    env: &HashMap<String, String>,
+) -> anyhow::Result<EnvPatternHash> {
+    let matcher = env_pattern_matcher(pattern)?;
+    let mut variables = env
+        .keys()
+        .filter(|name| matcher.is_match(name.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    variables.sort();
+
+    let mut hasher = Xxh3::new();
+    for name in &variables {
+        hasher.update(name.as_bytes());
+        hasher.update(b"=");
+        hasher.update(env[name].as_bytes());
+        hasher.update(b"\0");
+    }
+
+    Ok(EnvPatternHash {
+        hash: hasher.digest().to_string(),
+        variables,
+    })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -27,4 +73,30 @@ mod test {
 
         assert_eq!(hash, "3244421341483603138");
     }
+
+    #[test]
+    fn should_hash_every_variable_matching_a_pattern() {
+        let env = HashMap::from([
+            ("VITE_B".to_string(), "b".to_string()),
+            ("VITE_A".to_string(), "a".to_string()),
+            ("NEXT_PUBLIC_A".to_string(), "a".to_string()),
+        ]);
+
+        let hashed = hash_env_pattern("VITE_*", &env).unwrap();
+        assert_eq!(hashed.variables, vec!["VITE_A", "VITE_B"]);
+
+        let mut changed = env.clone();
+        changed.insert("VITE_B".to_string(), "c".to_string());
+        assert_ne!(
+            hash_env_pattern("VITE_*", &changed).unwrap().hash,
+            hashed.hash
+        );
+
+        let mut unrelated = env.clone();
+        unrelated.insert("NEXT_PUBLIC_A".to_string(), "c".to_string());
+        assert_eq!(
+            hash_env_pattern("VITE_*", &unrelated).unwrap().hash,
+            hashed.hash
+        );
+    }
 }
@@ -0,0 +1,103 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::*;
+use dashmap::DashMap;
+use serde_json::Value;
+
+use crate::native::hasher::hash;
+
+/// Hashes the value at a JSON pointer, like `/dependencies`, in a JSON file.
+/// Keys are sorted, so only changes to the value itself change the hash.
+/// A missing file or value hashes the same as an empty string.
+pub fn hash_json_path(
+    workspace_root: &str,
+    file: &str,
+    pointer: &str,
+    cache: Arc<DashMap<String, String>>,
+) -> Result<String> {
+    let cache_key = format!("{}#{}", file, pointer);
+    if let Some(cached) = cache.get(&cache_key) {
+        return Ok(cached.clone());
+    }
+
+    let path = Path::new(workspace_root).join(file);
+    let value = if path.exists() {
+        let contents = std::fs::read(&path)?;
+        let json: Value = serde_json::from_slice(&contents)
+            .map_err(|e| anyhow!("Failed to parse {} as JSON: {}", file, e))?;
+        json.pointer(pointer)
+            .map(serde_json::to_string)
+            .transpose()?
+    } else {
+        None
+    };
+    let hashed = hash(value.unwrap_or_default().as_bytes());
+
+    cache.insert(cache_key, hashed.clone());
+    Ok(hashed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    fn hash_package_json(contents: &str, pointer: &str) -> String {
+        let temp = TempDir::new().unwrap();
+        temp.child("libs/lib/package.json")
+            .write_str(contents)
+            .unwrap();
+        hash_json_path(
+            temp.display().to_string().as_str(),
+            "libs/lib/package.json",
+            pointer,
+            Arc::new(DashMap::new()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_only_hash_the_selected_value() {
+        let hashed = hash_package_json(
+            r#"{ "version": "1.0.0", "dependencies": { "a": "1", "b": "2" } }"#,
+            "/dependencies",
+        );
+
+        assert_eq!(
+            hash_package_json(
+                r#"{ "version": "2.0.0", "dependencies": { "b": "2", "a": "1" } }"#,
+                "/dependencies",
+            ),
+            hashed
+        );
+        assert_ne!(
+            hash_package_json(
+                r#"{ "version": "1.0.0", "dependencies": { "a": "1", "b": "3" } }"#,
+                "/dependencies",
+            ),
+            hashed
+        );
+    }
+
+    #[test]
+    fn should_hash_nested_and_missing_values() {
+        let hashed = hash_package_json(
+            r#"{ "scripts": { "build": "tsc", "test": "jest" } }"#,
+            "/scripts/build",
+        );
+
+        assert_eq!(
+            hashed,
+            hash_package_json(
+                r#"{ "scripts": { "build": "tsc", "test": "vitest" } }"#,
+                "/scripts/build",
+            )
+        );
+        assert_eq!(
+            hash_package_json(r#"{ "scripts": {} }"#, "/scripts/build"),
+            hash(b"")
+        );
+    }
+}
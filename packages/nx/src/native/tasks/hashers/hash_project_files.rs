@@ -4,6 +4,7 @@ use anyhow::*;
 use tracing::{trace, trace_span};
 
 use crate::native::glob::build_glob_set;
+use crate::native::tasks::hashers::{FileSetHash, encode_file_list};
 use crate::native::types::FileData;
 
 pub fn hash_project_files(
@@ -11,16 +12,20 @@ pub fn hash_project_files(
     project_root: &str,
     file_sets: &[String],
     project_file_map: &HashMap<String, Vec<FileData>>,
-) -> Result<String> {
+    record_files: bool,
+) -> Result<FileSetHash> {
     let _span = trace_span!("hash_project_files", project_name).entered();
     let collected_files = collect_files(project_name, project_root, file_sets, project_file_map)?;
     trace!("collected_files: {:?}", collected_files.len());
     let mut hasher = xxhash_rust::xxh3::Xxh3::new();
-    for file in collected_files {
+    for file in collected_files.iter() {
         hasher.update(file.hash.as_bytes());
         hasher.update(file.file.as_bytes());
     }
-    Ok(hasher.digest().to_string())
+    Ok(FileSetHash {
+        hash: hasher.digest().to_string(),
+        files: record_files.then(|| encode_file_list(collected_files)),
+    })
 }
 
 /// base function that should be testable (to make sure that we're getting the proper files back)
@@ -155,9 +160,11 @@ mod tests {
                 file_data4.clone(),
             ],
         );
-        let hash_result = hash_project_files(proj_name, proj_root, file_sets, &file_map).unwrap();
+        let hash_result =
+            hash_project_files(proj_name, proj_root, file_sets, &file_map, false).unwrap();
+        assert_eq!(hash_result.files, None);
         assert_eq!(
-            hash_result,
+            hash_result.hash,
             hash(
                 &[
                     file_data1.hash.as_bytes(),
@@ -205,9 +212,11 @@ mod tests {
                 file_data4.clone(),
             ],
         );
-        let hash_result = hash_project_files(proj_name, proj_root, file_sets, &file_map).unwrap();
+        let hash_result =
+            hash_project_files(proj_name, proj_root, file_sets, &file_map, false).unwrap();
+        assert_eq!(hash_result.files, None);
         assert_eq!(
-            hash_result,
+            hash_result.hash,
             hash(
                 &[
                     file_data1.hash.as_bytes(),
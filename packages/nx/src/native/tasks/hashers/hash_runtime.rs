@@ -1,9 +1,16 @@
 use crate::native::hasher::hash;
+use crate::native::tasks::types::RuntimeCommand;
 use dashmap::DashMap;
-use std::collections::HashMap;
-use std::process::Command;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
 use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use thiserror::Error;
 use tracing::trace;
+use xxhash_rust::xxh3::Xxh3;
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
@@ -12,41 +19,188 @@ use std::os::windows::process::CommandExt;
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
+/// Passed to every command so that it can find executables, other variables are only passed
+/// when they are in the env allowlist of the runtime input
+#[cfg(not(target_os = "windows"))]
+const DEFAULT_ENV: &[&str] = &["PATH", "HOME"];
+#[cfg(target_os = "windows")]
+const DEFAULT_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "PATHEXT",
+    "SYSTEMROOT",
+    "COMSPEC",
+    "USERPROFILE",
+];
+
+#[derive(Debug, Error)]
+pub enum RuntimeInputError {
+    #[error("Failed to execute: '{command}'\n{source}")]
+    Spawn {
+        command: String,
+        source: std::io::Error,
+    },
+    #[error("Runtime input '{command}' exited with {}\n{stderr}", describe_exit_code(*.code))]
+    NonZeroExit {
+        command: String,
+        /// Missing when the command was terminated by a signal
+        code: Option<i32>,
+        stderr: String,
+    },
+    #[error("Runtime input '{command}' did not finish within {timeout}ms")]
+    TimedOut { command: String, timeout: u32 },
+}
+
+fn describe_exit_code(code: Option<i32>) -> String {
+    code.map(|code| format!("code {}", code))
+        .unwrap_or_else(|| "a signal".to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct RuntimeHash {
+    pub hash: String,
+    /// Set for hashes of commands with `persist` until they are taken to be persisted
+    pub persist: bool,
+}
+
 pub fn hash_runtime(
     workspace_root: &str,
-    command: &str,
+    runtime: &RuntimeCommand,
     env: &HashMap<String, String>,
-    cache: Arc<DashMap<String, String>>,
+    cache: Arc<DashMap<String, RuntimeHash>>,
 ) -> anyhow::Result<String> {
-    let cache_key = format!("{}-{:?}", command, env);
+    let env = command_env(runtime, env);
+    let cache_key = runtime_cache_key(runtime, &env);
 
     if let Some(cache_results) = cache.get(&cache_key) {
-        return Ok(cache_results.clone());
+        return Ok(cache_results.hash.clone());
     }
 
     let mut command_builder = create_command_builder();
 
-    command_builder.arg(command);
+    command_builder.arg(&runtime.command);
 
-    command_builder.current_dir(workspace_root);
-    env.iter().for_each(|(key, value)| {
-        command_builder.env(key, value);
+    let cwd = Path::new(workspace_root);
+    command_builder.current_dir(match &runtime.cwd {
+        Some(project_cwd) => cwd.join(project_cwd),
+        None => cwd.to_path_buf(),
     });
+    command_builder.env_clear();
+    command_builder.envs(&env);
     trace!("executing: {:?}", command_builder);
-    let output = command_builder
-        .output()
-        .map_err(|e| anyhow::anyhow!("Failed to execute: '{}'\n{}", command, e))?;
-    trace!("{} output: {:?}", command, output);
+    let output = run_command(command_builder, runtime)?;
+    trace!("{} output: {:?}", runtime.command, output);
 
     let std_out = std::str::from_utf8(&output.stdout)?.trim();
     let std_err = std::str::from_utf8(&output.stderr)?.trim();
+    if !output.status.success() {
+        return Err(RuntimeInputError::NonZeroExit {
+            command: runtime.command.clone(),
+            code: output.status.code(),
+            stderr: std_err.to_string(),
+        }
+        .into());
+    }
     let hash_result = hash(&[std_out.as_bytes(), std_err.as_bytes()].concat());
 
-    cache.insert(cache_key, hash_result.clone());
+    cache.insert(
+        cache_key,
+        RuntimeHash {
+            hash: hash_result.clone(),
+            persist: runtime.persist,
+        },
+    );
 
     Ok(hash_result)
 }
 
+/// The environment variables passed to a command, sorted by name
+fn command_env<'a>(
+    runtime: &RuntimeCommand,
+    env: &'a HashMap<String, String>,
+) -> BTreeMap<&'a str, &'a str> {
+    env.iter()
+        .filter(|(key, _)| {
+            runtime.env.iter().flatten().any(|allowed| allowed == *key)
+                || DEFAULT_ENV
+                    .iter()
+                    .any(|default| default.eq_ignore_ascii_case(key))
+        })
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect()
+}
+
+/// Identifies the output of a command across processes:
+/// the same command, run in the same directory with the same environment, gets the same key
+pub fn runtime_cache_key(runtime: &RuntimeCommand, env: &BTreeMap<&str, &str>) -> String {
+    let mut hasher = Xxh3::new();
+    hasher.update(runtime.command.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(runtime.cwd.as_deref().unwrap_or_default().as_bytes());
+    for (key, value) in env {
+        hasher.update(b"\0");
+        hasher.update(key.as_bytes());
+        hasher.update(b"=");
+        hasher.update(value.as_bytes());
+    }
+    hasher.digest().to_string()
+}
+
+fn run_command(
+    mut command: Command,
+    runtime: &RuntimeCommand,
+) -> Result<Output, RuntimeInputError> {
+    let spawn_error = |source| RuntimeInputError::Spawn {
+        command: runtime.command.clone(),
+        source,
+    };
+    let Some(timeout) = runtime.timeout else {
+        return command.output().map_err(spawn_error);
+    };
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(spawn_error)?;
+    // The pipes are drained while waiting so that the command does not block on a full pipe
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let deadline = Instant::now() + Duration::from_millis(timeout as u64);
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(spawn_error)? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(RuntimeInputError::TimedOut {
+                command: runtime.command.clone(),
+                timeout,
+            });
+        }
+        thread::sleep(Duration::from_millis(5));
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut output = vec![];
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut output);
+        }
+        output
+    })
+}
+
 #[cfg(target_os = "windows")]
 pub fn create_command_builder() -> Command {
     let comspec = std::env::var("COMSPEC");
@@ -77,11 +231,137 @@ mod tests {
     #[test]
     fn test_hash_runtime() {
         let workspace_root = "/tmp";
-        let command = "echo 'runtime'";
+        let command = "echo 'runtime'".into();
         let env: HashMap<String, String> = HashMap::new();
         let cache = Arc::new(DashMap::new());
 
-        let result = hash_runtime(workspace_root, command, &env, Arc::clone(&cache)).unwrap();
+        let result = hash_runtime(workspace_root, &command, &env, Arc::clone(&cache)).unwrap();
         assert_eq!(result, "10571312846059850300");
     }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn should_report_non_zero_exit_codes() {
+        let cache = Arc::new(DashMap::new());
+
+        let error = hash_runtime(
+            "/tmp",
+            &"echo 'broken' >&2; exit 3".into(),
+            &HashMap::new(),
+            Arc::clone(&cache),
+        )
+        .unwrap_err();
+
+        match error.downcast::<RuntimeInputError>().unwrap() {
+            RuntimeInputError::NonZeroExit { code, stderr, .. } => {
+                assert_eq!(code, Some(3));
+                assert_eq!(stderr, "broken");
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+        assert!(cache.is_empty());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn should_time_out() {
+        let runtime = RuntimeCommand {
+            timeout: Some(50),
+            ..RuntimeCommand::from("sleep 5")
+        };
+
+        let start = Instant::now();
+        let error =
+            hash_runtime("/tmp", &runtime, &HashMap::new(), Arc::new(DashMap::new())).unwrap_err();
+
+        assert!(matches!(
+            error.downcast::<RuntimeInputError>().unwrap(),
+            RuntimeInputError::TimedOut { timeout: 50, .. }
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn should_only_pass_allowed_env() {
+        let env = HashMap::from([
+            ("ALLOWED".to_string(), "a".to_string()),
+            ("SECRET".to_string(), "b".to_string()),
+        ]);
+        let sandboxed = RuntimeCommand {
+            env: Some(vec!["ALLOWED".into()]),
+            ..RuntimeCommand::from("echo \"$ALLOWED$SECRET\"")
+        };
+        let expected = hash_runtime(
+            "/tmp",
+            &"echo a".into(),
+            &HashMap::new(),
+            Arc::new(DashMap::new()),
+        )
+        .unwrap();
+
+        let result = hash_runtime("/tmp", &sandboxed, &env, Arc::new(DashMap::new())).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn should_only_pass_the_default_env_without_an_allowlist() {
+        let env = HashMap::from([
+            ("HOME".to_string(), "home".to_string()),
+            ("SECRET".to_string(), "b".to_string()),
+        ]);
+        let expected = hash_runtime(
+            "/tmp",
+            &"echo home".into(),
+            &HashMap::new(),
+            Arc::new(DashMap::new()),
+        )
+        .unwrap();
+
+        let result = hash_runtime(
+            "/tmp",
+            &"echo \"$HOME$SECRET\"".into(),
+            &env,
+            Arc::new(DashMap::new()),
+        )
+        .unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn should_create_stable_cache_keys() {
+        let runtime = RuntimeCommand {
+            env: Some(vec!["A".into(), "B".into()]),
+            ..RuntimeCommand::from("node -v")
+        };
+        let vars = (0..20)
+            .map(|i| (format!("VAR_{}", i), i.to_string()))
+            .chain([
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "2".to_string()),
+            ])
+            .collect::<Vec<_>>();
+        let env = vars.iter().cloned().collect::<HashMap<_, _>>();
+        let reordered = vars.into_iter().rev().collect::<HashMap<_, _>>();
+
+        let key = runtime_cache_key(&runtime, &command_env(&runtime, &env));
+
+        assert_eq!(
+            key,
+            runtime_cache_key(&runtime, &command_env(&runtime, &reordered))
+        );
+        assert_ne!(
+            key,
+            runtime_cache_key(&runtime, &command_env(&runtime, &HashMap::new()))
+        );
+        // Without an allowlist, only the default variables are part of the key
+        let plain = RuntimeCommand::from("node -v");
+        assert_eq!(
+            runtime_cache_key(&plain, &command_env(&plain, &env)),
+            runtime_cache_key(&plain, &command_env(&plain, &HashMap::new()))
+        );
+    }
 }
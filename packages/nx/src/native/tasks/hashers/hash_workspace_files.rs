@@ -4,14 +4,16 @@ use anyhow::*;
 use dashmap::DashMap;
 use tracing::{debug, debug_span, trace, warn};
 
+use crate::native::tasks::hashers::{FileSetHash, encode_file_list};
 use crate::native::types::FileData;
 use crate::native::{glob::build_glob_set, hasher::hash};
 
 pub fn hash_workspace_files(
     workspace_file_sets: &[String],
     all_workspace_files: &[FileData],
-    cache: Arc<DashMap<String, String>>,
-) -> Result<String> {
+    cache: Arc<DashMap<String, FileSetHash>>,
+    record_files: bool,
+) -> Result<FileSetHash> {
     let globs: Vec<String> = workspace_file_sets
         .iter()
         .inspect(|&x| trace!("Workspace file set: {}", x))
@@ -36,7 +38,10 @@ pub fn hash_workspace_files(
         .collect();
 
     if globs.is_empty() {
-        return Ok(hash(b""));
+        return Ok(FileSetHash {
+            hash: hash(b""),
+            files: record_files.then(String::new),
+        });
     }
 
     let cache_key = globs.join(",");
@@ -48,16 +53,20 @@ pub fn hash_workspace_files(
 
     let mut hasher = xxhash_rust::xxh3::Xxh3::new();
     debug_span!("Hashing workspace fileset", cache_key).in_scope(|| {
-        for file in all_workspace_files
+        let files = all_workspace_files
             .iter()
             .filter(|file| glob.is_match(&file.file))
-        {
+            .collect::<Vec<_>>();
+        for file in files.iter() {
             debug!("Adding {:?} ({:?}) to hash", file.hash, file.file);
             hasher.update(file.file.clone().as_bytes());
             hasher.update(file.hash.clone().as_bytes());
         }
-        let hashed_value = hasher.digest().to_string();
-        debug!("Hash Value: {:?}", hashed_value);
+        let hashed_value = FileSetHash {
+            hash: hasher.digest().to_string(),
+            files: record_files.then(|| encode_file_list(files)),
+        };
+        debug!("Hash Value: {:?}", hashed_value.hash);
 
         cache.insert(cache_key.to_string(), hashed_value.clone());
         Ok(hashed_value)
@@ -78,9 +87,10 @@ mod test {
             &["packages/{package}".to_string()],
             &[],
             Arc::new(DashMap::new()),
+            false,
         )
         .unwrap();
-        assert_eq!(result, hash(b""));
+        assert_eq!(result.hash, hash(b""));
     }
 
     #[test]
@@ -110,9 +120,11 @@ mod test {
                 project_file.clone(),
             ],
             Arc::new(DashMap::new()),
+            true,
         )
         .unwrap();
-        assert_eq!(result, "15841935230129999746");
+        assert_eq!(result.hash, "15841935230129999746");
+        assert_eq!(result.files.as_deref(), Some("123 .gitignore\n"));
     }
 
     #[test]
@@ -144,9 +156,10 @@ mod test {
                     project_file.clone(),
                 ],
                 Arc::new(DashMap::new()),
+                false,
             )
             .unwrap();
-            assert_eq!(result, "13759877301064854697");
+            assert_eq!(result.hash, "13759877301064854697");
         }
     }
 }
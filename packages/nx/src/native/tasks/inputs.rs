@@ -1,4 +1,5 @@
 use crate::native::project_graph::types::{Project, ProjectGraph};
+use crate::native::tasks::hashers::{env_pattern_matcher, is_env_pattern};
 use crate::native::tasks::types::Task;
 use crate::native::types::{Input, NxJson};
 use std::collections::HashMap;
@@ -104,6 +105,7 @@ fn split_inputs_into_self_and_deps<'a>(
                 | Input::FileSet(_)
                 | Input::Runtime(_)
                 | Input::Environment(_)
+                | Input::Json(_)
                 | Input::DepsOutputs { .. }
                 | Input::ExternalDependency(_) => {
                     acc.1.push(input);
@@ -160,7 +162,16 @@ pub(super) fn expand_single_project_inputs<'a>(
                 expanded.push(Input::FileSet(fileset));
             }
             Input::Runtime(runtime) => expanded.push(Input::Runtime(runtime)),
-            Input::Environment(env) => expanded.push(Input::Environment(env)),
+            Input::Environment(env) => {
+                if is_env_pattern(env) {
+                    env_pattern_matcher(env)?;
+                }
+                expanded.push(Input::Environment(env))
+            }
+            Input::Json(json) => {
+                validate_json_input(json)?;
+                expanded.push(Input::Json(json));
+            }
             Input::ExternalDependency(external) => {
                 expanded.push(Input::ExternalDependency(external))
             }
@@ -185,6 +196,28 @@ pub(super) fn expand_single_project_inputs<'a>(
     Ok(expanded)
 }
 
+fn validate_json_input(s: &str) -> anyhow::Result<()> {
+    let (file, pointer) = s.split_once('#').unwrap_or((s, ""));
+    if file.starts_with('!') {
+        anyhow::bail!(
+            r#""{json}" is an invalid json input.
+Json inputs hash the value in a single file, they cannot exclude files like filesets do.
+"#,
+            json = s,
+        );
+    }
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        anyhow::bail!(
+            r#""{json}" is an invalid json input.
+The part after # has to be a JSON pointer, for instance: "{projectRoot}/package.json#/dependencies".
+"#,
+            json = s,
+            projectRoot = "{projectRoot}",
+        );
+    }
+    validate_file_set(file)
+}
+
 fn validate_file_set(s: &str) -> anyhow::Result<()> {
     if !s.starts_with("{projectRoot}")
         && !s.starts_with("!{projectRoot}")
@@ -234,3 +267,18 @@ pub(super) fn get_named_inputs<'a>(
 
     collected_named_inputs
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_validate_json_inputs() {
+        assert!(validate_json_input("{projectRoot}/package.json#/dependencies").is_ok());
+        assert!(validate_json_input("{workspaceRoot}/package.json").is_ok());
+        assert!(validate_json_input("{projectRoot}/package.json#dependencies").is_err());
+        assert!(validate_json_input("package.json#/dependencies").is_err());
+        assert!(validate_json_input("!{projectRoot}/package.json#/dependencies").is_err());
+        assert!(validate_json_input("!{workspaceRoot}/package.json").is_err());
+    }
+}
@@ -9,6 +9,10 @@ mod utils;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod details;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod hash_diff;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod running_tasks_service;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod runtime_input_hashes;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod task_history;
@@ -0,0 +1,110 @@
+use crate::native::db::connection::NxDbConnection;
+use napi::bindgen_prelude::*;
+use rusqlite::params;
+use std::collections::HashMap;
+use tracing::trace;
+
+/// Persisted hashes are reused for this long, so that changes which do not show in the environment are eventually picked up
+const PERSISTED_HASH_TTL_SECONDS: i64 = 24 * 60 * 60;
+
+/// Persists the hashes of runtime inputs with `persist` across processes,
+/// keyed by `runtime_cache_key`
+#[napi]
+pub struct RuntimeInputHashes {
+    db: External<NxDbConnection>,
+}
+
+#[napi]
+impl RuntimeInputHashes {
+    #[napi(constructor)]
+    pub fn new(db: External<NxDbConnection>) -> anyhow::Result<Self> {
+        let r = Self { db };
+
+        r.setup()?;
+
+        Ok(r)
+    }
+
+    fn setup(&self) -> anyhow::Result<()> {
+        self.db.execute(
+            "CREATE TABLE IF NOT EXISTS runtime_input_hashes (
+                key    TEXT PRIMARY KEY NOT NULL,
+                hash    TEXT NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );",
+            params![],
+        )?;
+        Ok(())
+    }
+
+    #[napi]
+    pub fn get_runtime_hashes(&self) -> anyhow::Result<HashMap<String, String>> {
+        let hashes = self
+            .db
+            .prepare(
+                "SELECT key, hash FROM runtime_input_hashes WHERE created_at > datetime('now', ?1)",
+            )?
+            .query_map(params![ttl_modifier()], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+        Ok(hashes)
+    }
+
+    #[napi]
+    pub fn record_runtime_hashes(&mut self, hashes: HashMap<String, String>) -> anyhow::Result<()> {
+        if hashes.is_empty() {
+            return Ok(());
+        }
+        trace!("Recording {} runtime input hashes", hashes.len());
+        self.db.transaction(|conn| {
+            conn.execute(
+                "DELETE FROM runtime_input_hashes WHERE created_at <= datetime('now', ?1)",
+                params![ttl_modifier()],
+            )?;
+            let mut stmt = conn.prepare(
+                "INSERT OR REPLACE INTO runtime_input_hashes (key, hash) VALUES (?1, ?2)",
+            )?;
+            for (key, hash) in hashes.iter() {
+                stmt.execute(params![key, hash])?;
+            }
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+}
+
+fn ttl_modifier() -> String {
+    format!("-{} seconds", PERSISTED_HASH_TTL_SECONDS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[test]
+    fn should_only_get_hashes_within_the_ttl() {
+        let mut hashes = RuntimeInputHashes::new(External::new(NxDbConnection::new(
+            Connection::open_in_memory().unwrap(),
+        )))
+        .unwrap();
+
+        hashes
+            .record_runtime_hashes(HashMap::from([("fresh".to_string(), "1".to_string())]))
+            .unwrap();
+        hashes
+            .db
+            .execute(
+                "INSERT INTO runtime_input_hashes (key, hash, created_at) VALUES ('stale', '2', datetime('now', '-2 days'))",
+                params![],
+            )
+            .unwrap();
+
+        assert_eq!(
+            hashes.get_runtime_hashes().unwrap(),
+            HashMap::from([("fresh".to_string(), "1".to_string())])
+        );
+    }
+}
@@ -4,17 +4,17 @@ use std::sync::Arc;
 use crate::native::{
     hasher::hash,
     project_graph::{types::ProjectGraph, utils::create_project_root_mappings},
-    tasks::types::HashInstruction,
+    tasks::{hash_diff::HashInstructionKind, types::HashInstruction},
     types::NapiDashMap,
 };
 use crate::native::{
     project_graph::utils::ProjectRootMappings,
-    tasks::hashers::{hash_env, hash_runtime, hash_workspace_files},
+    tasks::hashers::{RuntimeHash, hash_env, hash_env_pattern, hash_runtime, hash_workspace_files},
 };
 use crate::native::{
     tasks::hashers::{
-        hash_all_externals, hash_external, hash_project_config, hash_project_files,
-        hash_task_output, hash_tsconfig_selectively,
+        FileSetHash, hash_all_externals, hash_external, hash_json_path, hash_project_config,
+        hash_project_files, hash_task_output, hash_tsconfig_selectively,
     },
     types::FileData,
     workspace::types::ProjectFiles,
@@ -30,11 +30,20 @@ use tracing::{debug, trace, trace_span};
 pub struct HashDetails {
     pub value: String,
     pub details: HashMap<String, String>,
+    /// Kind of each instruction in `details`, keyed by instruction
+    pub kinds: HashMap<String, HashInstructionKind>,
+    /// Files matched by each file set instruction in detailed mode, keyed by instruction.
+    /// Every file is encoded as a `<hash> <path>` line.
+    pub files: Option<HashMap<String, String>>,
+    /// Variables matched by each env pattern instruction, like `env:VITE_*`, keyed by instruction
+    pub env: Option<HashMap<String, Vec<String>>>,
 }
 
 #[napi(object)]
 pub struct HasherOptions {
     pub selectively_hash_ts_config: bool,
+    /// Records the files matched by file set instructions in `HashDetails::files`
+    pub detailed_file_sets: Option<bool>,
 }
 
 #[napi]
@@ -46,9 +55,10 @@ pub struct TaskHasher {
     ts_config: Vec<u8>,
     ts_config_paths: HashMap<String, Vec<String>>,
     options: Option<HasherOptions>,
-    workspace_files_cache: Arc<DashMap<String, String>>,
+    workspace_files_cache: Arc<DashMap<String, FileSetHash>>,
     external_cache: Arc<DashMap<String, String>>,
-    runtime_cache: Arc<DashMap<String, String>>,
+    runtime_cache: Arc<DashMap<String, RuntimeHash>>,
+    json_cache: Arc<DashMap<String, String>>,
 }
 #[napi]
 impl TaskHasher {
@@ -73,9 +83,37 @@ impl TaskHasher {
             workspace_files_cache: Arc::new(DashMap::new()),
             external_cache: Arc::new(DashMap::new()),
             runtime_cache: Arc::new(DashMap::new()),
+            json_cache: Arc::new(DashMap::new()),
         }
     }
 
+    /// Reuses hashes of runtime inputs which were persisted by other processes, keyed by `runtime_cache_key`
+    #[napi]
+    pub fn seed_runtime_hashes(&self, hashes: HashMap<String, String>) {
+        for (key, hash) in hashes {
+            self.runtime_cache.entry(key).or_insert(RuntimeHash {
+                hash,
+                persist: false,
+            });
+        }
+    }
+
+    /// Takes the hashes of runtime inputs with `persist` which were computed since the last call
+    #[napi]
+    pub fn take_runtime_hashes_to_persist(&self) -> HashMap<String, String> {
+        self.runtime_cache
+            .iter_mut()
+            .filter_map(|mut entry| {
+                let (key, runtime_hash) = entry.pair_mut();
+                if !runtime_hash.persist {
+                    return None;
+                }
+                runtime_hash.persist = false;
+                Some((key.clone(), runtime_hash.hash.clone()))
+            })
+            .collect()
+    }
+
     #[napi]
     pub fn hash_plans(
         &self,
@@ -98,6 +136,11 @@ impl TaskHasher {
             .as_ref()
             .map(|o| o.selectively_hash_ts_config)
             .unwrap_or(false);
+        let detailed_file_sets = self
+            .options
+            .as_ref()
+            .and_then(|o| o.detailed_file_sets)
+            .unwrap_or(false);
 
         let hash_time = std::time::Instant::now();
 
@@ -121,6 +164,7 @@ impl TaskHasher {
                         project_root_mappings: &project_root_mappings,
                         sorted_externals: &sorted_externals,
                         selectively_hash_tsconfig,
+                        detailed_file_sets,
                     },
                 )?;
 
@@ -129,9 +173,26 @@ impl TaskHasher {
                     .or_insert_with(|| HashDetails {
                         value: String::new(),
                         details: HashMap::new(),
+                        kinds: HashMap::new(),
+                        files: detailed_file_sets.then(HashMap::new),
+                        env: None,
                     });
 
-                entry.details.insert(hash_detail.0, hash_detail.1);
+                if let (Some(files), Some(task_files)) = (hash_detail.files, &mut entry.files) {
+                    task_files.insert(hash_detail.instruction.clone(), files);
+                }
+                if let Some(variables) = hash_detail.env {
+                    entry
+                        .env
+                        .get_or_insert_with(HashMap::new)
+                        .insert(hash_detail.instruction.clone(), variables);
+                }
+                entry
+                    .kinds
+                    .insert(hash_detail.instruction.clone(), hash_detail.kind);
+                entry
+                    .details
+                    .insert(hash_detail.instruction, hash_detail.hash);
                 Ok::<(), anyhow::Error>(())
             })?;
 
@@ -165,19 +226,25 @@ impl TaskHasher {
             project_root_mappings,
             sorted_externals,
             selectively_hash_tsconfig,
+            detailed_file_sets,
         }: HashInstructionArgs,
-    ) -> anyhow::Result<(String, String)> {
+    ) -> anyhow::Result<InstructionHash> {
         let now = std::time::Instant::now();
         let span = trace_span!("hashing", task_id).entered();
+        let mut files = None;
+        let mut env = None;
         let hash = match instruction {
             HashInstruction::WorkspaceFileSet(workspace_file_set) => {
                 let hashed_workspace_files = hash_workspace_files(
                     workspace_file_set,
                     &self.all_workspace_files,
                     Arc::clone(&self.workspace_files_cache),
+                    detailed_file_sets,
                 );
                 trace!(parent: &span, "hash_workspace_files: {:?}", now.elapsed());
-                hashed_workspace_files?
+                let hashed_workspace_files = hashed_workspace_files?;
+                files = hashed_workspace_files.files;
+                hashed_workspace_files.hash
             }
             HashInstruction::Runtime(runtime) => {
                 let hashed_runtime = hash_runtime(
@@ -194,6 +261,12 @@ impl TaskHasher {
                 trace!(parent: &span, "hash_env: {:?}", now.elapsed());
                 hashed_env
             }
+            HashInstruction::EnvironmentPattern(pattern) => {
+                let hashed_env = hash_env_pattern(pattern, js_env)?;
+                trace!(parent: &span, "hash_env_pattern: {:?}", now.elapsed());
+                env = Some(hashed_env.variables);
+                hashed_env.hash
+            }
             HashInstruction::ProjectFileSet(project_name, file_sets) => {
                 let project = self
                     .project_graph
@@ -205,9 +278,21 @@ impl TaskHasher {
                     &project.root,
                     file_sets,
                     &self.project_file_map,
+                    detailed_file_sets,
                 )?;
                 trace!(parent: &span, "hash_project_files: {:?}", now.elapsed());
-                hashed_project_files
+                files = hashed_project_files.files;
+                hashed_project_files.hash
+            }
+            HashInstruction::JsonPath(file, pointer) => {
+                let hashed_json = hash_json_path(
+                    &self.workspace_root,
+                    file,
+                    pointer,
+                    Arc::clone(&self.json_cache),
+                )?;
+                trace!(parent: &span, "hash_json_path: {:?}", now.elapsed());
+                hashed_json
             }
             HashInstruction::ProjectConfiguration(project_name) => {
                 let hashed_project_config =
@@ -265,14 +350,29 @@ impl TaskHasher {
                 hashed_all_externals
             }
         };
-        Ok((instruction.to_string(), hash))
+        Ok(InstructionHash {
+            instruction: instruction.to_string(),
+            kind: HashInstructionKind::from(instruction),
+            hash,
+            files,
+            env,
+        })
     }
 }
 
+struct InstructionHash {
+    instruction: String,
+    kind: HashInstructionKind,
+    hash: String,
+    files: Option<String>,
+    env: Option<Vec<String>>,
+}
+
 struct HashInstructionArgs<'a> {
     js_env: &'a HashMap<String, String>,
     ts_config_hash: &'a str,
     project_root_mappings: &'a ProjectRootMappings,
     sorted_externals: &'a [&'a String],
     selectively_hash_tsconfig: bool,
+    detailed_file_sets: bool,
 }
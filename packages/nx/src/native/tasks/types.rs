@@ -45,9 +45,13 @@ pub struct TaskGraph {
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum HashInstruction {
     WorkspaceFileSet(Vec<String>),
-    Runtime(String),
+    Runtime(RuntimeCommand),
     Environment(String),
+    /// Every variable matching a pattern like `VITE_*`, expanded when the plans are hashed
+    EnvironmentPattern(String),
     ProjectFileSet(String, Vec<String>),
+    /// A file relative to the workspace root, and the JSON pointer to the value which is hashed
+    JsonPath(String, String),
     ProjectConfiguration(String),
     TsConfiguration(String),
     TaskOutput(String, Vec<String>),
@@ -55,6 +59,44 @@ pub enum HashInstruction {
     AllExternalDependencies,
 }
 
+/// A command whose output is hashed by a runtime input
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RuntimeCommand {
+    pub command: String,
+    /// Directory to run the command in, relative to the workspace root
+    pub cwd: Option<String>,
+    /// Sorted names of the environment variables passed to the command, besides `PATH` and `HOME`
+    pub env: Option<Vec<String>>,
+    /// Milliseconds after which the command is killed
+    pub timeout: Option<u32>,
+    /// Whether the hash can be persisted and reused by other processes
+    pub persist: bool,
+}
+
+impl From<&str> for RuntimeCommand {
+    fn from(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+// Only what changes the output of the command is displayed,
+// so the instructions of plain commands keep their key
+impl fmt::Display for RuntimeCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.command)?;
+        if let Some(cwd) = &self.cwd {
+            write!(f, " (cwd: {})", cwd)?;
+        }
+        if let Some(env) = &self.env {
+            write!(f, " (env: {})", env.join(","))?;
+        }
+        Ok(())
+    }
+}
+
 impl ToNapiValue for HashInstruction {
     unsafe fn to_napi_value(
         env: napi::sys::napi_env,
@@ -89,6 +131,8 @@ impl fmt::Display for HashInstruction {
                     format!("workspace:[{}]", file_set.join(",")),
                 HashInstruction::Runtime(runtime) => format!("runtime:{}", runtime),
                 HashInstruction::Environment(env) => format!("env:{}", env),
+                HashInstruction::JsonPath(file, pointer) => format!("json:{}#{}", file, pointer),
+                HashInstruction::EnvironmentPattern(pattern) => format!("env:{}", pattern),
                 HashInstruction::TaskOutput(task_output, dep_outputs) => {
                     let dep_outputs = dep_outputs.join(",");
                     format!("{task_output}:{dep_outputs}")
@@ -9,12 +9,13 @@ describe('Cache', () => {
   let cache: NxCache;
   let tempFs: TempFs;
   let taskDetails: TaskDetails;
+  let dbConnection: ReturnType<typeof getDbConnection>;
 
   const dbOutputFolder = 'temp-db-cache';
   beforeEach(() => {
     tempFs = new TempFs('cache');
 
-    const dbConnection = getDbConnection({
+    dbConnection = getDbConnection({
       directory: join(__dirname, dbOutputFolder),
       dbName: `temp-db-${randomBytes(4).toString('hex')}`,
     });
@@ -65,8 +66,106 @@ describe('Cache', () => {
     );
   });
 
+  it('should find missing hashes without accessing entries', () => {
+    tempFs.createFileSync('dist/output.txt', 'output contents');
+    cache.put('123', 'output', ['dist'], 0);
+
+    expect(cache.missingHashes(['123', '456'])).toEqual(['456']);
+    expect(cache.listEntries()[0].hitCount).toEqual(0);
+  });
+
   it('should handle storing hashes that already exist in the cache', async () => {
     cache.put('123', 'output 123', ['dist'], 0);
     expect(() => cache.put('123', 'output 123', ['dist'], 0)).not.toThrow();
   });
+
+  it('should only store identical outputs once', async () => {
+    taskDetails.recordTaskDetails([
+      {
+        hash: '456',
+        project: 'proj',
+        target: 'build',
+        configuration: 'production',
+      },
+    ]);
+    tempFs.createFileSync('dist/output.txt', 'output contents');
+
+    cache.put('123', 'output', ['dist'], 0);
+    cache.put('456', 'output', ['dist'], 0);
+
+    expect(cache.getCacheSize()).toEqual(
+      'output contents'.length + 2 * 'output'.length
+    );
+    expect(cache.get('456').size).toEqual(
+      'output contents'.length + 'output'.length
+    );
+  });
+
+  it('should report what would be evicted in a dry run', () => {
+    tempFs.createFileSync('dist/output.txt', 'output contents');
+    cache.put('123', 'output', ['dist'], 0);
+
+    const limitedCache = new NxCache(
+      tempFs.tempDir,
+      join(tempFs.tempDir, '.cache'),
+      dbConnection,
+      undefined,
+      1
+    );
+
+    expect(limitedCache.evict(true)).toEqual({
+      hashes: ['123'],
+      freedBytes: 'output contents'.length + 'output'.length,
+    });
+    expect(cache.get('123')).not.toBeNull();
+
+    limitedCache.evict();
+    expect(cache.get('123')).toBeNull();
+  });
+
+  it('should never evict pinned entries', () => {
+    tempFs.createFileSync('dist/output.txt', 'output contents');
+    cache.put('123', 'output', ['dist'], 0);
+    cache.pin(['123']);
+
+    const limitedCache = new NxCache(
+      tempFs.tempDir,
+      join(tempFs.tempDir, '.cache'),
+      dbConnection,
+      undefined,
+      1
+    );
+
+    expect(limitedCache.evict().hashes).toEqual([]);
+    expect(cache.getPinnedHashes()).toEqual(['123']);
+
+    cache.unpin(['123']);
+    expect(limitedCache.evict().hashes).toEqual(['123']);
+  });
+
+  it('should list entries with their task details', () => {
+    tempFs.createFileSync('dist/output.txt', 'output contents');
+    cache.put('123', 'output', ['dist'], 0);
+    cache.get('123');
+
+    expect(cache.listEntries({ project: 'proj' })).toEqual([
+      expect.objectContaining({
+        hash: '123',
+        size: 'output contents'.length + 'output'.length,
+        hitCount: 1,
+        project: 'proj',
+        target: 'test',
+        configuration: 'production',
+      }),
+    ]);
+    expect(cache.listEntries({ project: 'other' })).toEqual([]);
+    expect(cache.statsByTarget()).toEqual([
+      expect.objectContaining({
+        project: 'proj',
+        target: 'test',
+        entries: 1,
+        hitCount: 1,
+      }),
+    ]);
+  });
 });
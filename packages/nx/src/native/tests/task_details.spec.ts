@@ -0,0 +1,117 @@
+import { HashInstructionKind, TaskDetails } from '../index';
+import { join } from 'path';
+import { rmSync } from 'fs';
+import { getDbConnection } from '../../utils/db-connection';
+import { randomBytes } from 'crypto';
+
+describe('TaskDetails', () => {
+  let taskDetails: TaskDetails;
+
+  const dbOutputFolder = 'temp-db-task-details';
+  beforeEach(() => {
+    const dbConnection = getDbConnection({
+      directory: join(__dirname, dbOutputFolder),
+      dbName: `temp-db-${randomBytes(4).toString('hex')}`,
+    });
+    taskDetails = new TaskDetails(dbConnection);
+  });
+
+  afterAll(() => {
+    rmSync(join(__dirname, dbOutputFolder), {
+      recursive: true,
+      force: true,
+    });
+  });
+
+  it('should explain which instructions changed between two hashes', () => {
+    taskDetails.recordTaskDetails([
+      {
+        hash: '123',
+        project: 'proj',
+        target: 'build',
+        details: {
+          'npm:{projectRoot}/**/*': 'a',
+          'npm:ProjectConfiguration': 'b',
+          'env:CI': 'c',
+        },
+        kinds: {
+          'npm:{projectRoot}/**/*': HashInstructionKind.projectFileSet,
+          'npm:ProjectConfiguration': HashInstructionKind.projectConfiguration,
+          'env:CI': HashInstructionKind.environment,
+        },
+      },
+      {
+        hash: '456',
+        project: 'proj',
+        target: 'build',
+        details: {
+          'npm:{projectRoot}/**/*': 'd',
+          'npm:ProjectConfiguration': 'b',
+          'npm:react': 'e',
+          command: 'f',
+        },
+        kinds: {
+          'npm:{projectRoot}/**/*': HashInstructionKind.projectFileSet,
+          'npm:ProjectConfiguration': HashInstructionKind.projectConfiguration,
+          'npm:react': HashInstructionKind.external,
+        },
+      },
+    ]);
+
+    expect(taskDetails.explainHashDiff('123', '456')).toEqual([
+      {
+        instruction: 'command',
+        change: 'added',
+        after: 'f',
+      },
+      {
+        instruction: 'env:CI',
+        kind: 'environment',
+        change: 'removed',
+        before: 'c',
+      },
+      {
+        instruction: 'npm:react',
+        kind: 'external',
+        change: 'added',
+        after: 'e',
+      },
+      {
+        instruction: 'npm:{projectRoot}/**/*',
+        kind: 'projectFileSet',
+        change: 'changed',
+        before: 'a',
+        after: 'd',
+      },
+    ]);
+  });
+
+  it('should throw when the details of a hash were not recorded', () => {
+    expect(() => taskDetails.explainHashDiff('123', '456')).toThrow();
+  });
+
+  it('should explain which files of a file set changed', () => {
+    const instruction = 'proj:{projectRoot}/**/*';
+    taskDetails.recordTaskDetails([
+      {
+        hash: '123',
+        project: 'proj',
+        target: 'build',
+        details: { [instruction]: 'a' },
+        files: { [instruction]: '1 proj/a.ts\n2 proj/b.ts\n' },
+      },
+      {
+        hash: '456',
+        project: 'proj',
+        target: 'build',
+        details: { [instruction]: 'b' },
+        files: { [instruction]: '1 proj/a.ts\n3 proj/b.ts\n4 proj/c.ts\n' },
+      },
+    ]);
+
+    expect(taskDetails.explainHashDiff('123', '456')[0].files).toEqual([
+      { path: 'proj/b.ts', change: 'changed' },
+      { path: 'proj/c.ts', change: 'added' },
+    ]);
+  });
+});
@@ -87,27 +87,244 @@ describe('watcher', () => {
       watcher = new Watcher(temp.tempDir);
 
       watcher.watch((err, paths) => {
-        expect(paths.length).toBe(2);
-        expect(paths.find((p) => p.type === 'create')).toMatchInlineSnapshot(`
-        {
-          "path": "app1/rename.js",
-          "type": "create",
-        }
+        expect(paths).toMatchInlineSnapshot(`
+        [
+          {
+            "from": "app1/main.js",
+            "path": "app1/rename.js",
+            "type": "rename",
+          },
+        ]
       `);
-        expect(paths.find((p) => p.type === 'delete')).toMatchInlineSnapshot(`
+        done();
+      });
+
+      await wait();
+      temp.renameFile('app1/main.js', 'app1/rename.js');
+    });
+  }, 15000);
+
+  it('should coalesce a burst of changes into a single batch', async () => {
+    return new Promise<void>(async (done) => {
+      await wait();
+      watcher = new Watcher(temp.tempDir, undefined, undefined, {
+        debounceMs: 500,
+      });
+
+      watcher.watch((err, paths) => {
+        expect(paths).toMatchInlineSnapshot(`
+        [
+          {
+            "path": "app1/main.css",
+            "type": "delete",
+          },
+          {
+            "path": "app1/main.js",
+            "type": "update",
+          },
+          {
+            "path": "app1/new.js",
+            "type": "create",
+          },
+        ]
+      `);
+        done();
+      });
+
+      await wait();
+      temp.createFileSync('app1/new.js', '');
+      await wait(100);
+      temp.appendFile('app1/new.js', 'update');
+      await wait(100);
+      temp.appendFile('app1/main.js', 'update');
+      await wait(100);
+      temp.removeFileSync('app1/main.css');
+    });
+  }, 15000);
+
+  it('should route events to the matching subscriptions', async () => {
+    return new Promise<void>(async (done, fail) => {
+      await wait();
+      watcher = new Watcher(temp.tempDir);
+      watcher.setProjectRoots({ app1: 'app1', inner: 'inner' });
+
+      const cssSubscription = watcher.subscribe(
+        () => fail(new Error('the subscription was removed')),
+        { include: ['**/*.css'] }
+      );
+      watcher.unsubscribe(cssSubscription);
+      watcher.subscribe(
+        () => fail(new Error('no files of inner were changed')),
+        { projects: ['inner'] }
+      );
+      watcher.subscribe(
+        (err, paths) => {
+          expect(paths).toMatchInlineSnapshot(`
+          [
+            {
+              "path": "app1/main.js",
+              "type": "update",
+            },
+          ]
+        `);
+          done();
+        },
+        { projects: ['app1'], include: ['**/*.js'] }
+      );
+
+      await wait();
+      temp.appendFile('app1/main.css', 'update');
+      temp.appendFile('app1/main.js', 'update');
+    });
+  }, 15000);
+
+  it('should only drop ignored files for subscriptions which use ignore files', async () => {
+    return new Promise<void>(async (done, fail) => {
+      await wait();
+      watcher = new Watcher(temp.tempDir, undefined, false);
+
+      watcher.subscribe(
+        () => fail(new Error('nested-ignore is gitignored')),
+        { useIgnore: true, root: 'nested-ignore' }
+      );
+      watcher.subscribe(
+        (err, paths) => {
+          expect(paths).toMatchInlineSnapshot(`
+          [
+            {
+              "path": "nested-ignore/hello.txt",
+              "type": "create",
+            },
+          ]
+        `);
+          done();
+        },
+        { root: 'nested-ignore' }
+      );
+
+      await wait();
+      temp.createFileSync('nested-ignore/hello.txt', '');
+    });
+  }, 15000);
+
+  it('should only watch the roots', async () => {
+    return new Promise<void>(async (done) => {
+      await wait();
+      watcher = new Watcher(temp.tempDir, undefined, undefined, {
+        roots: ['app1'],
+      });
+
+      watcher.watch((err, paths) => {
+        expect(paths).toMatchInlineSnapshot(`
+        [
+          {
+            "path": "app1/main.js",
+            "type": "update",
+          },
+        ]
+      `);
+        done();
+      });
+
+      await wait();
+      temp.createFileSync('bar.txt', '');
+      await wait();
+      temp.appendFile('app1/main.js', 'update');
+    });
+  }, 15000);
+
+  it('should poll for changes', async () => {
+    return new Promise<void>(async (done) => {
+      await wait();
+      watcher = new Watcher(temp.tempDir, undefined, undefined, {
+        polling: true,
+        pollIntervalMs: 100,
+      });
+
+      watcher.watch((err, paths) => {
+        expect(paths).toMatchInlineSnapshot(`
+        [
+          {
+            "path": "app1/main.js",
+            "type": "update",
+          },
+        ]
+      `);
+        done();
+      });
+
+      await wait();
+      // nxignored file should not trigger a callback
+      temp.appendFile('app2/main.js', 'update');
+      temp.appendFile('app1/main.js', 'update');
+    });
+  }, 15000);
+
+  it('should report when the watcher started', async () => {
+    return new Promise<void>(async (done) => {
+      await wait();
+      watcher = new Watcher(temp.tempDir);
+
+      watcher.onStatus((err, status) => {
+        expect(status).toMatchInlineSnapshot(`
         {
-          "path": "app1/main.js",
-          "type": "delete",
+          "type": "started",
         }
       `);
         done();
       });
+      watcher.watch(() => {});
+    });
+  }, 15000);
 
+  it('should send the changes lost in an overflow', async () => {
+    return new Promise<void>(async (done) => {
       await wait();
-      temp.renameFile('app1/main.js', 'app1/rename.js');
+      // fills up with the first event, so the rest are lost
+      watcher = new Watcher(temp.tempDir, undefined, undefined, {
+        eventChannelSize: 1,
+      });
+      const statuses = new Set<string>();
+      watcher.onStatus((err, status) => {
+        statuses.add(status.type);
+      });
+
+      const files = Array.from({ length: 200 }, (_, i) => `app1/file-${i}.js`);
+      const changedFiles = new Set<string>();
+      watcher.watch((err, events) => {
+        for (const { path } of events) {
+          changedFiles.add(path);
+        }
+        if (files.every((file) => changedFiles.has(file))) {
+          expect(statuses.has('overflow')).toBeTruthy();
+          done();
+        }
+      });
+
+      await wait();
+      for (const file of files) {
+        temp.createFileSync(file, '');
+      }
     });
   }, 15000);
 
+  it('should report once that the root was deleted', async () => {
+    await wait();
+    watcher = new Watcher(temp.tempDir);
+    const statuses: string[] = [];
+    watcher.onStatus((err, status) => {
+      statuses.push(status.type);
+    });
+    watcher.watch(() => {});
+
+    await wait();
+    temp.cleanup();
+    await wait(2000);
+    expect(statuses.filter((type) => type === 'rootDeleted')).toEqual([
+      'rootDeleted',
+    ]);
+  }, 15000);
+
   it('should trigger on deletes', async () => {
     return new Promise<void>(async (done) => {
       await wait();
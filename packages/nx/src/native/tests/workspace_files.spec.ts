@@ -274,6 +274,98 @@ describe('Workspace Context', () => {
       expect(results).toContain('file.css');
       expect(results).toContain('file.js');
     });
+
+    it('should not match anything without globs', () => {
+      expect(context.glob([])).toEqual([]);
+      expect(context.multiGlob([])).toEqual([]);
+      expect(context.queryFiles({ include: [] }).files).toEqual([]);
+    });
+
+    it('should lint globs against the workspace files', () => {
+      const diagnostics = context.lintGlobs([
+        '**/*.txt',
+        '**/*.md',
+        '{projectRoot}/**/*.md',
+        'src/{*.ts',
+      ]);
+      expect(diagnostics.map((d) => [d.glob, d.severity, d.message])).toEqual([
+        [
+          '**/*.md',
+          'warning',
+          'The glob does not match any files in the workspace',
+        ],
+        ['src/{*.ts', 'error', '`{` is never closed'],
+      ]);
+    });
+  });
+
+  describe('querying', () => {
+    let context: WorkspaceContext;
+    let fs: TempFs;
+
+    beforeEach(async () => {
+      fs = new TempFs('workspace-files');
+
+      await fs.createFiles({
+        'libs/a/index.ts': 'same',
+        'libs/a/index.spec.ts': 'spec',
+        'libs/b/index.ts': 'same',
+        'README.md': 'readme',
+      });
+
+      context = new WorkspaceContext(
+        fs.tempDir,
+        cacheDirectoryForWorkspace(fs.tempDir)
+      );
+      context.trackWorkspaceFiles({ 'libs/a': 'a', 'libs/b': 'b' });
+    });
+
+    afterEach(() => {
+      context = null;
+      fs.reset();
+    });
+
+    it('should combine filters and paginate', () => {
+      const query = {
+        include: ['**/*.ts'],
+        exclude: ['**/*.spec.ts'],
+        directory: 'libs',
+      };
+
+      expect(context.queryFiles(query).files.map((f) => f.file)).toEqual([
+        'libs/a/index.ts',
+        'libs/b/index.ts',
+      ]);
+      expect(context.countFiles(query)).toEqual(2);
+
+      const page = context.queryFiles({ ...query, offset: 1, limit: 1 });
+      expect(page.total).toEqual(2);
+      expect(page.files.map((f) => f.file)).toEqual(['libs/b/index.ts']);
+    });
+
+    it('should query the files of a project', () => {
+      expect(
+        context.queryFiles({ project: 'a' }).files.map((f) => f.file)
+      ).toEqual(['libs/a/index.spec.ts', 'libs/a/index.ts']);
+    });
+
+    it('should not replace the tracked project file map when files are retrieved for other projects', () => {
+      context.getWorkspaceFiles({ libs: 'libs' });
+
+      expect(
+        context.queryFiles({ project: 'a' }).files.map((f) => f.file)
+      ).toEqual(['libs/a/index.spec.ts', 'libs/a/index.ts']);
+      expect(context.queryFiles({ project: 'libs' }).files).toEqual([]);
+    });
+
+    it('should find duplicate files', () => {
+      expect(context.findDuplicateFiles()).toEqual([
+        {
+          hash: expect.any(String),
+          files: ['libs/a/index.ts', 'libs/b/index.ts'],
+        },
+      ]);
+    });
   });
 
   // describe('errors', () => {
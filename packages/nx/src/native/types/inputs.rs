@@ -1,5 +1,5 @@
 use napi::Either;
-use napi::bindgen_prelude::Either7;
+use napi::bindgen_prelude::Either8;
 
 #[napi(object)]
 pub struct InputsInput {
@@ -14,8 +14,19 @@ pub struct FileSetInput {
 }
 
 #[napi(object)]
+#[derive(Debug)]
 pub struct RuntimeInput {
     pub runtime: String,
+    /// Milliseconds after which the command is killed and hashing fails
+    pub timeout: Option<u32>,
+    /// Names of the environment variables passed to the command, besides `PATH` and `HOME`.
+    /// No other environment variables are passed when this is not set.
+    pub env: Option<Vec<String>>,
+    /// Directory to run the command in, relative to the project root. Defaults to the workspace root.
+    pub cwd: Option<String>,
+    /// Keeps the hash in the Nx database so that other processes can reuse it.
+    /// Only use this for commands whose output does not change for the same environment.
+    pub persist: Option<bool>,
 }
 
 #[napi(object)]
@@ -23,6 +34,13 @@ pub struct EnvironmentInput {
     pub env: String,
 }
 
+/// Hashes a single value of a JSON file, selected by a JSON pointer after `#`.
+/// For example `{projectRoot}/package.json#/dependencies`.
+#[napi(object)]
+pub struct JsonInput {
+    pub json: String,
+}
+
 #[napi(object)]
 pub struct ExternalDependenciesInput {
     pub external_dependencies: Vec<String>,
@@ -34,7 +52,7 @@ pub struct DepsOutputsInput {
     pub transitive: Option<bool>,
 }
 
-pub(crate) type JsInputs = Either7<
+pub(crate) type JsInputs = Either8<
     InputsInput,
     String,
     FileSetInput,
@@ -42,12 +60,13 @@ pub(crate) type JsInputs = Either7<
     EnvironmentInput,
     ExternalDependenciesInput,
     DepsOutputsInput,
+    JsonInput,
 >;
 
 impl<'a> From<&'a JsInputs> for Input<'a> {
     fn from(value: &'a JsInputs) -> Self {
         match value {
-            Either7::A(inputs) => {
+            Either8::A(inputs) => {
                 if let Some(projects) = &inputs.projects {
                     Input::Projects {
                         input: &inputs.input,
@@ -63,7 +82,7 @@ impl<'a> From<&'a JsInputs> for Input<'a> {
                     }
                 }
             }
-            Either7::B(string) => {
+            Either8::B(string) => {
                 if let Some(input) = string.strip_prefix('^') {
                     Input::Inputs {
                         input,
@@ -73,16 +92,17 @@ impl<'a> From<&'a JsInputs> for Input<'a> {
                     Input::String(string)
                 }
             }
-            Either7::C(file_set) => Input::FileSet(&file_set.fileset),
-            Either7::D(runtime) => Input::Runtime(&runtime.runtime),
-            Either7::E(environment) => Input::Environment(&environment.env),
-            Either7::F(external_dependencies) => {
+            Either8::C(file_set) => Input::FileSet(&file_set.fileset),
+            Either8::D(runtime) => Input::Runtime(runtime),
+            Either8::E(environment) => Input::Environment(&environment.env),
+            Either8::F(external_dependencies) => {
                 Input::ExternalDependency(&external_dependencies.external_dependencies)
             }
-            Either7::G(deps_outputs) => Input::DepsOutputs {
+            Either8::G(deps_outputs) => Input::DepsOutputs {
                 transitive: deps_outputs.transitive.unwrap_or(false),
                 dependent_tasks_output_files: &deps_outputs.dependent_tasks_output_files,
             },
+            Either8::H(json) => Input::Json(&json.json),
         }
     }
 }
@@ -95,8 +115,9 @@ pub(crate) enum Input<'a> {
     },
     String(&'a str),
     FileSet(&'a str),
-    Runtime(&'a str),
+    Runtime(&'a RuntimeInput),
     Environment(&'a str),
+    Json(&'a str),
     ExternalDependency(&'a [String]),
     DepsOutputs {
         dependent_tasks_output_files: &'a str,
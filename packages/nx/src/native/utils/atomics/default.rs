@@ -1,5 +1,6 @@
 use parking_lot::{Condvar, Mutex, MutexGuard};
 
+#[derive(Default)]
 pub struct NxMutex<T>(Mutex<T>);
 
 impl<T> NxMutex<T> {
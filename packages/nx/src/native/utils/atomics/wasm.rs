@@ -1,5 +1,6 @@
 use std::sync::{Condvar, LockResult, Mutex, MutexGuard};
 
+#[derive(Default)]
 pub struct NxMutex<T>(Mutex<T>);
 
 impl<T> NxMutex<T> {
@@ -157,7 +157,7 @@ where
     receiver_thread.join().unwrap()
 }
 
-fn create_walker<P>(directory: P, use_ignores: bool) -> WalkBuilder
+pub fn create_walker<P>(directory: P, use_ignores: bool) -> WalkBuilder
 where
     P: AsRef<Path>,
 {
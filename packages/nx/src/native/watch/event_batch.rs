@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Receiver;
+use tracing::trace;
+
+use crate::native::watch::types::{EventType, WatchEventInternal};
+
+/// The longest a batch waits for events to stop, so that constant writes cannot hold back events forever
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct BatchLimits {
+    /// How long to wait for more events before sending a batch
+    pub debounce: Duration,
+    /// The maximum number of events in a batch
+    pub max_count: Option<usize>,
+    /// The maximum total length of the paths in a batch, in bytes
+    pub max_size: Option<usize>,
+}
+
+impl BatchLimits {
+    fn is_full(&self, batch: &EventBatch) -> bool {
+        self.max_count.is_some_and(|max| batch.len() >= max)
+            || self.max_size.is_some_and(|max| batch.size() >= max)
+    }
+}
+
+/// Receives events until the channel is closed and sends them on in batches.
+/// A batch is sent once no events were received for the debounce window, or as soon as it is full.
+pub(super) fn batch_events(
+    receiver: Receiver<Vec<WatchEventInternal>>,
+    limits: BatchLimits,
+    mut send: impl FnMut(Vec<WatchEventInternal>),
+) {
+    let mut batch = EventBatch::default();
+    while let Ok(mut events) = receiver.recv() {
+        let mut started = Instant::now();
+        loop {
+            for event in events {
+                batch.add(event);
+                if limits.is_full(&batch) {
+                    trace!(
+                        count = batch.len(),
+                        size = batch.size(),
+                        "sending full batch"
+                    );
+                    send(batch.take());
+                    started = Instant::now();
+                }
+            }
+
+            let deadline = (Instant::now() + limits.debounce).min(started + MAX_BATCH_DELAY);
+            match receiver.recv_deadline(deadline) {
+                Ok(next) => events = next,
+                Err(_) => break,
+            }
+        }
+
+        if !batch.is_empty() {
+            trace!(count = batch.len(), size = batch.size(), "sending batch");
+            send(batch.take());
+        }
+    }
+}
+
+/// Watch events which are waiting to be sent, coalesced to at most one event per path.
+///
+/// Events have to be added in the order they happened:
+/// * a file which is created and then updated is still created
+/// * a file which is deleted and then created again is updated
+/// * a file which is renamed and then renamed again is renamed once, from its first path to its last path
+#[derive(Default)]
+pub(super) struct EventBatch {
+    events: HashMap<PathBuf, WatchEventInternal>,
+    /// The paths of the renames in `events`, by the path they were renamed from
+    renamed_to: HashMap<PathBuf, PathBuf>,
+    size: usize,
+}
+
+impl EventBatch {
+    pub fn add(&mut self, event: WatchEventInternal) {
+        match event.from.clone() {
+            Some(from) => self.add_rename(from, event),
+            None => self.add_change(event),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The total length of the paths in the batch, in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Takes the events out of the batch, sorted by path
+    pub fn take(&mut self) -> Vec<WatchEventInternal> {
+        self.renamed_to.clear();
+        self.size = 0;
+        let mut events = self.events.drain().map(|(_, e)| e).collect::<Vec<_>>();
+        events.sort_by(|a, b| a.path.cmp(&b.path));
+        events
+    }
+
+    fn add_change(&mut self, event: WatchEventInternal) {
+        // a new file at a path which was renamed away, or the renamed file is deleted
+        if let Some(to) = self.renamed_to.get(&event.path).cloned() {
+            self.split_rename(&to);
+        }
+        if matches!(event.r#type, EventType::delete) && self.is_rename(&event.path) {
+            self.split_rename(&event.path);
+        }
+
+        let r#type = match (self.events.get(&event.path).map(|e| e.r#type), event.r#type) {
+            (None, r#type) => r#type,
+            // the renamed file changed again
+            (Some(EventType::rename), _) => return,
+            (Some(_), EventType::delete) => EventType::delete,
+            (Some(EventType::create), _) => EventType::create,
+            // the file existed before the batch
+            (Some(_), _) => EventType::update,
+        };
+        self.insert(WatchEventInternal { r#type, ..event });
+    }
+
+    fn add_rename(&mut self, mut from: PathBuf, event: WatchEventInternal) {
+        if let Some(to) = self.renamed_to.get(&from).cloned() {
+            self.split_rename(&to);
+        }
+        if let Some(previous) = self.remove(&from) {
+            match previous.r#type {
+                EventType::rename => {
+                    // a -> b -> c is a rename from a to c
+                    from = previous.from.expect("renames have a previous path");
+                    self.renamed_to.remove(&from);
+                }
+                EventType::create => {
+                    return self.add_change(WatchEventInternal {
+                        r#type: EventType::create,
+                        from: None,
+                        ..event
+                    });
+                }
+                _ => {}
+            }
+        }
+        if from == event.path {
+            return self.add_change(WatchEventInternal {
+                r#type: EventType::update,
+                from: None,
+                ..event
+            });
+        }
+
+        // the renamed file replaces a file which was renamed here before, so that file is gone
+        if self.is_rename(&event.path) {
+            self.split_rename(&event.path);
+        }
+        self.remove(&event.path);
+        self.renamed_to.insert(from.clone(), event.path.clone());
+        self.insert(WatchEventInternal {
+            r#type: EventType::rename,
+            from: Some(from),
+            ..event
+        });
+    }
+
+    fn is_rename(&self, path: &Path) -> bool {
+        self.events
+            .get(path)
+            .is_some_and(|e| matches!(e.r#type, EventType::rename))
+    }
+
+    /// Replaces the rename to `to` with a deletion of its previous path and a creation of `to`
+    fn split_rename(&mut self, to: &Path) {
+        let Some(rename) = self.remove(to) else {
+            return;
+        };
+        let from = rename.from.clone().expect("renames have a previous path");
+        self.renamed_to.remove(&from);
+        self.insert(WatchEventInternal {
+            path: from,
+            r#type: EventType::delete,
+            from: None,
+            origin: rename.origin.clone(),
+        });
+        self.insert(WatchEventInternal {
+            r#type: EventType::create,
+            from: None,
+            ..rename
+        });
+    }
+
+    fn insert(&mut self, event: WatchEventInternal) {
+        self.size += event_size(&event);
+        if let Some(previous) = self.events.insert(event.path.clone(), event) {
+            self.size -= event_size(&previous);
+        }
+    }
+
+    fn remove(&mut self, path: &Path) -> Option<WatchEventInternal> {
+        let event = self.events.remove(path)?;
+        self.size -= event_size(&event);
+        Some(event)
+    }
+}
+
+fn event_size(event: &WatchEventInternal) -> usize {
+    event.path.as_os_str().len() + event.from.as_ref().map_or(0, |from| from.as_os_str().len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crossbeam_channel::unbounded;
+
+    fn change(path: &str, r#type: EventType) -> WatchEventInternal {
+        WatchEventInternal {
+            path: path.into(),
+            r#type,
+            from: None,
+            origin: String::new(),
+        }
+    }
+
+    fn rename(from: &str, to: &str) -> WatchEventInternal {
+        WatchEventInternal {
+            from: Some(from.into()),
+            ..change(to, EventType::rename)
+        }
+    }
+
+    fn batch(events: Vec<WatchEventInternal>) -> Vec<String> {
+        let mut batch = EventBatch::default();
+        for event in events {
+            batch.add(event);
+        }
+        let size = batch.size();
+        let events = batch.take();
+        assert_eq!(size, events.iter().map(event_size).sum::<usize>());
+        events
+            .iter()
+            .map(|e| match &e.from {
+                Some(from) => format!("{:?} {} -> {}", e.r#type, from.display(), e.path.display()),
+                None => format!("{:?} {}", e.r#type, e.path.display()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_coalesce_changes_in_order() {
+        assert_eq!(
+            batch(vec![
+                change("a", EventType::create),
+                change("a", EventType::update),
+                change("b", EventType::delete),
+                change("b", EventType::create),
+                change("c", EventType::update),
+                change("c", EventType::delete),
+            ]),
+            vec!["create a", "update b", "delete c"]
+        );
+    }
+
+    #[test]
+    fn should_coalesce_renames() {
+        assert_eq!(
+            batch(vec![rename("a", "b"), rename("b", "c")]),
+            vec!["rename a -> c"]
+        );
+        assert_eq!(
+            batch(vec![rename("a", "b"), change("b", EventType::update)]),
+            vec!["rename a -> b"]
+        );
+        assert_eq!(
+            batch(vec![rename("a", "b"), rename("b", "a")]),
+            vec!["update a"]
+        );
+        assert_eq!(
+            batch(vec![change("a", EventType::create), rename("a", "b")]),
+            vec!["create b"]
+        );
+    }
+
+    #[test]
+    fn should_split_renames_which_no_longer_apply() {
+        assert_eq!(
+            batch(vec![rename("a", "b"), change("b", EventType::delete)]),
+            vec!["delete a", "delete b"]
+        );
+        assert_eq!(
+            batch(vec![rename("a", "b"), change("a", EventType::create)]),
+            vec!["update a", "create b"]
+        );
+        assert_eq!(
+            batch(vec![rename("a", "b"), rename("c", "b")]),
+            vec!["delete a", "rename c -> b"]
+        );
+    }
+
+    #[test]
+    fn should_send_batches() {
+        let send_all = |limits: BatchLimits| {
+            let (sender, receiver) = unbounded();
+            sender
+                .send(vec![
+                    change("a", EventType::create),
+                    change("b", EventType::create),
+                ])
+                .unwrap();
+            sender
+                .send(vec![
+                    change("a", EventType::update),
+                    change("c", EventType::create),
+                ])
+                .unwrap();
+            drop(sender);
+
+            let mut batches = vec![];
+            batch_events(receiver, limits, |events| batches.push(events.len()));
+            batches
+        };
+        let limits = BatchLimits {
+            debounce: Duration::from_secs(60),
+            max_count: None,
+            max_size: None,
+        };
+
+        assert_eq!(send_all(limits), vec![3]);
+        assert_eq!(
+            send_all(BatchLimits {
+                max_count: Some(2),
+                ..limits
+            }),
+            vec![2, 2]
+        );
+        assert_eq!(
+            send_all(BatchLimits {
+                max_size: Some(1),
+                ..limits
+            }),
+            vec![1, 1, 1, 1]
+        );
+    }
+}
@@ -1,3 +1,7 @@
+mod event_batch;
+mod poller;
+mod status;
+mod subscriptions;
 mod types;
 mod utils;
 mod watch_filterer;
@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use ignore::WalkBuilder;
+use tracing::trace;
+
+use crate::native::watch::status::{StatusReporter, WatchStatusType};
+use crate::native::watch::types::{EventType, WatchEventInternal};
+use crate::native::watch::watch_filterer::WatchFilterer;
+
+/// File systems which do not report changes made by other machines, or by the host of a container, to inotify
+const POLLED_FILE_SYSTEMS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "9p",
+    "vboxsf",
+    "fuse.sshfs",
+];
+
+#[derive(Debug, PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    size: u64,
+}
+
+impl FileStamp {
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        (!metadata.is_dir()).then(|| FileStamp {
+            modified: metadata.modified().ok(),
+            size: metadata.len(),
+        })
+    }
+}
+
+type FileStamps = HashMap<PathBuf, FileStamp>;
+
+/// Stats the files in the directory which the filter does not ignore.
+/// Ignored directories are skipped rather than walked, unless negated globs include files in them again.
+fn read_file_stamps(directory: &Path, filter: &WatchFilterer) -> FileStamps {
+    let walk_filter = filter.clone();
+    WalkBuilder::new(directory)
+        .standard_filters(false)
+        .filter_entry(move |entry| {
+            !entry.file_type().is_some_and(|t| t.is_dir())
+                || !walk_filter.is_directory_ignored(entry.path())
+        })
+        .build()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            if entry.file_type().is_some_and(|t| t.is_dir()) || filter.is_ignored(entry.path()) {
+                return None;
+            }
+            let metadata = entry.metadata().ok()?;
+            Some((
+                entry.into_path(),
+                FileStamp {
+                    modified: metadata.modified().ok(),
+                    size: metadata.len(),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// The files which were created, updated or deleted between two polls, sorted by path
+fn diff_file_stamps<'a>(
+    previous: impl IntoIterator<Item = (&'a PathBuf, &'a FileStamp)>,
+    current: &FileStamps,
+) -> Vec<(PathBuf, EventType)> {
+    let previous = previous.into_iter().collect::<HashMap<_, _>>();
+    let mut changes = current
+        .iter()
+        .filter_map(|(path, stamp)| match previous.get(path) {
+            None => Some((path.clone(), EventType::create)),
+            Some(previous_stamp) if *previous_stamp != stamp => {
+                Some((path.clone(), EventType::update))
+            }
+            Some(_) => None,
+        })
+        .chain(
+            previous
+                .keys()
+                .filter(|path| !current.contains_key(**path))
+                .map(|path| ((*path).clone(), EventType::delete)),
+        )
+        .collect::<Vec<_>>();
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+    changes
+}
+
+/// The modification times and sizes of the files in the workspace, which changes are found by comparing against.
+/// Only files which the watcher reports changes to are indexed, but each of them is stat'ed on every rescan,
+/// so watching ignored files like build outputs makes polling and rescans slower.
+pub(super) struct FileIndex {
+    origin: String,
+    roots: Vec<PathBuf>,
+    filter: WatchFilterer,
+    stamps: FileStamps,
+}
+
+impl FileIndex {
+    pub fn read(origin: String, roots: Vec<PathBuf>, filter: WatchFilterer) -> Self {
+        let stamps = roots
+            .iter()
+            .flat_map(|root| read_file_stamps(root, &filter))
+            .collect::<FileStamps>();
+        trace!(files = stamps.len(), "indexed the workspace");
+        FileIndex {
+            origin,
+            roots,
+            filter,
+            stamps,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.stamps.len()
+    }
+
+    /// Walks every root again, and returns the changes to their files since they were last indexed
+    pub fn rescan_roots(&mut self) -> Vec<WatchEventInternal> {
+        let roots = self.roots.clone();
+        roots.iter().flat_map(|root| self.rescan(root)).collect()
+    }
+
+    /// Walks the directory again, and returns the changes to its files since they were last indexed
+    pub fn rescan(&mut self, directory: &Path) -> Vec<WatchEventInternal> {
+        let current = read_file_stamps(directory, &self.filter);
+        let changes = diff_file_stamps(
+            self.stamps
+                .iter()
+                .filter(|(path, _)| path.starts_with(directory)),
+            &current,
+        );
+        self.stamps.retain(|path, _| !path.starts_with(directory));
+        self.stamps.extend(current);
+
+        changes
+            .into_iter()
+            .map(|(path, r#type)| WatchEventInternal {
+                path,
+                r#type,
+                from: None,
+                origin: self.origin.clone(),
+            })
+            .collect()
+    }
+
+    /// Keeps the index up to date with changes which were reported natively
+    pub fn update(&mut self, events: &[WatchEventInternal]) {
+        for event in events {
+            if let Some(from) = &event.from {
+                self.stamps.remove(from);
+            }
+            match FileStamp::read(&event.path) {
+                Some(stamp) => self.stamps.insert(event.path.clone(), stamp),
+                None => self.stamps.remove(&event.path),
+            };
+        }
+    }
+}
+
+/// What the index of a native watcher is told about
+pub(super) enum IndexMessage {
+    /// Changes which were reported natively
+    Events(Vec<WatchEventInternal>),
+    /// Changes in the directories were lost, or in every root when no directories are known
+    Overflow(Vec<PathBuf>),
+}
+
+/// Keeps the index of a native watcher up to date, and sends the changes found by rescanning the directories of each overflow.
+/// Events received while indexing or rescanning are applied afterwards, and overflows received together are rescanned once.
+/// `rescan_pending` is unset once queued overflows are taken.
+pub(super) fn index_files(
+    mut index: FileIndex,
+    messages: Receiver<IndexMessage>,
+    rescan_pending: Arc<AtomicBool>,
+    events: Sender<Vec<WatchEventInternal>>,
+) {
+    while let Ok(message) = messages.recv() {
+        rescan_pending.store(false, Ordering::Relaxed);
+        let mut directories = vec![];
+        let mut overflowed = false;
+        let mut all_roots = false;
+        for message in std::iter::once(message).chain(messages.try_iter()) {
+            match message {
+                IndexMessage::Events(events) => index.update(&events),
+                IndexMessage::Overflow(overflowed_directories) => {
+                    overflowed = true;
+                    // an overflow of unknown directories affects every root
+                    all_roots |= overflowed_directories.is_empty();
+                    directories.extend(overflowed_directories);
+                }
+            }
+        }
+        if !overflowed {
+            continue;
+        }
+        if all_roots {
+            directories = index.roots.clone();
+        }
+
+        let changes = directories
+            .iter()
+            .flat_map(|directory| index.rescan(directory))
+            .collect::<Vec<_>>();
+        trace!(?directories, ?changes, "rescanned after an overflow");
+        if !changes.is_empty() && events.send(changes).is_err() {
+            break;
+        }
+    }
+    trace!("stopped indexing");
+}
+
+/// Walks the roots of the index every `interval` and sends the changes since the previous walk, until `stop` is closed or sent to
+pub(super) fn poll(
+    mut index: FileIndex,
+    interval: Duration,
+    stop: Receiver<()>,
+    events: Sender<Vec<WatchEventInternal>>,
+    status: StatusReporter,
+) {
+    let origin_path = PathBuf::from(&index.origin);
+    trace!(files = index.len(), ?interval, "polling for changes");
+    status.report(WatchStatusType::started);
+
+    let mut root_deleted = false;
+    while let Err(RecvTimeoutError::Timeout) = stop.recv_timeout(interval) {
+        if !origin_path.exists() {
+            if !root_deleted {
+                status.report(WatchStatusType::rootDeleted);
+                root_deleted = true;
+            }
+            continue;
+        }
+        root_deleted = false;
+
+        let changes = index.rescan_roots();
+        if changes.is_empty() {
+            continue;
+        }
+        trace!(?changes, "polled changes");
+        if events.send(changes).is_err() {
+            break;
+        }
+    }
+    trace!("stopped polling");
+}
+
+/// Whether changes to the directory have to be polled, because its file system does not report them natively
+#[cfg(target_os = "linux")]
+pub(super) fn is_polling_required(directory: &Path) -> bool {
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return false;
+    };
+    let directory = std::fs::canonicalize(directory).unwrap_or_else(|_| directory.to_owned());
+    let fs_type = mount_fs_type(&mounts, &directory);
+    trace!(?directory, ?fs_type, "checking the file system type");
+    fs_type.is_some_and(|fs_type| POLLED_FILE_SYSTEMS.contains(&fs_type))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(super) fn is_polling_required(_directory: &Path) -> bool {
+    false
+}
+
+/// The type of the file system mounted closest to the path, from a mount table like `/proc/self/mounts`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn mount_fs_type<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    let mut closest: Option<(usize, &str)> = None;
+    for line in mounts.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(_), Some(mount_point), Some(fs_type)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        // spaces in mount points are escaped
+        let mount_point = mount_point.replace("\\040", " ");
+        // later mounts hide earlier mounts on the same mount point
+        if path.starts_with(&mount_point)
+            && closest.is_none_or(|(length, _)| mount_point.len() >= length)
+        {
+            closest = Some((mount_point.len(), fs_type));
+        }
+    }
+    closest.map(|(_, fs_type)| fs_type)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::native::watch::watch_filterer::create_filter;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    #[tokio::test]
+    async fn should_diff_polled_files() {
+        let temp = TempDir::new().unwrap();
+        temp.child("updated.txt").write_str("content").unwrap();
+        temp.child("deleted.txt").write_str("content").unwrap();
+        temp.child("unchanged.txt").write_str("content").unwrap();
+        let filter = create_filter(&temp.path().to_string_lossy(), &[], true)
+            .await
+            .unwrap();
+        let previous = read_file_stamps(temp.path(), &filter);
+
+        temp.child("updated.txt").write_str("new content").unwrap();
+        std::fs::remove_file(temp.child("deleted.txt").path()).unwrap();
+        temp.child("nested/created.txt").write_str("").unwrap();
+        let current = read_file_stamps(temp.path(), &filter);
+
+        let changes = diff_file_stamps(&previous, &current)
+            .into_iter()
+            .map(|(path, r#type)| {
+                let path = path.strip_prefix(temp.path()).unwrap().to_owned();
+                format!("{:?} {}", r#type, path.display())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                "delete deleted.txt",
+                "create nested/created.txt",
+                "update updated.txt"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_only_read_files_which_are_not_ignored() {
+        let temp = TempDir::new().unwrap();
+        temp.child(".gitignore").write_str("dist/").unwrap();
+        temp.child(".nxignore").write_str("tmp/").unwrap();
+        for file in [
+            "src/main.ts",
+            "dist/main.js",
+            "tmp/file.txt",
+            "node_modules/module/index.js",
+            ".nx/cache/file",
+            ".nx/workspace-data/d/server-process.json",
+        ] {
+            temp.child(file).write_str("").unwrap();
+        }
+        let origin = temp.path().to_string_lossy().to_string();
+        let globs = [
+            "node_modules/".to_string(),
+            ".nx/".to_string(),
+            "!.nx/workspace-data/d/server-process.json".to_string(),
+        ];
+        let read_files = |filter: &WatchFilterer| {
+            let mut files = read_file_stamps(temp.path(), filter)
+                .into_keys()
+                .map(|path| {
+                    let path = path.strip_prefix(temp.path()).unwrap().to_owned();
+                    path.display().to_string()
+                })
+                .collect::<Vec<_>>();
+            files.sort();
+            files
+        };
+
+        let filter = create_filter(&origin, &globs, true).await.unwrap();
+        assert_eq!(
+            read_files(&filter),
+            vec![
+                ".gitignore",
+                ".nx/workspace-data/d/server-process.json",
+                ".nxignore",
+                "src/main.ts"
+            ]
+        );
+        let filter = create_filter(&origin, &globs, false).await.unwrap();
+        assert_eq!(
+            read_files(&filter),
+            vec![
+                ".gitignore",
+                ".nx/workspace-data/d/server-process.json",
+                ".nxignore",
+                "dist/main.js",
+                "src/main.ts"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_rescan_directories_for_changes() {
+        let temp = TempDir::new().unwrap();
+        temp.child("a/updated.txt").write_str("content").unwrap();
+        temp.child("a/deleted.txt").write_str("content").unwrap();
+        temp.child("b/changed.txt").write_str("content").unwrap();
+        let origin = temp.path().to_string_lossy().to_string();
+        let filter = create_filter(&origin, &[], true).await.unwrap();
+        let mut index = FileIndex::read(origin, vec![temp.to_path_buf()], filter);
+
+        temp.child("a/updated.txt")
+            .write_str("new content")
+            .unwrap();
+        std::fs::remove_file(temp.child("a/deleted.txt").path()).unwrap();
+        temp.child("b/changed.txt")
+            .write_str("new content")
+            .unwrap();
+        // reported natively
+        temp.child("a/created.txt").write_str("").unwrap();
+        index.update(&[WatchEventInternal {
+            path: temp.child("a/created.txt").to_path_buf(),
+            r#type: EventType::create,
+            from: None,
+            origin: String::new(),
+        }]);
+
+        let rescan = |index: &mut FileIndex, directory: &str| {
+            index
+                .rescan(&temp.path().join(directory))
+                .into_iter()
+                .map(|e| {
+                    let path = e.path.strip_prefix(temp.path()).unwrap().to_owned();
+                    format!("{:?} {}", e.r#type, path.display())
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            rescan(&mut index, "a"),
+            vec!["delete a/deleted.txt", "update a/updated.txt"]
+        );
+        assert_eq!(rescan(&mut index, "a"), Vec::<String>::new());
+        assert_eq!(rescan(&mut index, "b"), vec!["update b/changed.txt"]);
+    }
+
+    #[test]
+    fn should_find_the_file_system_of_a_path() {
+        let mounts = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+server:/export /mnt/work nfs4 rw,relatime 0 0
+tmpfs /mnt/work/tmp tmpfs rw 0 0
+user@host:/code /home/my\\040code fuse.sshfs rw 0 0
+";
+        assert_eq!(mount_fs_type(mounts, Path::new("/home/user")), Some("ext4"));
+        assert_eq!(
+            mount_fs_type(mounts, Path::new("/mnt/work/repo")),
+            Some("nfs4")
+        );
+        assert_eq!(
+            mount_fs_type(mounts, Path::new("/mnt/work/tmp/a")),
+            Some("tmpfs")
+        );
+        assert_eq!(
+            mount_fs_type(mounts, Path::new("/mnt/workspace")),
+            Some("ext4")
+        );
+        assert_eq!(
+            mount_fs_type(mounts, Path::new("/home/my code/repo")),
+            Some("fuse.sshfs")
+        );
+    }
+}
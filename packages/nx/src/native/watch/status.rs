@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use parking_lot::Mutex;
+use tracing::{trace, warn};
+use watchexec::error::{FsWatcherError, RuntimeError};
+use watchexec_events::filekind::FileEventKind;
+use watchexec_events::{Event, Tag};
+
+#[napi(string_enum)]
+#[derive(Debug, PartialEq)]
+pub enum WatchStatusType {
+    #[allow(non_camel_case_types)]
+    started,
+    /// The OS limit of watched directories was reached, so changes in some directories are not reported
+    #[allow(non_camel_case_types)]
+    watchLimitReached,
+    /// Changes were lost because they came in faster than they could be read.
+    /// The affected directories are rescanned and the changes found are reported, when there is a status callback.
+    /// The files are indexed when a watcher with a status callback starts, so deleted files are found as well.
+    #[allow(non_camel_case_types)]
+    overflow,
+    #[allow(non_camel_case_types)]
+    rootDeleted,
+}
+
+#[napi(object)]
+#[derive(Debug, PartialEq)]
+pub struct WatchStatus {
+    pub r#type: WatchStatusType,
+    /// The maximum number of watched directories, when the watch limit was reached
+    pub limit: Option<u32>,
+}
+
+impl From<WatchStatusType> for WatchStatus {
+    fn from(r#type: WatchStatusType) -> Self {
+        WatchStatus {
+            r#type,
+            limit: None,
+        }
+    }
+}
+
+type StatusCallback = Box<dyn Fn(WatchStatus) + Send>;
+
+/// Passes statuses to the callback set with `Watcher.onStatus`. Statuses are dropped while there is no callback.
+#[derive(Clone, Default)]
+pub(super) struct StatusReporter(Arc<Mutex<Option<StatusCallback>>>);
+
+impl StatusReporter {
+    pub fn set_callback(&self, callback: ThreadsafeFunction<WatchStatus>) {
+        self.set_handler(move |status| {
+            callback.call(Ok(status), ThreadsafeFunctionCallMode::NonBlocking);
+        });
+    }
+
+    pub fn set_handler(&self, handler: impl Fn(WatchStatus) + Send + 'static) {
+        *self.0.lock() = Some(Box::new(handler));
+    }
+
+    pub fn has_callback(&self) -> bool {
+        self.0.lock().is_some()
+    }
+
+    pub fn report(&self, status: impl Into<WatchStatus>) {
+        let status = status.into();
+        trace!(?status, "watcher status");
+        if let Some(handler) = self.0.lock().as_ref() {
+            handler(status);
+        }
+    }
+}
+
+/// Events which are about the watcher itself rather than a file
+#[derive(Debug, PartialEq)]
+pub(super) enum StatusEvent {
+    /// Events were lost, and the directories have to be scanned for changes.
+    /// The whole watched directory is affected when no directories are known.
+    Overflow(Vec<PathBuf>),
+    RootDeleted,
+}
+
+pub(super) fn status_event(event: &Event, origin: &Path) -> Option<StatusEvent> {
+    let is_overflow = event
+        .tags
+        .iter()
+        .any(|t| matches!(t, Tag::FileEventKind(FileEventKind::Other)));
+    if is_overflow {
+        return Some(StatusEvent::Overflow(
+            event.paths().map(|(path, _)| path.to_owned()).collect(),
+        ));
+    }
+
+    if event.paths().any(|(path, _)| path == origin) && !origin.exists() {
+        return Some(StatusEvent::RootDeleted);
+    }
+
+    None
+}
+
+/// Turns errors of the watcher into statuses
+pub(super) fn error_status(error: &RuntimeError) -> Option<StatusEvent> {
+    match error {
+        RuntimeError::EventChannelTrySend { .. } => Some(StatusEvent::Overflow(vec![])),
+        _ => None,
+    }
+}
+
+pub(super) fn is_watch_limit_error(error: &RuntimeError) -> bool {
+    let RuntimeError::FsWatcher { err, .. } = error else {
+        return false;
+    };
+    let (FsWatcherError::PathAdd { err, .. } | FsWatcherError::Event(err)) = err else {
+        return false;
+    };
+    match &err.kind {
+        notify::ErrorKind::MaxFilesWatch => true,
+        // ENOSPC
+        notify::ErrorKind::Io(e) => e.raw_os_error() == Some(28),
+        _ => false,
+    }
+}
+
+/// The maximum number of directories inotify can watch
+pub(super) fn watch_limit() -> Option<u32> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    match std::fs::read_to_string("/proc/sys/fs/inotify/max_user_watches") {
+        Ok(limit) => limit.trim().parse().ok(),
+        Err(e) => {
+            warn!("could not read the inotify watch limit: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+    use watchexec_events::filekind::{ModifyKind, RemoveKind};
+
+    fn event(kind: FileEventKind, paths: &[&Path]) -> Event {
+        Event {
+            tags: paths
+                .iter()
+                .map(|path| Tag::Path {
+                    path: path.to_path_buf(),
+                    file_type: None,
+                })
+                .chain([Tag::FileEventKind(kind)])
+                .collect(),
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn should_find_status_events() {
+        let temp = TempDir::new().unwrap();
+        let origin = temp.path().to_owned();
+        let file = origin.join("file.txt");
+
+        assert_eq!(
+            status_event(&event(FileEventKind::Other, &[]), &origin),
+            Some(StatusEvent::Overflow(vec![]))
+        );
+        assert_eq!(
+            status_event(&event(FileEventKind::Other, &[&file]), &origin),
+            Some(StatusEvent::Overflow(vec![file.clone()]))
+        );
+        assert_eq!(
+            status_event(
+                &event(FileEventKind::Remove(RemoveKind::File), &[&file]),
+                &origin
+            ),
+            None
+        );
+        // the directory still exists
+        assert_eq!(
+            status_event(
+                &event(FileEventKind::Modify(ModifyKind::Any), &[&origin]),
+                &origin
+            ),
+            None
+        );
+
+        temp.close().unwrap();
+        assert_eq!(
+            status_event(
+                &event(FileEventKind::Remove(RemoveKind::Folder), &[&origin]),
+                &origin
+            ),
+            Some(StatusEvent::RootDeleted)
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use tracing::trace;
+
+use crate::native::glob::{NxGlobSet, build_glob_set};
+use crate::native::project_graph::utils::{
+    ProjectRootMappings, find_project_for_path, normalize_project_root,
+};
+use crate::native::watch::types::{EventType, WatchEvent, WatchEventInternal};
+use crate::native::watch::watch_filterer::WatchFilterer;
+
+/// Filters the events passed to a subscription. Files have to match every filter which is set.
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct WatchSubscriptionOptions {
+    /// The directory which contains the files, relative to the workspace root
+    pub root: Option<String>,
+    /// Globs which files have to match
+    pub include: Option<Vec<String>>,
+    /// Globs which files must not match
+    pub exclude: Option<Vec<String>>,
+    /// The projects which own the files, according to the roots passed to `Watcher.setProjectRoots`
+    pub projects: Option<Vec<String>>,
+    /// Drops changes to files ignored by `.gitignore` files, which a watcher created with `useIgnore` set to false passes on.
+    /// Lets subscriptions to sources and to outputs share one watcher.
+    pub use_ignore: Option<bool>,
+}
+
+pub(super) struct SubscriptionFilter {
+    root: Option<PathBuf>,
+    include: Option<NxGlobSet>,
+    exclude: Option<NxGlobSet>,
+    projects: Option<HashSet<String>>,
+    ignore: Option<WatchFilterer>,
+}
+
+impl SubscriptionFilter {
+    /// Files ignored by `ignore` are dropped as well, when it is set
+    pub fn new(
+        options: WatchSubscriptionOptions,
+        ignore: Option<WatchFilterer>,
+    ) -> anyhow::Result<Self> {
+        let glob_set = |globs: Option<Vec<String>>| {
+            globs
+                .filter(|globs| !globs.is_empty())
+                .map(|globs| build_glob_set(&globs))
+                .transpose()
+        };
+        Ok(Self {
+            root: options.root.map(PathBuf::from),
+            include: glob_set(options.include)?,
+            exclude: glob_set(options.exclude)?,
+            projects: options
+                .projects
+                .map(|projects| projects.into_iter().collect()),
+            ignore,
+        })
+    }
+
+    fn is_match(&self, path: &str, project_roots: &ProjectRootMappings) -> bool {
+        let path = Path::new(path);
+        self.root.as_ref().is_none_or(|root| path.starts_with(root))
+            && self
+                .include
+                .as_ref()
+                .is_none_or(|globs| globs.is_match(path))
+            && self
+                .exclude
+                .as_ref()
+                .is_none_or(|globs| !globs.is_match(path))
+            && self.projects.as_ref().is_none_or(|projects| {
+                find_project_for_path(path, project_roots)
+                    .is_some_and(|project| projects.contains(project))
+            })
+            && self
+                .ignore
+                .as_ref()
+                .is_none_or(|ignore| !ignore.is_ignored(&ignore.origin.join(path)))
+    }
+
+    /// The event as the subscription sees it. A file renamed into or out of what the subscription
+    /// watches is created or deleted for it.
+    pub fn filter_event(
+        &self,
+        event: &WatchEvent,
+        project_roots: &ProjectRootMappings,
+    ) -> Option<WatchEvent> {
+        let Some(from) = event.from.as_ref() else {
+            return self
+                .is_match(&event.path, project_roots)
+                .then(|| event.clone());
+        };
+        match (
+            self.is_match(from, project_roots),
+            self.is_match(&event.path, project_roots),
+        ) {
+            (true, true) => Some(event.clone()),
+            (false, true) => Some(WatchEvent {
+                path: event.path.clone(),
+                r#type: EventType::create,
+                from: None,
+            }),
+            (true, false) => Some(WatchEvent {
+                path: from.clone(),
+                r#type: EventType::delete,
+                from: None,
+            }),
+            (false, false) => None,
+        }
+    }
+}
+
+struct Subscription {
+    filter: SubscriptionFilter,
+    callback: ThreadsafeFunction<Vec<WatchEvent>>,
+}
+
+/// The subscriptions of a `Watcher`, which share its events
+#[derive(Default)]
+pub(super) struct Subscriptions {
+    subscriptions: HashMap<u32, Subscription>,
+    next_id: u32,
+    project_roots: ProjectRootMappings,
+}
+
+impl Subscriptions {
+    pub fn add(
+        &mut self,
+        filter: SubscriptionFilter,
+        callback: ThreadsafeFunction<Vec<WatchEvent>>,
+    ) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.subscriptions
+            .insert(id, Subscription { filter, callback });
+        id
+    }
+
+    pub fn remove(&mut self, id: u32) -> bool {
+        self.subscriptions.remove(&id).is_some()
+    }
+
+    /// Sets the roots of the projects, by project name
+    pub fn set_project_roots(&mut self, project_roots: HashMap<String, String>) {
+        self.project_roots = project_roots
+            .into_iter()
+            .map(|(project, root)| (normalize_project_root(&root), project))
+            .collect();
+    }
+
+    /// Passes the events to every subscription which matches any of them
+    pub fn send(&self, events: &[WatchEventInternal]) {
+        let events: Vec<WatchEvent> = events.iter().map(WatchEvent::from).collect();
+        for (id, subscription) in &self.subscriptions {
+            let events = events
+                .iter()
+                .filter_map(|e| subscription.filter.filter_event(e, &self.project_roots))
+                .collect::<Vec<_>>();
+            if events.is_empty() {
+                continue;
+            }
+            trace!(id, ?events, "sending to node");
+            subscription
+                .callback
+                .call(Ok(events), ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::native::watch::watch_filterer::create_filter;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    fn event(path: &str, r#type: EventType) -> WatchEvent {
+        WatchEvent {
+            path: path.into(),
+            r#type,
+            from: None,
+        }
+    }
+
+    fn rename(from: &str, to: &str) -> WatchEvent {
+        WatchEvent {
+            from: Some(from.into()),
+            ..event(to, EventType::rename)
+        }
+    }
+
+    fn filter_events(options: WatchSubscriptionOptions, events: &[WatchEvent]) -> Vec<String> {
+        let project_roots = HashMap::from([
+            ("libs/lib".to_string(), "lib".to_string()),
+            ("apps/app".to_string(), "app".to_string()),
+        ]);
+        let filter = SubscriptionFilter::new(options, None).unwrap();
+        events
+            .iter()
+            .filter_map(|e| filter.filter_event(e, &project_roots))
+            .map(|e| format!("{:?} {}", e.r#type, e.path))
+            .collect()
+    }
+
+    #[test]
+    fn should_filter_events() {
+        let events = [
+            event("apps/app/src/main.ts", EventType::update),
+            event("apps/app/src/main.spec.ts", EventType::create),
+            event("libs/lib/src/index.ts", EventType::delete),
+            event("README.md", EventType::update),
+        ];
+        assert_eq!(
+            filter_events(
+                WatchSubscriptionOptions {
+                    include: Some(vec!["**/*.ts".into()]),
+                    exclude: Some(vec!["**/*.spec.ts".into()]),
+                    ..Default::default()
+                },
+                &events
+            ),
+            vec![
+                "update apps/app/src/main.ts",
+                "delete libs/lib/src/index.ts"
+            ]
+        );
+        assert_eq!(
+            filter_events(
+                WatchSubscriptionOptions {
+                    projects: Some(vec!["lib".into()]),
+                    ..Default::default()
+                },
+                &events
+            ),
+            vec!["delete libs/lib/src/index.ts"]
+        );
+        assert_eq!(
+            filter_events(
+                WatchSubscriptionOptions {
+                    root: Some("apps".into()),
+                    ..Default::default()
+                },
+                &events
+            ),
+            vec![
+                "update apps/app/src/main.ts",
+                "create apps/app/src/main.spec.ts"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_filter_renames_by_both_paths() {
+        let options = || WatchSubscriptionOptions {
+            projects: Some(vec!["app".into()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter_events(
+                options(),
+                &[
+                    rename("apps/app/a.ts", "apps/app/b.ts"),
+                    rename("libs/lib/a.ts", "apps/app/c.ts"),
+                    rename("apps/app/d.ts", "libs/lib/d.ts"),
+                    rename("libs/lib/e.ts", "libs/lib/f.ts"),
+                ]
+            ),
+            vec![
+                "rename apps/app/b.ts",
+                "create apps/app/c.ts",
+                "delete apps/app/d.ts"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_drop_ignored_files_when_using_ignore() {
+        let temp = TempDir::new().unwrap();
+        temp.child(".gitignore").write_str("dist/").unwrap();
+        let origin = temp.path().to_string_lossy().to_string();
+        let ignore = create_filter(&origin, &[], true).await.unwrap();
+        let filter = SubscriptionFilter::new(
+            WatchSubscriptionOptions {
+                use_ignore: Some(true),
+                ..Default::default()
+            },
+            Some(ignore),
+        )
+        .unwrap();
+
+        let events = [
+            event("dist/main.js", EventType::create),
+            event("src/main.ts", EventType::update),
+            rename("src/old.ts", "dist/old.ts"),
+        ];
+        let events = events
+            .iter()
+            .filter_map(|e| filter.filter_event(e, &HashMap::new()))
+            .map(|e| format!("{:?} {}", e.r#type, e.path))
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec!["update src/main.ts", "delete src/old.ts"]);
+    }
+}
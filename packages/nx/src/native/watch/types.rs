@@ -18,6 +18,8 @@ pub enum EventType {
     update,
     #[allow(non_camel_case_types)]
     create,
+    #[allow(non_camel_case_types)]
+    rename,
 }
 
 #[derive(Debug, Clone)]
@@ -25,23 +27,29 @@ pub enum EventType {
 pub struct WatchEvent {
     pub path: String,
     pub r#type: EventType,
+    /// The path a renamed file was moved from, it was moved to `path`
+    pub from: Option<String>,
 }
 
 impl From<&WatchEventInternal> for WatchEvent {
     fn from(value: &WatchEventInternal) -> Self {
-        let path = value
-            .path
-            .strip_prefix(&value.origin)
-            .unwrap_or(&value.path)
-            .display()
-            .to_string();
+        let relative_path = |path: &Path| {
+            let path = path
+                .strip_prefix(&value.origin)
+                .unwrap_or(path)
+                .display()
+                .to_string();
+
+            #[cfg(windows)]
+            let path = path.replace('\\', "/");
 
-        #[cfg(windows)]
-        let path = path.replace('\\', "/");
+            path
+        };
 
         WatchEvent {
-            path,
+            path: relative_path(&value.path),
             r#type: value.r#type,
+            from: value.from.as_deref().map(relative_path),
         }
     }
 }
@@ -50,6 +58,8 @@ impl From<&WatchEventInternal> for WatchEvent {
 pub(super) struct WatchEventInternal {
     pub path: PathBuf,
     pub r#type: EventType,
+    /// The previous path of a renamed file
+    pub from: Option<PathBuf>,
     pub origin: String,
 }
 
@@ -66,8 +76,6 @@ pub fn transform_event_to_watch_events(
         anyhow::bail!(error_msg)
     };
 
-    #[allow(unused_variables)]
-    // this is used in linux and windows blocks, and will show it as being unused in macos
     let Some(event_kind) = value.tags.iter().find_map(|t| match t {
         Tag::FileEventKind(event_kind) => Some(event_kind),
         _ => None,
@@ -77,11 +85,24 @@ pub fn transform_event_to_watch_events(
         anyhow::bail!(error_msg)
     };
 
+    if let FileEventKind::Modify(Name(RenameMode::Both)) = event_kind {
+        let mut paths = value.paths();
+        if let (Some((from, _)), Some((to, _))) = (paths.next(), paths.next()) {
+            return Ok(vec![WatchEventInternal {
+                path: to.into(),
+                r#type: EventType::rename,
+                from: Some(from.into()),
+                origin: origin.to_owned(),
+            }]);
+        }
+    }
+
     let path_ref = path.0;
     if path.1.is_none() && !path_ref.exists() {
         Ok(vec![WatchEventInternal {
             path: path_ref.into(),
             r#type: EventType::delete,
+            from: None,
             origin: origin.to_owned(),
         }])
     } else {
@@ -112,6 +133,7 @@ pub fn transform_event_to_watch_events(
             Ok(vec![WatchEventInternal {
                 path: path_ref.into(),
                 r#type: event_type,
+                from: None,
                 origin,
             }])
         }
@@ -150,6 +172,7 @@ pub fn transform_event_to_watch_events(
                     result.push(WatchEventInternal {
                         path,
                         r#type: EventType::create,
+                        from: None,
                         origin: origin.to_owned(),
                     });
                 }
@@ -180,6 +203,7 @@ fn create_watch_event_internal(
     vec![WatchEventInternal {
         path: path_ref.into(),
         r#type: event_kind,
+        from: None,
         origin: origin.to_owned(),
     }]
 }
@@ -1,8 +1,10 @@
 use ignore::WalkBuilder;
 use ignore_files::IgnoreFile;
+use std::collections::HashSet;
 use std::path::Path;
 use std::{fs, path::PathBuf};
 use tracing::trace;
+use watchexec_events::filekind::{FileEventKind, ModifyKind, RenameMode};
 use watchexec_events::{Event, Tag};
 
 pub(super) fn get_ignore_files<T: AsRef<str>>(
@@ -79,3 +81,128 @@ pub(super) fn transform_event(watch_event: &Event) -> Option<Event> {
         None
     }
 }
+
+fn rename_mode(event: &Event) -> Option<RenameMode> {
+    event.tags.iter().find_map(|t| match t {
+        Tag::FileEventKind(FileEventKind::Modify(ModifyKind::Name(mode))) => Some(*mode),
+        _ => None,
+    })
+}
+
+/// Combines the two sides of each rename into a single `Modify(Name(Both))` event with the old and the new path.
+///
+/// inotify reports the old path, the new path and then both of them together,
+/// Windows only reports the old path followed by the new path,
+/// and FSEvents reports both paths without telling which one is the old one, so the path which no longer exists is assumed to be it.
+pub(super) fn pair_renames(events: &[Event]) -> Vec<Event> {
+    let paired_paths: HashSet<&Path> = events
+        .iter()
+        .filter(|e| rename_mode(e) == Some(RenameMode::Both))
+        .flat_map(|e| e.paths().map(|(path, _)| path))
+        .collect();
+
+    let mut result = Vec::with_capacity(events.len());
+    let mut events = events.iter().peekable();
+    while let Some(event) = events.next() {
+        let to = match rename_mode(event) {
+            Some(RenameMode::From | RenameMode::To)
+                if event.paths().all(|(path, _)| paired_paths.contains(path)) =>
+            {
+                continue;
+            }
+            Some(RenameMode::From) => {
+                events.next_if(|next| rename_mode(next) == Some(RenameMode::To))
+            }
+            Some(RenameMode::Any) if event.paths().all(|(path, _)| !path.exists()) => events
+                .next_if(|next| {
+                    rename_mode(next) == Some(RenameMode::Any)
+                        && next.paths().all(|(path, _)| path.exists())
+                }),
+            _ => None,
+        };
+
+        match to {
+            Some(to) => result.push(Event {
+                tags: event
+                    .tags
+                    .iter()
+                    .chain(to.tags.iter())
+                    .filter(|t| matches!(t, Tag::Path { .. }))
+                    .cloned()
+                    .chain([Tag::FileEventKind(FileEventKind::Modify(ModifyKind::Name(
+                        RenameMode::Both,
+                    )))])
+                    .collect(),
+                metadata: event.metadata.clone(),
+            }),
+            None => result.push(event.clone()),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rename_event(mode: RenameMode, paths: &[&str]) -> Event {
+        Event {
+            tags: paths
+                .iter()
+                .map(|path| Tag::Path {
+                    path: path.into(),
+                    file_type: None,
+                })
+                .chain([Tag::FileEventKind(FileEventKind::Modify(ModifyKind::Name(
+                    mode,
+                )))])
+                .collect(),
+            metadata: Default::default(),
+        }
+    }
+
+    fn paths(events: &[Event]) -> Vec<(Option<RenameMode>, Vec<&Path>)> {
+        events
+            .iter()
+            .map(|e| (rename_mode(e), e.paths().map(|(path, _)| path).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn should_pair_renames() {
+        // inotify
+        let events = pair_renames(&[
+            rename_event(RenameMode::From, &["/a"]),
+            rename_event(RenameMode::To, &["/b"]),
+            rename_event(RenameMode::Both, &["/a", "/b"]),
+            rename_event(RenameMode::From, &["/c"]),
+        ]);
+        assert_eq!(
+            paths(&events),
+            vec![
+                (
+                    Some(RenameMode::Both),
+                    vec![Path::new("/a"), Path::new("/b")]
+                ),
+                (Some(RenameMode::From), vec![Path::new("/c")]),
+            ]
+        );
+
+        // Windows
+        let events = pair_renames(&[
+            rename_event(RenameMode::To, &["/a"]),
+            rename_event(RenameMode::From, &["/b"]),
+            rename_event(RenameMode::To, &["/c"]),
+        ]);
+        assert_eq!(
+            paths(&events),
+            vec![
+                (Some(RenameMode::To), vec![Path::new("/a")]),
+                (
+                    Some(RenameMode::Both),
+                    vec![Path::new("/b"), Path::new("/c")]
+                ),
+            ]
+        );
+    }
+}
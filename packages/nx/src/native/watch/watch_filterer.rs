@@ -1,3 +1,5 @@
+use std::path::{Path, PathBuf};
+
 use ignore::Match;
 use tracing::trace;
 use watchexec::error::RuntimeError;
@@ -8,12 +10,16 @@ use ignore_files::IgnoreFilter;
 use watchexec_events::{Event, FileType, Priority, Source, Tag};
 use watchexec_filterer_ignore::IgnoreFilterer;
 
+use crate::native::watch::status::status_event;
 use crate::native::watch::utils::{get_ignore_files, get_nx_ignore, transform_event};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WatchFilterer {
+    pub origin: PathBuf,
     pub nx_ignore: Option<IgnoreFilter>,
     pub git_ignore: IgnoreFilterer,
+    /// The directories under which negated globs can include files again
+    pub negated_directories: Vec<PathBuf>,
 }
 
 impl WatchFilterer {
@@ -48,11 +54,45 @@ impl WatchFilterer {
 
         pass
     }
+
+    /// Whether the ignore files or globs of the filter ignore the file
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.is_path_ignored(path, FileType::File)
+    }
+
+    /// Whether the directory is ignored and no negated glob can include any of its files again,
+    /// so nothing in it has to be looked at
+    pub fn is_directory_ignored(&self, path: &Path) -> bool {
+        !self
+            .negated_directories
+            .iter()
+            .any(|directory| directory.starts_with(path) || path.starts_with(directory))
+            && self.is_path_ignored(path, FileType::Dir)
+    }
+
+    fn is_path_ignored(&self, path: &Path, file_type: FileType) -> bool {
+        let event = Event {
+            tags: vec![
+                Tag::Path {
+                    path: path.to_owned(),
+                    file_type: Some(file_type),
+                },
+                Tag::Source(Source::Filesystem),
+            ],
+            metadata: Default::default(),
+        };
+        !self.filter_event(&event, Priority::Normal)
+    }
 }
 
 /// Used to filter out events that that come from watchexec
 impl Filterer for WatchFilterer {
     fn check_event(&self, watch_event: &Event, priority: Priority) -> Result<bool, RuntimeError> {
+        // overflows and the deletion of the watched directory are reported as statuses
+        if status_event(watch_event, &self.origin).is_some() {
+            return Ok(true);
+        }
+
         let transformed = transform_event(watch_event);
         let event = transformed.as_ref().unwrap_or(watch_event);
 
@@ -145,6 +185,10 @@ pub(super) async fn create_filter(
         )
         .map_err(anyhow::Error::from)?;
 
+    let nx_ignore_globs = nx_ignore_file
+        .as_ref()
+        .and_then(|file| std::fs::read_to_string(&file.path).ok())
+        .unwrap_or_default();
     let nx_ignore = if let Some(nx_ignore_file) = nx_ignore_file {
         Some(
             IgnoreFilter::new(origin, &[nx_ignore_file])
@@ -155,8 +199,57 @@ pub(super) async fn create_filter(
         None
     };
 
+    let negated_directories = additional_globs
+        .iter()
+        .map(String::as_str)
+        .chain(nx_ignore_globs.lines())
+        .filter_map(|glob| negated_directory(origin, glob))
+        .collect();
+
     Ok(WatchFilterer {
+        origin: origin.into(),
         git_ignore: IgnoreFilterer(git_ignore),
         nx_ignore,
+        negated_directories,
     })
 }
+
+/// The deepest directory which every file a negated glob includes is in.
+/// Globs without a `/` can include files in any directory.
+fn negated_directory(origin: &str, glob: &str) -> Option<PathBuf> {
+    let glob = glob.trim().strip_prefix('!')?;
+    let glob = glob.strip_suffix('/').unwrap_or(glob);
+    let Some(anchored) = glob
+        .strip_prefix('/')
+        .or(glob.contains('/').then_some(glob))
+    else {
+        return Some(origin.into());
+    };
+    let literal = anchored
+        .find(['*', '?', '[', '{', '\\'])
+        .map_or(anchored, |end| &anchored[..end]);
+    let directory = literal
+        .rsplit_once('/')
+        .map_or("", |(directory, _)| directory);
+    Some(Path::new(origin).join(directory))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_find_the_directories_of_negated_globs() {
+        let directory = |glob: &str| {
+            negated_directory("/repo", glob).map(|directory| directory.display().to_string())
+        };
+        assert_eq!(directory(".nx/"), None);
+        assert_eq!(
+            directory("!.nx/workspace-data/d/server-process.json"),
+            Some("/repo/.nx/workspace-data/d".into())
+        );
+        assert_eq!(directory("!/dist/keep/"), Some("/repo/dist".into()));
+        assert_eq!(directory("!dist/**/*.map"), Some("/repo/dist".into()));
+        assert_eq!(directory("!.env.*"), Some("/repo".into()));
+    }
+}
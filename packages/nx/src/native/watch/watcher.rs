@@ -1,30 +1,169 @@
 use std::collections::HashMap;
-use std::collections::hash_map::Entry;
-use std::path::MAIN_SEPARATOR;
+use std::path::{MAIN_SEPARATOR, Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
+use crate::native::watch::event_batch::{BatchLimits, batch_events};
+use crate::native::watch::poller::{
+    FileIndex, IndexMessage, index_files, is_polling_required, poll,
+};
+use crate::native::watch::status::{
+    StatusEvent, StatusReporter, WatchStatus, WatchStatusType, error_status, is_watch_limit_error,
+    status_event, watch_limit,
+};
+use crate::native::watch::subscriptions::{
+    SubscriptionFilter, Subscriptions, WatchSubscriptionOptions,
+};
 use crate::native::watch::types::{
-    EventType, WatchEvent, WatchEventInternal, transform_event_to_watch_events,
+    WatchEvent, WatchEventInternal, transform_event_to_watch_events,
 };
+use crate::native::watch::utils::pair_renames;
 use crate::native::watch::watch_filterer;
+use crate::native::watch::watch_filterer::WatchFilterer;
+use crossbeam_channel::Sender;
 use napi::bindgen_prelude::*;
-use napi::threadsafe_function::{
-    ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
-};
+use napi::threadsafe_function::{ThreadSafeCallContext, ThreadsafeFunction};
 use napi::{Env, JsFunction, JsObject};
+use parking_lot::Mutex;
 use rayon::prelude::*;
 use tracing::trace;
 use tracing_subscriber::EnvFilter;
-use watchexec::Watchexec;
+use watchexec::{Config, ErrorHook, Watchexec};
 use watchexec_events::{Event, Priority, Tag};
 use watchexec_signals::Signal;
 
+const DEFAULT_DEBOUNCE_MS: u32 = 100;
+const DEFAULT_POLL_INTERVAL_MS: u32 = 2000;
+
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct WatcherOptions {
+    /// How long to wait for more changes before calling back, in milliseconds. Defaults to 100.
+    /// Events keep being collected while changes come in, but for at most 10 seconds.
+    pub debounce_ms: Option<u32>,
+    /// The maximum number of events passed to a single callback
+    pub max_batch_count: Option<u32>,
+    /// The maximum total length of the paths passed to a single callback, in bytes
+    pub max_batch_size: Option<u32>,
+    /// Polls for changes instead of relying on native file system events.
+    /// Defaults to polling when the workspace is on a file system which does not report changes natively, like NFS or SSHFS.
+    /// Every poll stats each file which is not ignored, so polling costs more with `useIgnore` set to false,
+    /// which includes build outputs. Setting `roots` or a longer `pollIntervalMs` limits the cost.
+    pub polling: Option<bool>,
+    /// How often to poll for changes, in milliseconds. Defaults to 2000.
+    pub poll_interval_ms: Option<u32>,
+    /// The directories to watch, relative to the origin. Defaults to the whole origin.
+    /// Paths of events stay relative to the origin.
+    pub roots: Option<Vec<String>>,
+    /// How many native events are buffered before changes are lost and the workspace is rescanned. Defaults to 4096.
+    pub event_channel_size: Option<u32>,
+}
+
+impl From<&WatcherOptions> for BatchLimits {
+    fn from(options: &WatcherOptions) -> Self {
+        BatchLimits {
+            debounce: Duration::from_millis(
+                options.debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS) as u64
+            ),
+            max_count: options.max_batch_count.map(|max| max as usize),
+            max_size: options.max_batch_size.map(|max| max as usize),
+        }
+    }
+}
+
 #[napi]
 pub struct Watcher {
     pub origin: String,
     watch_exec: Arc<Watchexec>,
     additional_globs: Vec<String>,
     use_ignore: bool,
+    options: WatcherOptions,
+    subscriptions: Arc<Mutex<Subscriptions>>,
+    /// Filters subscriptions which use ignore files when the watcher does not
+    ignore_filter: Option<WatchFilterer>,
+    started: bool,
+    /// Closed to stop polling, when polling for changes
+    stop_polling: Option<Sender<()>>,
+    status: StatusReporter,
+}
+
+/// Reports the statuses found in native events and errors, and recovers from overflows
+#[derive(Clone)]
+struct StatusHandler {
+    origin: PathBuf,
+    status: StatusReporter,
+    /// Tells the index about native events and overflows
+    index_sender: Sender<IndexMessage>,
+    /// Set when the watcher started with a status callback, so that the files are indexed to recover from overflows
+    indexing: bool,
+    /// Set while an overflow has not been taken by the index, so that overflows coming in until then are reported once
+    rescan_pending: Arc<AtomicBool>,
+    watch_limit_reached: Arc<AtomicBool>,
+    root_deleted: Arc<AtomicBool>,
+}
+
+impl StatusHandler {
+    fn new(origin: PathBuf, status: StatusReporter, index_sender: Sender<IndexMessage>) -> Self {
+        StatusHandler {
+            origin,
+            indexing: status.has_callback(),
+            status,
+            index_sender,
+            rescan_pending: Default::default(),
+            watch_limit_reached: Default::default(),
+            root_deleted: Default::default(),
+        }
+    }
+
+    fn handle_event(&self, event: StatusEvent) {
+        match event {
+            StatusEvent::Overflow(directories) => self.rescan(directories),
+            StatusEvent::RootDeleted => {
+                if !self.root_deleted.swap(true, Ordering::Relaxed) {
+                    self.status.report(WatchStatusType::rootDeleted);
+                }
+            }
+        }
+    }
+
+    fn handle_error(&self, hook: ErrorHook) {
+        if is_watch_limit_error(&hook.error) {
+            if !self.watch_limit_reached.swap(true, Ordering::Relaxed) {
+                self.status.report(WatchStatus {
+                    r#type: WatchStatusType::watchLimitReached,
+                    limit: watch_limit(),
+                });
+            }
+        } else if let Some(event) = error_status(&hook.error) {
+            self.handle_event(event);
+        } else {
+            trace!(error = ?hook.error, "watch exec error");
+        }
+    }
+
+    /// Has the index send the changes in the directories which were lost.
+    /// Only watchers with a status callback index their files, since nothing else would know that changes were lost.
+    fn rescan(&self, directories: Vec<PathBuf>) {
+        if !self.indexing {
+            trace!(?directories, "not rescanning without a status callback");
+            return;
+        }
+        if !self.rescan_pending.swap(true, Ordering::Relaxed) {
+            self.status.report(WatchStatusType::overflow);
+        }
+        // sending only fails if the index is gone
+        _ = self.index_sender.send(IndexMessage::Overflow(directories));
+    }
+
+    /// Keeps the index up to date
+    fn handle_file_events(&self, events: &[WatchEventInternal]) {
+        if self.indexing {
+            _ = self
+                .index_sender
+                .send(IndexMessage::Events(events.to_vec()));
+        }
+    }
 }
 
 #[napi]
@@ -34,11 +173,15 @@ impl Watcher {
     /// * .git/
     /// * node_modules/
     /// * .nx/
+    ///
+    /// Changes are debounced and coalesced, so a burst of changes like a branch switch
+    /// is passed to the callback as a single batch with at most one event per file.
     #[napi(constructor)]
     pub fn new(
         origin: String,
         additional_globs: Option<Vec<String>>,
         use_ignore: Option<bool>,
+        options: Option<WatcherOptions>,
     ) -> Watcher {
         // always have these globs come before the additional globs
         let mut globs = vec![
@@ -53,49 +196,165 @@ impl Watcher {
             globs.extend(additional_globs);
         }
 
+        let options = options.unwrap_or_default();
+        let mut config = Config::default();
+        if let Some(event_channel_size) = options.event_channel_size {
+            config.event_channel_size = event_channel_size.max(1) as usize;
+        }
+
         Watcher {
             origin: if cfg!(windows) {
                 origin.replace('/', "\\")
             } else {
                 origin
             },
-            watch_exec: Arc::new(Watchexec::default()),
+            watch_exec: Arc::new(
+                Watchexec::with_config(config).expect("the watcher is configured before it starts"),
+            ),
             additional_globs: globs,
             use_ignore: use_ignore.unwrap_or(true),
+            options,
+            subscriptions: Default::default(),
+            ignore_filter: None,
+            started: false,
+            stop_polling: None,
+            status: Default::default(),
         }
     }
 
+    /// Passes every change to the callback
     #[napi]
     pub fn watch(
         &mut self,
         env: Env,
         #[napi(ts_arg_type = "(err: string | null, events: WatchEvent[]) => void")]
         callback: JsFunction,
+    ) -> anyhow::Result<()> {
+        self.subscribe(env, callback, None)?;
+        Ok(())
+    }
+
+    /// Passes the changes which match the options to the callback, and returns the id of the subscription.
+    /// All subscriptions share the same watcher, which is started by the first one.
+    #[napi]
+    pub fn subscribe(
+        &mut self,
+        env: Env,
+        #[napi(ts_arg_type = "(err: string | null, events: WatchEvent[]) => void")]
+        callback: JsFunction,
+        options: Option<WatchSubscriptionOptions>,
+    ) -> anyhow::Result<u32> {
+        let options = options.unwrap_or_default();
+        let ignore = match options.use_ignore {
+            Some(true) if !self.use_ignore => Some(self.ignore_filter()?),
+            _ => None,
+        };
+        let filter = SubscriptionFilter::new(options, ignore)?;
+
+        let mut callback_tsfn: ThreadsafeFunction<Vec<WatchEvent>> = callback
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<Vec<WatchEvent>>| {
+                Ok(vec![ctx.value])
+            })?;
+        callback_tsfn.unref(&env)?;
+
+        let id = self.subscriptions.lock().add(filter, callback_tsfn);
+        if !self.started {
+            self.start(env)?;
+            self.started = true;
+        }
+        Ok(id)
+    }
+
+    /// Stops passing changes to the subscription. Returns false if there is no such subscription.
+    #[napi]
+    pub fn unsubscribe(&mut self, id: u32) -> bool {
+        self.subscriptions.lock().remove(id)
+    }
+
+    /// Sets the roots of the projects, by project name, which subscriptions filter projects with
+    #[napi]
+    pub fn set_project_roots(&mut self, project_roots: HashMap<String, String>) {
+        self.subscriptions.lock().set_project_roots(project_roots);
+    }
+
+    /// Passes the statuses of the watcher to the callback: when it started, when it could not watch every directory,
+    /// when changes were lost and the workspace was rescanned, and when the workspace was deleted.
+    /// Has to be called before watching to be told that the watcher started, and to recover from overflows,
+    /// for which the files which are not ignored are indexed when the watcher starts.
+    #[napi]
+    pub fn on_status(
+        &mut self,
+        env: Env,
+        #[napi(ts_arg_type = "(err: string | null, status: WatchStatus) => void")]
+        callback: JsFunction,
     ) -> Result<()> {
+        let mut callback_tsfn: ThreadsafeFunction<WatchStatus> = callback
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<WatchStatus>| {
+                Ok(vec![ctx.value])
+            })?;
+        callback_tsfn.unref(&env)?;
+        self.status.set_callback(callback_tsfn);
+        Ok(())
+    }
+
+    /// The filter of a watcher which uses ignore files, read once for every subscription which needs it
+    fn ignore_filter(&mut self) -> anyhow::Result<WatchFilterer> {
+        if let Some(filter) = &self.ignore_filter {
+            return Ok(filter.clone());
+        }
+        let filter = block_on(watch_filterer::create_filter(
+            &self.origin,
+            &self.additional_globs,
+            true,
+        ))?;
+        self.ignore_filter = Some(filter.clone());
+        Ok(filter)
+    }
+
+    /// The watched directories
+    fn roots(&self) -> Vec<PathBuf> {
+        match &self.options.roots {
+            Some(roots) if !roots.is_empty() => roots
+                .iter()
+                .map(|root| Path::new(&self.origin).join(root))
+                .collect(),
+            _ => vec![PathBuf::from(&self.origin)],
+        }
+    }
+
+    fn start(&mut self, env: Env) -> Result<()> {
         _ = tracing_subscriber::fmt()
             .with_env_filter(EnvFilter::from_env("NX_NATIVE_LOGGING"))
             .try_init();
 
-        let mut callback_tsfn: ThreadsafeFunction<HashMap<String, WatchEventInternal>> = callback
-            .create_threadsafe_function(
-            0,
-            |ctx: ThreadSafeCallContext<HashMap<String, WatchEventInternal>>| {
-                let mut watch_events: Vec<WatchEvent> = vec![];
-                trace!(?ctx.value, "Base collection that will be sent");
-
-                for event in ctx.value.values() {
-                    watch_events.push(event.into());
-                }
+        let (event_sender, event_receiver) = crossbeam_channel::unbounded();
+        let limits = BatchLimits::from(&self.options);
+        let subscriptions = self.subscriptions.clone();
+        std::thread::spawn(move || {
+            batch_events(event_receiver, limits, |events| {
+                trace!(?events, "Base collection that will be sent");
+                subscriptions.lock().send(&events);
+            });
+        });
 
-                trace!(?watch_events, "sending to node");
+        let polling = self
+            .options
+            .polling
+            .unwrap_or_else(|| is_polling_required(Path::new(&self.origin)));
+        if polling {
+            return self.start_polling(env, event_sender);
+        }
 
-                Ok(vec![watch_events])
-            },
-        )?;
-
-        callback_tsfn.unref(&env)?;
+        let (index_sender, index_receiver) = crossbeam_channel::unbounded();
+        let handler = StatusHandler::new(
+            PathBuf::from(&self.origin),
+            self.status.clone(),
+            index_sender,
+        );
 
         let origin = self.origin.clone();
+        let action_handler = handler.clone();
+        let action_event_sender = event_sender.clone();
         self.watch_exec.config.on_action(move |mut action| {
             let signals: Vec<Signal> = action.signals().collect();
 
@@ -117,54 +376,65 @@ impl Watcher {
             }
             trace!(?origin_path);
 
-            let events = action
-                .events
+            let file_events = pair_renames(&action.events)
+                .into_iter()
+                .filter(|ev| match status_event(ev, &action_handler.origin) {
+                    Some(status_event) => {
+                        action_handler.handle_event(status_event);
+                        false
+                    }
+                    None => true,
+                })
+                .collect::<Vec<_>>();
+            let events = file_events
                 .par_iter()
                 .filter_map(|ev| transform_event_to_watch_events(ev, &origin_path).ok())
                 .flatten()
                 .collect::<Vec<WatchEventInternal>>();
 
-            let mut group_events: HashMap<String, WatchEventInternal> = HashMap::new();
-            for g in events.into_iter() {
-                let path = g.path.display().to_string();
-
-                // Delete > Create > Modify
-                match group_events.entry(path) {
-                    // Delete should override anything
-                    Entry::Occupied(mut e) if matches!(g.r#type, EventType::delete) => {
-                        e.insert(g);
-                    }
-                    // Create should override update
-                    Entry::Occupied(mut e)
-                        if matches!(g.r#type, EventType::create)
-                            && matches!(e.get().r#type, EventType::update) =>
-                    {
-                        e.insert(g);
-                    }
-                    Entry::Occupied(_) => {}
-                    // If its empty, insert
-                    Entry::Vacant(e) => {
-                        e.insert(g);
-                    }
-                }
+            if !events.is_empty() {
+                action_handler.handle_file_events(&events);
+                // sending only fails if the batching thread is gone
+                _ = action_event_sender.send(events);
             }
-            callback_tsfn.call(Ok(group_events), ThreadsafeFunctionCallMode::NonBlocking);
 
             action
         });
 
         let origin = self.origin.clone();
+        let roots = self.roots();
         let additional_globs = self.additional_globs.clone();
         let use_ignore = self.use_ignore;
         let watch_exec = self.watch_exec.clone();
         let start = async move {
             trace!("configuring watch exec");
-            watch_exec.config.pathset([&origin.as_str()]);
-            watch_exec.config.filterer(
-                watch_filterer::create_filter(&origin, &additional_globs, use_ignore).await?,
-            );
+            watch_exec.config.pathset(&roots);
+            let filter =
+                watch_filterer::create_filter(&origin, &additional_globs, use_ignore).await?;
+
+            if handler.indexing {
+                let index_filter = filter.clone();
+                let rescan_pending = handler.rescan_pending.clone();
+                // native events are queued while the files are indexed, and applied afterwards
+                std::thread::spawn(move || {
+                    index_files(
+                        FileIndex::read(origin, roots, index_filter),
+                        index_receiver,
+                        rescan_pending,
+                        event_sender,
+                    )
+                });
+            }
+
+            watch_exec.config.filterer(filter);
+            let status = handler.status.clone();
+            watch_exec
+                .config
+                .on_error(move |hook: ErrorHook| handler.handle_error(hook));
             trace!("starting watch exec");
-            watch_exec.main().await.map_err(anyhow::Error::from)?.ok();
+            let main = watch_exec.main();
+            status.report(WatchStatusType::started);
+            main.await.map_err(anyhow::Error::from)?.ok();
             Ok(())
         };
 
@@ -173,8 +443,52 @@ impl Watcher {
         Ok(())
     }
 
+    fn start_polling(
+        &mut self,
+        env: Env,
+        event_sender: Sender<Vec<WatchEventInternal>>,
+    ) -> Result<()> {
+        let (stop_sender, stop_receiver) = crossbeam_channel::bounded(0);
+        self.stop_polling = Some(stop_sender);
+
+        let origin = self.origin.clone();
+        let roots = self.roots();
+        let additional_globs = self.additional_globs.clone();
+        let use_ignore = self.use_ignore;
+        let interval = Duration::from_millis(
+            self.options
+                .poll_interval_ms
+                .unwrap_or(DEFAULT_POLL_INTERVAL_MS) as u64,
+        );
+        let status = self.status.clone();
+        let start = async move {
+            let filter =
+                watch_filterer::create_filter(&origin, &additional_globs, use_ignore).await?;
+            std::thread::spawn(move || {
+                poll(
+                    FileIndex::read(origin, roots, filter),
+                    interval,
+                    stop_receiver,
+                    event_sender,
+                    status,
+                )
+            });
+            Ok(())
+        };
+
+        env.spawn_future(start)?;
+        trace!("started polling");
+        Ok(())
+    }
+
     #[napi(ts_return_type = "Promise<void>")]
     pub fn stop(&mut self, env: Env) -> Result<JsObject> {
+        if let Some(stop_polling) = self.stop_polling.take() {
+            trace!("stopping the poller");
+            drop(stop_polling);
+            return env.spawn_future(async { Ok(()) });
+        }
+
         trace!("stopping the watch process");
         let watch_exec = self.watch_exec.clone();
         let send_terminate = async move {
@@ -195,3 +509,153 @@ impl Watcher {
         env.spawn_future(send_terminate)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::native::watch::types::EventType;
+    use crate::native::watch::watch_filterer::create_filter;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+    use crossbeam_channel::{Receiver, RecvTimeoutError};
+
+    type Statuses = Arc<Mutex<Vec<WatchStatusType>>>;
+
+    fn status_handler(
+        temp: &TempDir,
+        with_callback: bool,
+    ) -> (StatusHandler, Receiver<IndexMessage>, Statuses) {
+        let status = StatusReporter::default();
+        let statuses: Statuses = Default::default();
+        if with_callback {
+            let statuses = statuses.clone();
+            status.set_handler(move |status| statuses.lock().push(status.r#type));
+        }
+        let (index_sender, index_receiver) = crossbeam_channel::unbounded();
+        let handler = StatusHandler::new(temp.to_path_buf(), status, index_sender);
+        (handler, index_receiver, statuses)
+    }
+
+    async fn read_index(temp: &TempDir) -> FileIndex {
+        let origin = temp.path().to_string_lossy().to_string();
+        let filter = create_filter(&origin, &[], true).await.unwrap();
+        FileIndex::read(origin, vec![temp.to_path_buf()], filter)
+    }
+
+    fn start_indexing(
+        handler: &StatusHandler,
+        index: FileIndex,
+        index_receiver: Receiver<IndexMessage>,
+    ) -> Receiver<Vec<WatchEventInternal>> {
+        let rescan_pending = handler.rescan_pending.clone();
+        let (event_sender, event_receiver) = crossbeam_channel::unbounded();
+        std::thread::spawn(move || {
+            index_files(index, index_receiver, rescan_pending, event_sender)
+        });
+        event_receiver
+    }
+
+    fn receive(temp: &TempDir, events: &Receiver<Vec<WatchEventInternal>>) -> Vec<String> {
+        events
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap()
+            .into_iter()
+            .map(|e| {
+                let path = e.path.strip_prefix(temp.path()).unwrap().to_owned();
+                format!("{:?} {}", e.r#type, path.display())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn should_report_files_deleted_during_the_first_overflow() {
+        let temp = TempDir::new().unwrap();
+        temp.child("a/deleted.txt").write_str("").unwrap();
+        let (handler, index_receiver, statuses) = status_handler(&temp, true);
+        let index = read_index(&temp).await;
+        let events = start_indexing(&handler, index, index_receiver);
+
+        std::fs::remove_file(temp.child("a/deleted.txt").path()).unwrap();
+        handler.handle_event(StatusEvent::Overflow(vec![temp.child("a").to_path_buf()]));
+
+        assert_eq!(receive(&temp, &events), vec!["delete a/deleted.txt"]);
+        assert_eq!(*statuses.lock(), vec![WatchStatusType::overflow]);
+    }
+
+    #[tokio::test]
+    async fn should_send_the_changes_lost_in_overflows() {
+        let temp = TempDir::new().unwrap();
+        temp.child("a/updated.txt").write_str("content").unwrap();
+        temp.child("a/deleted.txt").write_str("content").unwrap();
+        temp.child("b/unchanged.txt").write_str("content").unwrap();
+        let (handler, index_receiver, statuses) = status_handler(&temp, true);
+        let index = read_index(&temp).await;
+
+        temp.child("a/updated.txt")
+            .write_str("new content")
+            .unwrap();
+        std::fs::remove_file(temp.child("a/deleted.txt").path()).unwrap();
+        temp.child("a/created.txt").write_str("").unwrap();
+        // reported natively, so it is not sent again
+        temp.child("a/native.txt").write_str("").unwrap();
+        handler.handle_file_events(&[WatchEventInternal {
+            path: temp.child("a/native.txt").to_path_buf(),
+            r#type: EventType::create,
+            from: None,
+            origin: String::new(),
+        }]);
+        // overflows which come in before the index takes them are reported and rescanned once
+        handler.handle_event(StatusEvent::Overflow(vec![temp.child("a").to_path_buf()]));
+        handler.handle_event(StatusEvent::Overflow(vec![]));
+        let events = start_indexing(&handler, index, index_receiver);
+
+        assert_eq!(
+            receive(&temp, &events),
+            vec![
+                "create a/created.txt",
+                "delete a/deleted.txt",
+                "update a/updated.txt"
+            ]
+        );
+        assert_eq!(
+            events.recv_timeout(Duration::from_millis(200)).err(),
+            Some(RecvTimeoutError::Timeout)
+        );
+        assert_eq!(*statuses.lock(), vec![WatchStatusType::overflow]);
+
+        std::fs::remove_file(temp.child("b/unchanged.txt").path()).unwrap();
+        handler.handle_event(StatusEvent::Overflow(vec![temp.child("b").to_path_buf()]));
+
+        assert_eq!(receive(&temp, &events), vec!["delete b/unchanged.txt"]);
+        assert_eq!(
+            *statuses.lock(),
+            vec![WatchStatusType::overflow, WatchStatusType::overflow]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_rescan_without_a_status_callback() {
+        let temp = TempDir::new().unwrap();
+        temp.child("file.txt").write_str("").unwrap();
+        let (handler, index_receiver, _) = status_handler(&temp, false);
+        let index = read_index(&temp).await;
+        std::fs::remove_file(temp.child("file.txt").path()).unwrap();
+        handler.handle_event(StatusEvent::Overflow(vec![]));
+        let events = start_indexing(&handler, index, index_receiver);
+
+        assert_eq!(
+            events.recv_timeout(Duration::from_millis(200)).err(),
+            Some(RecvTimeoutError::Timeout)
+        );
+        assert!(!handler.indexing);
+    }
+
+    #[test]
+    fn should_report_that_the_root_was_deleted_once() {
+        let temp = TempDir::new().unwrap();
+        let (handler, _, statuses) = status_handler(&temp, true);
+        handler.handle_event(StatusEvent::RootDeleted);
+        handler.handle_event(StatusEvent::RootDeleted);
+        assert_eq!(*statuses.lock(), vec![WatchStatusType::rootDeleted]);
+    }
+}
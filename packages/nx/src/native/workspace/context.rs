@@ -1,43 +1,79 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::mem;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use crate::native::hasher::hash;
+use crate::native::glob::build_glob_set;
+use crate::native::glob::glob_lint::{
+    GlobDiagnostic, GlobDiagnosticSeverity, contains_interpolation, lint_glob,
+};
 use crate::native::logger::enable_logger;
-use crate::native::project_graph::utils::{ProjectRootMappings, find_project_for_path};
 use crate::native::types::FileData;
-use crate::native::utils::{Normalize, NxCondvar, NxMutex, path::get_child_files};
+use crate::native::utils::{Normalize, NxCondvar, NxMutex};
+use crate::native::workspace::errors::{InternalWorkspaceErrors, WorkspaceErrors};
 use crate::native::workspace::files_archive::{read_files_archive, write_files_archive};
-use crate::native::workspace::files_hashing::{full_files_hash, selective_files_hash};
-use crate::native::workspace::types::{
-    FileMap, NxWorkspaceFilesExternals, ProjectFiles, UpdatedWorkspaceFiles,
+use crate::native::workspace::files_hashing::{
+    FileHasher, full_files_hash, git_files_hash, selective_files_hash,
+};
+use crate::native::workspace::files_query::{
+    DuplicateFiles, FileFilter, FilesQuery, FilesQueryResult, find_duplicates, paginate,
 };
+use crate::native::workspace::git_index::find_git_dir;
+use crate::native::workspace::project_file_map::ProjectFileMap;
+use crate::native::workspace::types::{NxWorkspaceFilesExternals, ProjectFileMapUpdate};
 use crate::native::workspace::{config_files, types::NxWorkspaceFiles, workspace_files};
-use napi::bindgen_prelude::External;
 use rayon::prelude::*;
 use tracing::{trace, warn};
 use xxhash_rust::xxh3;
 
+#[napi(object)]
+#[derive(Debug, Default)]
+pub struct WorkspaceContextOptions {
+    /// Reuses the blob ids of files which are unchanged in the git index instead of hashing them,
+    /// so that only untracked and modified files are hashed.
+    /// Every file is then hashed like git hashes blobs, so file hashes differ from the default mode.
+    pub seed_from_git_index: Option<bool>,
+}
+
 #[napi]
 pub struct WorkspaceContext {
     pub workspace_root: String,
     workspace_root_path: PathBuf,
     files_worker: FilesWorker,
+    project_file_map: NxMutex<TrackedProjectFileMap>,
 }
 
-type Files = Vec<(PathBuf, String)>;
+/// The project file map kept by `track_workspace_files`, and its generation,
+/// which changes whenever the project file map is replaced or updated
+#[derive(Default)]
+struct TrackedProjectFileMap {
+    generation: u32,
+    project_file_map: Option<ProjectFileMap>,
+}
 
-fn gather_and_hash_files(workspace_root: &Path, cache_dir: String) -> Vec<(PathBuf, String)> {
-    let archived_files = read_files_archive(&cache_dir);
+type Files = Vec<(PathBuf, String)>;
 
+fn gather_and_hash_files(
+    workspace_root: &Path,
+    cache_dir: String,
+    file_hasher: FileHasher,
+) -> (Files, Option<InternalWorkspaceErrors>) {
     trace!("Gathering files in {}", workspace_root.display());
     let now = std::time::Instant::now();
-    let file_hashes = if let Some(archived_files) = archived_files {
-        selective_files_hash(workspace_root, archived_files)
+    let mut archive_error = None;
+    let file_hashes = if file_hasher == FileHasher::GitBlob {
+        git_files_hash(workspace_root)
     } else {
-        full_files_hash(workspace_root)
+        match read_files_archive(&cache_dir, workspace_root) {
+            Ok(Some(archived_files)) => selective_files_hash(workspace_root, archived_files),
+            Ok(None) => full_files_hash(workspace_root),
+            Err(e) => {
+                warn!("{}", e);
+                archive_error = Some(e);
+                full_files_hash(workspace_root)
+            }
+        }
     };
 
     let mut files = file_hashes
@@ -47,26 +83,36 @@ fn gather_and_hash_files(workspace_root: &Path, cache_dir: String) -> Vec<(PathB
     files.par_sort();
     trace!("hashed and sorted files in {:?}", now.elapsed());
 
-    write_files_archive(&cache_dir, file_hashes);
+    // the archive only holds hashes of the default mode
+    if file_hasher == FileHasher::Xxh3 {
+        write_files_archive(&cache_dir, workspace_root, file_hashes);
+    }
 
-    files
+    (files, archive_error)
 }
 
 #[derive(Default)]
-struct FilesWorker(Option<Arc<(NxMutex<Files>, NxCondvar)>>);
+struct FilesWorker(
+    Option<Arc<(NxMutex<Files>, NxCondvar)>>,
+    FileHasher,
+    /// Set while the files are locked for gathering, so it is known once the files are available
+    Arc<NxMutex<Option<InternalWorkspaceErrors>>>,
+);
 impl FilesWorker {
     #[cfg(not(target_arch = "wasm32"))]
-    fn gather_files(workspace_root: &Path, cache_dir: String) -> Self {
+    fn gather_files(workspace_root: &Path, cache_dir: String, file_hasher: FileHasher) -> Self {
         if !workspace_root.exists() {
             warn!(
                 "workspace root does not exist: {}",
                 workspace_root.display()
             );
-            return FilesWorker(None);
+            return FilesWorker(None, file_hasher, Default::default());
         }
 
         let files_lock = Arc::new((NxMutex::new(Vec::new()), NxCondvar::new()));
         let files_lock_clone = Arc::clone(&files_lock);
+        let archive_error: Arc<NxMutex<Option<InternalWorkspaceErrors>>> = Default::default();
+        let archive_error_clone = Arc::clone(&archive_error);
         let workspace_root = workspace_root.to_owned();
 
         std::thread::spawn(move || {
@@ -74,8 +120,11 @@ impl FilesWorker {
             trace!("Initially locking files");
             let mut workspace_files = lock.lock().expect("Should be the first time locking files");
 
-            let files = gather_and_hash_files(&workspace_root, cache_dir);
+            let (files, error) = gather_and_hash_files(&workspace_root, cache_dir, file_hasher);
 
+            *archive_error_clone
+                .lock()
+                .expect("Should be able to lock the archive error") = error;
             *workspace_files = files;
             let files_len = workspace_files.len();
             trace!(?files_len, "files retrieved");
@@ -84,62 +133,75 @@ impl FilesWorker {
             cvar.notify_all();
         });
 
-        FilesWorker(Some(files_lock))
+        FilesWorker(Some(files_lock), file_hasher, archive_error)
     }
 
     #[cfg(target_arch = "wasm32")]
-    fn gather_files(workspace_root: &Path, cache_dir: String) -> Self {
+    fn gather_files(workspace_root: &Path, cache_dir: String, file_hasher: FileHasher) -> Self {
         if !workspace_root.exists() {
             warn!(
                 "workspace root does not exist: {}",
                 workspace_root.display()
             );
-            return FilesWorker(None);
+            return FilesWorker(None, file_hasher, Default::default());
         }
 
         let workspace_root = workspace_root.to_owned();
 
-        let files = gather_and_hash_files(&workspace_root, cache_dir);
+        let (files, error) = gather_and_hash_files(&workspace_root, cache_dir, file_hasher);
 
         trace!("{} files retrieved", files.len());
 
         let files_lock = Arc::new((NxMutex::new(files), NxCondvar::new()));
 
-        FilesWorker(Some(files_lock))
+        FilesWorker(Some(files_lock), file_hasher, Arc::new(NxMutex::new(error)))
     }
 
-    fn get_files(&self) -> Vec<FileData> {
-        if let Some(files_sync) = &self.0 {
-            let (files_lock, cvar) = files_sync.deref();
+    /// Waits for the files to be available and runs `f` with them, without copying them
+    fn with_files<T>(&self, f: impl FnOnce(&Files) -> T) -> T {
+        let Some(files_sync) = &self.0 else {
+            return f(&vec![]);
+        };
+        let (files_lock, cvar) = files_sync.deref();
 
-            trace!("waiting for files to be available");
-            let files = files_lock.lock().expect("Should be able to lock files");
+        trace!("waiting for files to be available");
+        let files = files_lock.lock().expect("Should be able to lock files");
 
-            #[cfg(target_arch = "wasm32")]
-            let files = cvar
-                .wait(files, |guard| guard.len() == 0)
-                .expect("Should be able to wait for files");
+        #[cfg(target_arch = "wasm32")]
+        let files = cvar
+            .wait(files, |guard| guard.len() == 0)
+            .expect("Should be able to wait for files");
 
-            #[cfg(not(target_arch = "wasm32"))]
-            let files = cvar
-                .wait(files, |guard| guard.len() == 0)
-                .expect("Should be able to wait for files");
+        #[cfg(not(target_arch = "wasm32"))]
+        let files = cvar
+            .wait(files, |guard| guard.len() == 0)
+            .expect("Should be able to wait for files");
 
-            let file_data = files
+        trace!("files are available");
+        f(&files)
+    }
+
+    fn get_files(&self) -> Vec<FileData> {
+        self.with_files(|files| {
+            files
                 .iter()
                 .map(|(path, hash)| FileData {
                     file: path.to_normalized_string(),
                     hash: hash.clone(),
                 })
-                .collect();
+                .collect()
+        })
+    }
 
-            drop(files);
+    /// Waits for the files to be gathered and takes the error from reading the files archive, if any
+    fn take_archive_error(&self) -> Option<InternalWorkspaceErrors> {
+        self.0.as_ref()?;
+        self.with_files(|_| ());
 
-            trace!("files are available");
-            file_data
-        } else {
-            vec![]
-        }
+        self.2
+            .lock()
+            .expect("Should be able to lock the archive error")
+            .take()
     }
 
     pub fn update_files(
@@ -157,18 +219,27 @@ impl FilesWorker {
         let mut files = files_lock
             .lock()
             .expect("Should always be able to update files");
-        let mut map: HashMap<PathBuf, String> = files.drain(..).collect();
 
+        // The files stay sorted by path, so they are updated in place instead of being sorted again
         for deleted_path in deleted_files_and_directories {
-            // If the path is a file, this removes it.
-            let removal = map.remove(&PathBuf::from(deleted_path));
-            if removal.is_none() {
-                // If the path is a directory, this retains only files not in the directory.
-                map.retain(|path, _| {
-                    let owned_deleted_path = deleted_path.to_owned();
-                    !path.starts_with(owned_deleted_path + "/")
-                });
-            };
+            if deleted_path.is_empty() {
+                continue;
+            }
+            let deleted_path = Path::new(deleted_path);
+            match files.binary_search_by(|(path, _)| path.as_path().cmp(deleted_path)) {
+                // If the path is a file, this removes it.
+                Ok(pos) => {
+                    files.remove(pos);
+                }
+                // If the path is a directory, the files in it are sorted right after where it would be.
+                Err(pos) => {
+                    let len = files[pos..]
+                        .iter()
+                        .take_while(|(path, _)| path.starts_with(deleted_path))
+                        .count();
+                    files.drain(pos..pos + len);
+                }
+            }
         }
 
         let updated_files_hashes: HashMap<String, String> = updated_files
@@ -179,19 +250,18 @@ impl FilesWorker {
                     trace!("could not read file: {full_path:?}");
                     return None;
                 };
-                Some((path.to_string(), hash(&content)))
+                Some((path.to_string(), self.1.hash(&content)))
             })
             .collect();
 
         for (file, hash) in &updated_files_hashes {
-            map.entry(file.into())
-                .and_modify(|e| e.clone_from(hash))
-                .or_insert(hash.clone());
+            let path = Path::new(file);
+            match files.binary_search_by(|(p, _)| p.as_path().cmp(path)) {
+                Ok(pos) => files[pos].1.clone_from(hash),
+                Err(pos) => files.insert(pos, (path.to_owned(), hash.clone())),
+            }
         }
 
-        *files = map.into_iter().collect();
-        files.par_sort();
-
         updated_files_hashes
     }
 }
@@ -199,17 +269,43 @@ impl FilesWorker {
 #[napi]
 impl WorkspaceContext {
     #[napi(constructor)]
-    pub fn new(workspace_root: String, cache_dir: String) -> Self {
+    pub fn new(
+        workspace_root: String,
+        cache_dir: String,
+        options: Option<WorkspaceContextOptions>,
+    ) -> Self {
         enable_logger();
 
-        trace!(?workspace_root);
+        trace!(?workspace_root, ?options);
 
         let workspace_root_path = PathBuf::from(&workspace_root);
 
+        let seed_from_git_index = options.and_then(|o| o.seed_from_git_index).unwrap_or(false);
+        let file_hasher = if seed_from_git_index && find_git_dir(&workspace_root_path).is_some() {
+            FileHasher::GitBlob
+        } else {
+            FileHasher::Xxh3
+        };
+
         WorkspaceContext {
-            files_worker: FilesWorker::gather_files(&workspace_root_path, cache_dir.clone()),
+            files_worker: FilesWorker::gather_files(
+                &workspace_root_path,
+                cache_dir.clone(),
+                file_hasher,
+            ),
             workspace_root,
             workspace_root_path,
+            project_file_map: Default::default(),
+        }
+    }
+
+    /// Errors with `FilesArchiveMismatch` when the file hashes archived in the cache directory
+    /// could not be reused, so every file was hashed again. The error is only reported once.
+    #[napi]
+    pub fn check_files_archive(&self) -> napi::Result<(), WorkspaceErrors> {
+        match self.files_worker.take_archive_error() {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
         }
     }
 
@@ -222,15 +318,51 @@ impl WorkspaceContext {
             .map_err(anyhow::Error::from)
     }
 
+    /// Same as `get_workspace_files`, but also keeps the project file map so that
+    /// `update_project_files` can update it in place.
+    /// Only the project graph computation which owns the context should track the project file map,
+    /// e.g. the daemon's, files requested on behalf of others must not replace the map it updates.
+    #[napi]
+    pub fn track_workspace_files(
+        &self,
+        project_root_map: HashMap<String, String>,
+    ) -> anyhow::Result<NxWorkspaceFiles> {
+        let workspace_files =
+            workspace_files::get_files(project_root_map.clone(), self.all_file_data())?;
+
+        let mut tracked = self
+            .project_file_map
+            .lock()
+            .expect("Should be able to lock the project file map");
+        tracked.generation += 1;
+        tracked.project_file_map = Some(ProjectFileMap::new(
+            project_root_map,
+            workspace_files.project_file_map.clone(),
+            workspace_files.global_files.clone(),
+        ));
+
+        Ok(workspace_files)
+    }
+
     #[napi]
     pub fn glob(
         &self,
         globs: Vec<String>,
         exclude: Option<Vec<String>>,
     ) -> napi::Result<Vec<String>> {
-        let file_data = self.all_file_data();
-        let globbed_files = config_files::glob_files(&file_data, globs, exclude)?;
-        Ok(globbed_files.map(|file| file.file.to_owned()).collect())
+        let query = FilesQuery {
+            include: Some(globs),
+            exclude,
+            ..Default::default()
+        };
+        let filter = FileFilter::new(&query)?;
+        Ok(self.files_worker.with_files(|files| {
+            filter
+                .matching(files)
+                .into_iter()
+                .map(|(path, _)| path.to_normalized_string())
+                .collect()
+        }))
     }
 
     /// Performs multiple glob pattern matches against workspace files in parallel
@@ -243,15 +375,9 @@ impl WorkspaceContext {
         globs: Vec<String>,
         exclude: Option<Vec<String>>,
     ) -> napi::Result<Vec<Vec<String>>> {
-        let file_data = self.all_file_data();
-
         globs
             .into_iter()
-            .map(|glob| {
-                let globbed_files =
-                    config_files::glob_files(&file_data, vec![glob], exclude.clone())?;
-                Ok(globbed_files.map(|file| file.file.to_owned()).collect())
-            })
+            .map(|glob| self.glob(vec![glob], exclude.clone()))
             .collect()
     }
 
@@ -306,115 +432,205 @@ impl WorkspaceContext {
             .update_files(&self.workspace_root_path, updated_files, deleted_files)
     }
 
+    /// Updates the project file map kept by the last `track_workspace_files` call in place,
+    /// with the files updated and deleted by `incremental_update`.
+    /// Returns how the files of each project changed, or nothing when there is no project file map yet.
     #[napi]
     pub fn update_project_files(
         &self,
-        project_root_mappings: ProjectRootMappings,
-        project_files: External<ProjectFiles>,
-        global_files: External<Vec<FileData>>,
         updated_files: HashMap<String, String>,
         deleted_files: Vec<&str>,
-    ) -> UpdatedWorkspaceFiles {
-        trace!("updating project files");
-        trace!("{project_root_mappings:?}");
-        let mut project_files_map = project_files.clone();
-        let mut global_files = global_files
-            .iter()
-            .map(|f| (f.file.clone(), f.hash.clone()))
-            .collect::<HashMap<_, _>>();
-
-        trace!(
-            "adding {} updated files to project files",
-            updated_files.len()
-        );
+    ) -> Option<ProjectFileMapUpdate> {
+        let mut tracked = self
+            .project_file_map
+            .lock()
+            .expect("Should be able to lock the project file map");
+        tracked.generation += 1;
+        let generation = tracked.generation;
+        let project_file_map = tracked.project_file_map.as_mut()?;
+
+        let (affected_projects, project_files, non_project_files) = project_file_map
+            .update(updated_files, deleted_files)
+            .into_parts();
+        trace!(?affected_projects, "updated project files");
+
+        Some(ProjectFileMapUpdate {
+            generation,
+            affected_projects,
+            project_files,
+            non_project_files,
+        })
+    }
 
-        let mut updated_projects = HashSet::<&str>::new();
-        for updated_file in updated_files.into_iter() {
-            let file = updated_file.0;
-            let hash = updated_file.1;
-            let project = find_project_for_path(&file, &project_root_mappings);
-            if let Some(project_files) =
-                project.and_then(|project| project_files_map.get_mut(project))
-            {
-                trace!("{file:?} was found in a project");
-                if let Some(file) = project_files.iter_mut().find(|f| f.file == file) {
-                    trace!("updating hash for file");
-                    file.hash = hash;
-                } else {
-                    trace!("{file:?} was not part of a project, adding to project files");
-                    project_files.push(FileData { file, hash });
-                    updated_projects.insert(project.expect("Project already exists"));
-                }
-            } else {
-                trace!("{file:?} was not found in any project, updating global files");
-                global_files
-                    .entry(file)
-                    .and_modify(|e| e.clone_from(&hash))
-                    .or_insert(hash);
-            }
+    /// Copies the project file map kept by `track_workspace_files` for the task hasher.
+    /// This copies every file in the workspace, so it should only be called once a hasher needs them.
+    /// Returns nothing when the project file map changed since the `generation` of an update,
+    /// since the files would not match the project file map of that update anymore.
+    #[napi]
+    pub fn get_project_file_map_references(
+        &self,
+        generation: u32,
+    ) -> Option<NxWorkspaceFilesExternals> {
+        let tracked = self
+            .project_file_map
+            .lock()
+            .expect("Should be able to lock the project file map");
+        if tracked.generation != generation {
+            trace!(
+                generation,
+                current = tracked.generation,
+                "the project file map changed"
+            );
+            return None;
         }
+        let project_file_map = tracked.project_file_map.as_ref()?;
+        Some(project_file_map.external_references(self.all_file_data()))
+    }
 
-        trace!(
-            "removing {} deleted files from project files",
-            deleted_files.len()
-        );
-        for deleted_file in deleted_files.into_iter() {
-            if let Some(project_files) = find_project_for_path(deleted_file, &project_root_mappings)
-                .and_then(|project| project_files_map.get_mut(project))
-            {
-                if let Some(pos) = project_files.iter().position(|f| f.file == deleted_file) {
-                    trace!("removing file: {deleted_file:?} from project");
-                    project_files.remove(pos);
-                }
-            }
+    #[napi]
+    pub fn all_file_data(&self) -> Vec<FileData> {
+        self.files_worker.get_files()
+    }
 
-            if global_files.contains_key(deleted_file) {
-                trace!("removing {deleted_file:?} from global files");
-                global_files.remove(deleted_file);
-            }
-        }
+    #[napi]
+    pub fn get_files_in_directory(&self, directory: String) -> Vec<String> {
+        let directory = Path::new(&directory);
+        self.files_worker.with_files(|files| {
+            files
+                .iter()
+                .filter(|(path, _)| path.starts_with(directory))
+                .map(|(path, _)| path.to_normalized_string())
+                .collect()
+        })
+    }
 
-        // sort the updated projects after deletion
-        // projects that have deleted files were not added to `updated_projects` set because deletion doesnt change the determinism
-        // but if there were any files deleted from projects, the sort should be faster becaues there potentially could be less files to sort
-        for updated_project in updated_projects {
-            trace!(updated_project, "sorting updated project");
-            if let Some(project_files) = project_files_map.get_mut(updated_project) {
-                // if the project files are less than 500, then parallel sort has too much overhead to actually be faster
-                if cfg!(target_arch = "wasm32") || project_files.len() < 500 {
-                    project_files.sort();
-                } else {
-                    project_files.par_sort();
-                }
-            }
+    /// Finds the files matching every filter of the query, sorted by path.
+    /// Only the page selected by `offset` and `limit` is copied into the result.
+    #[napi]
+    pub fn query_files(&self, query: FilesQuery) -> napi::Result<FilesQueryResult> {
+        let filter = FileFilter::new(&query)?;
+        let to_page = |matching: Vec<(&Path, &String)>| FilesQueryResult {
+            total: matching.len() as u32,
+            files: paginate(matching, &query)
+                .map(|(path, hash)| FileData {
+                    file: path.to_normalized_string(),
+                    hash: hash.clone(),
+                })
+                .collect(),
+        };
+
+        match &query.project {
+            Some(project) => self.with_project_files(project, |files| {
+                to_page(
+                    filter
+                        .matching_file_data(files)
+                        .into_iter()
+                        .map(|f| (Path::new(&f.file), &f.hash))
+                        .collect(),
+                )
+            }),
+            None => Ok(self.files_worker.with_files(|files| {
+                to_page(
+                    filter
+                        .matching(files)
+                        .into_iter()
+                        .map(|(path, hash)| (path.as_path(), hash))
+                        .collect(),
+                )
+            })),
         }
+    }
 
-        let non_project_files = global_files
-            .into_iter()
-            .map(|(file, hash)| FileData { file, hash })
-            .collect::<Vec<_>>();
-
-        UpdatedWorkspaceFiles {
-            file_map: FileMap {
-                project_file_map: project_files_map.clone(),
-                non_project_files: non_project_files.clone(),
-            },
-            external_references: NxWorkspaceFilesExternals {
-                project_files: External::new(project_files_map),
-                global_files: External::new(non_project_files),
-                all_workspace_files: External::new(self.all_file_data()),
-            },
+    /// Counts the files matching every filter of the query, ignoring `offset` and `limit`
+    #[napi]
+    pub fn count_files(&self, query: FilesQuery) -> napi::Result<u32> {
+        let filter = FileFilter::new(&query)?;
+        match &query.project {
+            Some(project) => self.with_project_files(project, |files| {
+                filter.matching_file_data(files).len() as u32
+            }),
+            None => Ok(self
+                .files_worker
+                .with_files(|files| filter.matching(files).len() as u32)),
         }
     }
 
+    /// Groups the files matching the query which have the same content hash.
+    /// `offset` and `limit` select groups rather than files.
     #[napi]
-    pub fn all_file_data(&self) -> Vec<FileData> {
-        self.files_worker.get_files()
+    pub fn find_duplicate_files(
+        &self,
+        query: Option<FilesQuery>,
+    ) -> napi::Result<Vec<DuplicateFiles>> {
+        let query = query.unwrap_or_default();
+        let filter = FileFilter::new(&query)?;
+        let duplicates = match &query.project {
+            Some(project) => self.with_project_files(project, |files| {
+                find_duplicates(
+                    filter
+                        .matching_file_data(files)
+                        .into_iter()
+                        .map(|f| (Path::new(&f.file), f.hash.as_str())),
+                )
+            })?,
+            None => self.files_worker.with_files(|files| {
+                find_duplicates(
+                    filter
+                        .matching(files)
+                        .into_iter()
+                        .map(|(path, hash)| (path.as_path(), hash.as_str())),
+                )
+            }),
+        };
+        Ok(paginate(duplicates, &query).collect())
     }
 
+    /// Lints globs like `lint_globs`, and also warns about globs which do not match any file in the workspace.
+    /// Globs with interpolations like `{projectRoot}` are only checked against the files once they are interpolated.
     #[napi]
-    pub fn get_files_in_directory(&self, directory: String) -> Vec<String> {
-        get_child_files(directory, self.files_worker.get_files())
+    pub fn lint_globs(&self, globs: Vec<String>) -> Vec<GlobDiagnostic> {
+        globs
+            .iter()
+            .flat_map(|glob| {
+                let mut diagnostics = lint_glob(glob);
+                let is_negated = glob.starts_with('!') && !glob.starts_with("!(");
+                let is_valid = diagnostics
+                    .iter()
+                    .all(|d| d.severity != GlobDiagnosticSeverity::error);
+                if is_valid && !is_negated && !contains_interpolation(glob) {
+                    let glob_set = build_glob_set(&[glob]).expect("Linted globs should build");
+                    let matches_files = self.files_worker.with_files(|files| {
+                        files.par_iter().any(|(path, _)| glob_set.is_match(path))
+                    });
+                    if !matches_files {
+                        diagnostics.push(GlobDiagnostic::warning(
+                            glob,
+                            0,
+                            glob.len(),
+                            "The glob does not match any files in the workspace".into(),
+                        ));
+                    }
+                }
+                diagnostics
+            })
+            .collect()
+    }
+
+    fn with_project_files<T>(
+        &self,
+        project: &str,
+        f: impl FnOnce(&[FileData]) -> T,
+    ) -> napi::Result<T> {
+        let tracked = self
+            .project_file_map
+            .lock()
+            .expect("Should be able to lock the project file map");
+        let project_file_map = tracked.project_file_map.as_ref().ok_or_else(|| {
+            napi::Error::from_reason(
+                "Files can only be queried by project after the workspace files were retrieved",
+            )
+        })?;
+        Ok(f(project_file_map.project_files(project)))
     }
 }
 
@@ -424,3 +640,57 @@ impl Drop for WorkspaceContext {
         drop(fw);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    fn files(context: &WorkspaceContext) -> Vec<String> {
+        context
+            .all_file_data()
+            .into_iter()
+            .map(|f| f.file)
+            .collect()
+    }
+
+    #[test]
+    fn should_update_the_sorted_files_in_place() {
+        let temp = TempDir::new().unwrap();
+        for file in [
+            "a/deleted/b.ts",
+            "a/deleted/c/d.ts",
+            "a/kept.ts",
+            "a-b.ts",
+            "z.ts",
+        ] {
+            temp.child(file).write_str(file).unwrap();
+        }
+        let context = WorkspaceContext::new(
+            temp.path().to_string_lossy().to_string(),
+            temp.child(".nx/cache").path().to_string_lossy().to_string(),
+            None,
+        );
+        let hash = |file: &str| {
+            context
+                .all_file_data()
+                .into_iter()
+                .find(|f| f.file == file)
+                .map(|f| f.hash)
+        };
+        let unchanged_hash = hash("a/kept.ts");
+
+        temp.child("a/added.ts").write_str("added").unwrap();
+        temp.child("z.ts").write_str("changed").unwrap();
+        let updated = context.incremental_update(
+            vec!["a/added.ts", "z.ts"],
+            vec!["a/deleted", "a-b.ts", "does-not-exist.ts"],
+        );
+
+        assert_eq!(updated.len(), 2);
+        assert_eq!(files(&context), vec!["a/added.ts", "a/kept.ts", "z.ts"]);
+        assert_eq!(hash("a/kept.ts"), unchanged_hash);
+        assert_eq!(hash("z.ts").as_ref(), updated.get("z.ts"));
+    }
+}
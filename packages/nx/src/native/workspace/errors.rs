@@ -7,6 +7,7 @@ use thiserror::Error;
 pub enum WorkspaceErrors {
     ParseError,
     Generic,
+    FilesArchiveMismatch,
 }
 
 impl AsRef<str> for WorkspaceErrors {
@@ -14,6 +15,7 @@ impl AsRef<str> for WorkspaceErrors {
         match self {
             WorkspaceErrors::ParseError => "ParseError",
             WorkspaceErrors::Generic => "Generic",
+            WorkspaceErrors::FilesArchiveMismatch => "FilesArchiveMismatch",
         }
     }
 }
@@ -25,6 +27,9 @@ pub enum InternalWorkspaceErrors {
     ParseError(String),
     #[error("{0}")]
     Generic(String),
+    /// The files archive in the cache directory could not be reused, so every file was hashed again
+    #[error("Could not reuse the file hashes in {path} because {reason}")]
+    FilesArchiveMismatch { path: String, reason: String },
 }
 
 impl From<InternalWorkspaceErrors> for napi::Error<WorkspaceErrors> {
@@ -34,6 +39,9 @@ impl From<InternalWorkspaceErrors> for napi::Error<WorkspaceErrors> {
                 Error::new(WorkspaceErrors::ParseError, msg)
             }
             InternalWorkspaceErrors::Generic(msg) => Error::new(WorkspaceErrors::Generic, msg),
+            e @ InternalWorkspaceErrors::FilesArchiveMismatch { .. } => {
+                Error::new(WorkspaceErrors::FilesArchiveMismatch, e.to_string())
+            }
         }
     }
 }
@@ -1,12 +1,17 @@
 use anyhow::anyhow;
 use hashbrown::HashMap;
-use rkyv::{Archive, Deserialize, Infallible, Serialize};
+use rkyv::{AlignedVec, Archive, Deserialize, Infallible, Serialize};
 use std::ops::{Deref, DerefMut};
 use std::path::Path;
 
 use tracing::trace;
 
+use crate::native::workspace::errors::InternalWorkspaceErrors;
+
 const NX_FILES_ARCHIVE: &str = "nx_files.nxt";
+const NX_FILES_ARCHIVE_MAGIC: &[u8; 4] = b"NXFH";
+/// Bump when the layout of the archive or the way files are hashed changes
+const NX_FILES_ARCHIVE_VERSION: u32 = 1;
 
 #[derive(Archive, Serialize, Deserialize, PartialEq, Debug)]
 #[archive(check_bytes)]
@@ -38,46 +43,77 @@ impl FromIterator<(String, NxFileHashed)> for NxFileHashes {
     }
 }
 
-pub fn read_files_archive<P: AsRef<Path>>(cache_dir: P) -> Option<NxFileHashes> {
+/// Reads the file hashes archived for `workspace_root`.
+/// Returns `Ok(None)` when there is no archive yet, and an error when the archive cannot be reused.
+pub fn read_files_archive<P: AsRef<Path>>(
+    cache_dir: P,
+    workspace_root: &Path,
+) -> Result<Option<NxFileHashes>, InternalWorkspaceErrors> {
     let now = std::time::Instant::now();
     let archive_path = cache_dir.as_ref().join(NX_FILES_ARCHIVE);
     if !archive_path.exists() {
-        return None;
+        return Ok(None);
     }
 
-    let bytes = std::fs::read(archive_path)
-        .map_err(anyhow::Error::from)
-        .and_then(|bytes| {
-            // let archived = unsafe { rkyv::archived_root::<NxFilesArchive>(&bytes) };
-            let archived = rkyv::check_archived_root::<NxFileHashes>(&bytes)
-                .map_err(|_| anyhow!("invalid archive file"))?;
-            <ArchivedNxFileHashes as Deserialize<NxFileHashes, Infallible>>::deserialize(
-                archived,
-                &mut rkyv::Infallible,
-            )
-            .map_err(anyhow::Error::from)
-        });
+    let mismatch = |reason: String| InternalWorkspaceErrors::FilesArchiveMismatch {
+        path: archive_path.display().to_string(),
+        reason,
+    };
 
-    match bytes {
-        Ok(archive) => {
-            trace!("read archive in {:?}", now.elapsed());
-            Some(archive)
-        }
-        Err(e) => {
-            trace!("could not read files archive: {:?}", e);
-            None
-        }
+    let bytes = std::fs::read(&archive_path)
+        .map_err(|e| InternalWorkspaceErrors::Generic(e.to_string()))?;
+    let (header, payload) = read_header(&bytes)
+        .ok_or_else(|| mismatch("it does not start with an Nx files archive header".to_string()))?;
+    if header.version != NX_FILES_ARCHIVE_VERSION {
+        return Err(mismatch(format!(
+            "it has format version {} instead of {}",
+            header.version, NX_FILES_ARCHIVE_VERSION
+        )));
+    }
+    let expected_root = workspace_root.to_string_lossy();
+    if header.workspace_root != expected_root {
+        return Err(mismatch(format!(
+            "it belongs to the workspace at {} instead of {}",
+            header.workspace_root, expected_root
+        )));
     }
+
+    // rkyv needs the archived bytes to be aligned, which they are not after the header
+    let mut aligned = AlignedVec::with_capacity(payload.len());
+    aligned.extend_from_slice(payload);
+    let archived = rkyv::check_archived_root::<NxFileHashes>(&aligned)
+        .map_err(|e| mismatch(format!("its contents are invalid: {}", e)))?;
+    let archive = <ArchivedNxFileHashes as Deserialize<NxFileHashes, Infallible>>::deserialize(
+        archived,
+        &mut rkyv::Infallible,
+    )
+    .map_err(|e| mismatch(format!("its contents are invalid: {:?}", e)))?;
+
+    trace!("read archive in {:?}", now.elapsed());
+    Ok(Some(archive))
 }
 
-pub fn write_files_archive<P: AsRef<Path>>(cache_dir: P, files: NxFileHashes) {
+/// Writes the archive next to the existing one and renames it into place,
+/// so that a crash while writing never leaves a partially written archive behind
+pub fn write_files_archive<P: AsRef<Path>>(
+    cache_dir: P,
+    workspace_root: &Path,
+    files: NxFileHashes,
+) {
     let now = std::time::Instant::now();
     let archive_path = cache_dir.as_ref().join(NX_FILES_ARCHIVE);
+    let temp_path =
+        cache_dir
+            .as_ref()
+            .join(format!("{}.{}.tmp", NX_FILES_ARCHIVE, std::process::id()));
     let result = rkyv::to_bytes::<_, 2048>(&files)
         .map_err(anyhow::Error::from)
         .and_then(|encoded| {
-            std::fs::write(archive_path, encoded)?;
-            Ok(())
+            let mut bytes = write_header(&workspace_root.to_string_lossy());
+            bytes.extend_from_slice(&encoded);
+            std::fs::write(&temp_path, bytes)?;
+            std::fs::rename(&temp_path, &archive_path)
+                .map_err(|e| anyhow!("could not rename {}: {}", temp_path.display(), e))
         });
 
     match result {
@@ -85,7 +121,117 @@ pub fn write_files_archive<P: AsRef<Path>>(cache_dir: P, files: NxFileHashes) {
             trace!("write archive in {:?}", now.elapsed());
         }
         Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
             trace!("could not write files archive: {:?}", e);
         }
     }
 }
+
+struct ArchiveHeader<'a> {
+    version: u32,
+    workspace_root: &'a str,
+}
+
+/// The header is the magic bytes, the format version,
+/// and the length of the workspace root followed by the workspace root itself
+fn write_header(workspace_root: &str) -> Vec<u8> {
+    let mut header = Vec::with_capacity(12 + workspace_root.len());
+    header.extend_from_slice(NX_FILES_ARCHIVE_MAGIC);
+    header.extend_from_slice(&NX_FILES_ARCHIVE_VERSION.to_le_bytes());
+    header.extend_from_slice(&(workspace_root.len() as u32).to_le_bytes());
+    header.extend_from_slice(workspace_root.as_bytes());
+    header
+}
+
+fn read_header(bytes: &[u8]) -> Option<(ArchiveHeader<'_>, &[u8])> {
+    let rest = bytes.strip_prefix(NX_FILES_ARCHIVE_MAGIC)?;
+    let (version, rest) = rest.split_first_chunk::<4>()?;
+    let (root_len, rest) = rest.split_first_chunk::<4>()?;
+    let root_len = u32::from_le_bytes(*root_len) as usize;
+    let workspace_root = std::str::from_utf8(rest.get(..root_len)?).ok()?;
+    Some((
+        ArchiveHeader {
+            version: u32::from_le_bytes(*version),
+            workspace_root,
+        },
+        &rest[root_len..],
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+
+    fn files() -> NxFileHashes {
+        [(
+            "libs/lib/index.ts".to_string(),
+            NxFileHashed("123".into(), 1),
+        )]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn should_read_archives_written_for_the_same_workspace() {
+        let cache_dir = TempDir::new().unwrap();
+        let workspace_root = Path::new("/workspace");
+        assert!(
+            read_files_archive(&cache_dir, workspace_root)
+                .unwrap()
+                .is_none()
+        );
+
+        write_files_archive(&cache_dir, workspace_root, files());
+
+        assert_eq!(
+            read_files_archive(&cache_dir, workspace_root).unwrap(),
+            Some(files())
+        );
+        assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn should_report_mismatched_archives() {
+        let cache_dir = TempDir::new().unwrap();
+        let archive_path = cache_dir.join(NX_FILES_ARCHIVE);
+        write_files_archive(&cache_dir, Path::new("/workspace"), files());
+
+        let reason = |workspace_root: &str| match read_files_archive(
+            &cache_dir,
+            Path::new(workspace_root),
+        ) {
+            Err(InternalWorkspaceErrors::FilesArchiveMismatch { reason, .. }) => reason,
+            other => panic!("expected a mismatch, got {:?}", other),
+        };
+
+        assert_eq!(
+            reason("/other"),
+            "it belongs to the workspace at /workspace instead of /other"
+        );
+
+        let mut bytes = std::fs::read(&archive_path).unwrap();
+        bytes[4..8].copy_from_slice(&0u32.to_le_bytes());
+        std::fs::write(&archive_path, &bytes).unwrap();
+        assert_eq!(
+            reason("/workspace"),
+            format!(
+                "it has format version 0 instead of {}",
+                NX_FILES_ARCHIVE_VERSION
+            )
+        );
+
+        // archives written before the header was introduced
+        std::fs::write(&archive_path, rkyv::to_bytes::<_, 2048>(&files()).unwrap()).unwrap();
+        assert_eq!(
+            reason("/workspace"),
+            "it does not start with an Nx files archive header"
+        );
+
+        // a truncated archive
+        write_files_archive(&cache_dir, Path::new("/workspace"), files());
+        let bytes = std::fs::read(&archive_path).unwrap();
+        std::fs::write(&archive_path, &bytes[..bytes.len() - 8]).unwrap();
+        assert!(reason("/workspace").starts_with("its contents are invalid"));
+    }
+}
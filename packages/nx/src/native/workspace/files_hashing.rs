@@ -5,14 +5,85 @@ use std::thread::available_parallelism;
 use rayon::prelude::*;
 use tracing::trace;
 
-use crate::native::hasher::hash_file_path;
+use crate::native::hasher::{hash, hash_file_path};
 use crate::native::walker::{NxFile, nx_walker};
 use crate::native::workspace::files_archive::{NxFileHashed, NxFileHashes};
+use crate::native::workspace::git_index::{GitIndex, hash_git_blob};
+
+/// How the contents of workspace files are hashed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FileHasher {
+    #[default]
+    Xxh3,
+    /// Hashes files like git hashes blobs, so that unchanged files can reuse the blob ids from the git index
+    GitBlob,
+}
+
+impl FileHasher {
+    pub fn hash(&self, content: &[u8]) -> String {
+        match self {
+            FileHasher::Xxh3 => hash(content),
+            FileHasher::GitBlob => hash_git_blob(content),
+        }
+    }
+
+    fn hash_file(&self, path: &str) -> Option<String> {
+        match self {
+            FileHasher::Xxh3 => hash_file_path(path),
+            FileHasher::GitBlob => std::fs::read(path)
+                .map(|content| hash_git_blob(&content))
+                .ok(),
+        }
+    }
+}
 
 pub fn full_files_hash(workspace_root: &Path) -> NxFileHashes {
     let files = nx_walker(workspace_root, true).collect::<Vec<_>>();
     trace!("Found {} files", files.len());
-    hash_files(files).into_iter().collect()
+    hash_files(files, FileHasher::Xxh3).into_iter().collect()
+}
+
+/// Reuses the blob ids of files which are unchanged in the git index,
+/// so that only untracked and modified files are hashed
+pub fn git_files_hash(workspace_root: &Path) -> NxFileHashes {
+    let git_index = match GitIndex::read(workspace_root) {
+        Ok(git_index) => git_index,
+        Err(e) => {
+            trace!("could not read the git index, hashing every file: {:?}", e);
+            None
+        }
+    };
+    let files = nx_walker(workspace_root, true).collect::<Vec<_>>();
+    trace!("Found {} files", files.len());
+
+    let mut seeded = vec![];
+    let mut not_seeded = vec![];
+    let now = std::time::Instant::now();
+    for file in files {
+        let blob_id = git_index.as_ref().and_then(|git_index| {
+            let metadata = std::fs::symlink_metadata(&file.full_path).ok()?;
+            git_index
+                .clean_blob_id(&file.normalized_path, &metadata)
+                .map(String::from)
+        });
+        match blob_id {
+            Some(blob_id) => {
+                seeded.push((file.normalized_path, NxFileHashed(blob_id, file.mod_time)))
+            }
+            None => not_seeded.push(file),
+        }
+    }
+    trace!(
+        "seeded {} files from the git index in {:?}, {} files are untracked or modified",
+        seeded.len(),
+        now.elapsed(),
+        not_seeded.len()
+    );
+
+    seeded
+        .into_iter()
+        .chain(hash_files(not_seeded, FileHasher::GitBlob))
+        .collect()
 }
 
 pub fn selective_files_hash(
@@ -42,11 +113,11 @@ pub fn selective_files_hash(
 
     archived
         .into_iter()
-        .chain(hash_files(not_archived))
+        .chain(hash_files(not_archived, FileHasher::Xxh3))
         .collect()
 }
 
-fn hash_files(files: Vec<NxFile>) -> Vec<(String, NxFileHashed)> {
+fn hash_files(files: Vec<NxFile>, file_hasher: FileHasher) -> Vec<(String, NxFileHashed)> {
     let num_parallelism = cmp::max(available_parallelism().map_or(2, |n| n.get()) / 3, 2);
     let chunks = files.len() / num_parallelism;
 
@@ -56,7 +127,8 @@ fn hash_files(files: Vec<NxFile>) -> Vec<(String, NxFileHashed)> {
         files
             .into_par_iter()
             .filter_map(|file| {
-                hash_file_path(&file.full_path)
+                file_hasher
+                    .hash_file(&file.full_path)
                     .map(|hash| (file.normalized_path, NxFileHashed(hash, file.mod_time)))
             })
             .collect::<Vec<_>>()
@@ -69,7 +141,7 @@ fn hash_files(files: Vec<NxFile>) -> Vec<(String, NxFileHashed)> {
             .par_chunks(chunks)
             .flat_map_iter(|chunks| {
                 chunks.iter().filter_map(|file| {
-                    hash_file_path(&file.full_path).map(|hash| {
+                    file_hasher.hash_file(&file.full_path).map(|hash| {
                         (
                             file.normalized_path.clone(),
                             NxFileHashed(hash, file.mod_time),
@@ -89,9 +161,23 @@ mod tests {
     use assert_fs::TempDir;
     use assert_fs::prelude::*;
 
+    use std::path::Path;
+    use std::process::Command;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
     use crate::native::utils::get_mod_time;
     use crate::native::workspace::files_archive::{NxFileHashed, NxFileHashes};
 
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("git should be installed");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
     fn setup_fs() -> TempDir {
         let temp = TempDir::new().unwrap();
         temp.child("test.txt").write_str("content").unwrap();
@@ -165,4 +251,53 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn should_hash_files_like_git() {
+        let temp = TempDir::new().unwrap();
+        temp.child("clean.txt").write_str("clean\n").unwrap();
+        temp.child("modified.txt").write_str("before\n").unwrap();
+        git(temp.path(), &["init", "-q"]);
+        git(temp.path(), &["add", "."]);
+        // let the index be written after the files were, so they are not racily clean
+        std::thread::sleep(Duration::from_millis(10));
+        git(temp.path(), &["update-index", "--really-refresh"]);
+        temp.child("modified.txt").write_str("after\n").unwrap();
+        temp.child("untracked.txt")
+            .write_str("untracked\n")
+            .unwrap();
+
+        // A racily clean file has the size and mtime which were staged, but a different content,
+        // because it was modified after the index was written
+        let racy = temp.child("racy.txt");
+        // Whole seconds, git may not record the nanoseconds of mtimes
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let mtime = UNIX_EPOCH + Duration::from_secs(now.as_secs() + 60);
+        racy.write_str("aaaa\n").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(racy.path())
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+        git(temp.path(), &["add", "racy.txt"]);
+        racy.write_str("bbbb\n").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(racy.path())
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let hashed_files = super::git_files_hash(temp.path());
+
+        for file in ["clean.txt", "modified.txt", "untracked.txt", "racy.txt"] {
+            assert_eq!(
+                hashed_files[file].0,
+                git(temp.path(), &["hash-object", file]),
+                "{}",
+                file
+            );
+        }
+    }
 }
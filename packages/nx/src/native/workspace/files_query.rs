@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::native::glob::{NxGlobSet, build_glob_set};
+use crate::native::types::FileData;
+use crate::native::utils::Normalize;
+
+/// Filters files of a `WorkspaceContext`. Files have to match every filter which is set.
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct FilesQuery {
+    /// Globs which files have to match, no files match an empty list
+    pub include: Option<Vec<String>>,
+    /// Globs which files must not match
+    pub exclude: Option<Vec<String>>,
+    /// The project which owns the files, according to the last `track_workspace_files` call
+    pub project: Option<String>,
+    /// The directory which contains the files, relative to the workspace root
+    pub directory: Option<String>,
+    /// The content hash of the files
+    pub hash: Option<String>,
+    /// The number of results to skip
+    pub offset: Option<u32>,
+    /// The maximum number of results to return
+    pub limit: Option<u32>,
+}
+
+#[napi(object)]
+pub struct FilesQueryResult {
+    pub files: Vec<FileData>,
+    /// The number of files which matched the query, regardless of `offset` and `limit`
+    pub total: u32,
+}
+
+/// Files which have the same contents
+#[napi(object)]
+#[derive(Debug, PartialEq)]
+pub struct DuplicateFiles {
+    pub hash: String,
+    pub files: Vec<String>,
+}
+
+pub(super) struct FileFilter<'a> {
+    include: Option<NxGlobSet>,
+    /// An empty include matches no files, whereas an empty glob set would match every file
+    include_nothing: bool,
+    exclude: Option<NxGlobSet>,
+    directory: Option<&'a Path>,
+    hash: Option<&'a str>,
+}
+
+impl<'a> FileFilter<'a> {
+    pub fn new(query: &'a FilesQuery) -> anyhow::Result<Self> {
+        let glob_set = |globs: &Option<Vec<String>>| {
+            globs
+                .as_ref()
+                .filter(|globs| !globs.is_empty())
+                .map(|globs| build_glob_set(globs))
+                .transpose()
+        };
+        Ok(Self {
+            include: glob_set(&query.include)?,
+            include_nothing: query.include.as_ref().is_some_and(|globs| globs.is_empty()),
+            exclude: glob_set(&query.exclude)?,
+            directory: query.directory.as_deref().map(Path::new),
+            hash: query.hash.as_deref(),
+        })
+    }
+
+    pub fn is_match(&self, path: &Path, hash: &str) -> bool {
+        !self.include_nothing
+            && self.hash.is_none_or(|h| h == hash)
+            && self.directory.is_none_or(|dir| path.starts_with(dir))
+            && self
+                .include
+                .as_ref()
+                .is_none_or(|globs| globs.is_match(path))
+            && self
+                .exclude
+                .as_ref()
+                .is_none_or(|globs| !globs.is_match(path))
+    }
+
+    /// The matching files, in the order of `files`
+    pub fn matching<'f>(&self, files: &'f [(PathBuf, String)]) -> Vec<&'f (PathBuf, String)> {
+        files
+            .par_iter()
+            .filter(|(path, hash)| self.is_match(path, hash))
+            .collect()
+    }
+
+    /// Same as [`FileFilter::matching`], for files which are already `FileData`
+    pub fn matching_file_data<'f>(&self, files: &'f [FileData]) -> Vec<&'f FileData> {
+        files
+            .par_iter()
+            .filter(|f| self.is_match(Path::new(&f.file), &f.hash))
+            .collect()
+    }
+}
+
+/// Skips `offset` results and takes up to `limit` of the rest
+pub(super) fn paginate<T>(results: Vec<T>, query: &FilesQuery) -> impl Iterator<Item = T> {
+    results
+        .into_iter()
+        .skip(query.offset.unwrap_or(0) as usize)
+        .take(query.limit.map_or(usize::MAX, |limit| limit as usize))
+}
+
+/// Groups the files by hash, keeping the groups with more than one file, ordered by their first file
+pub(super) fn find_duplicates<'f>(
+    files: impl Iterator<Item = (&'f Path, &'f str)>,
+) -> Vec<DuplicateFiles> {
+    let mut by_hash: HashMap<&str, Vec<String>> = HashMap::new();
+    for (path, hash) in files {
+        by_hash
+            .entry(hash)
+            .or_default()
+            .push(path.to_normalized_string());
+    }
+    let mut duplicates = by_hash
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|(hash, mut files)| {
+            files.sort();
+            DuplicateFiles {
+                hash: hash.to_string(),
+                files,
+            }
+        })
+        .collect::<Vec<_>>();
+    duplicates.sort_by(|a, b| a.files[0].cmp(&b.files[0]));
+    duplicates
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn files() -> Vec<(PathBuf, String)> {
+        let mut files = [
+            ("apps/app/src/main.ts", "1"),
+            ("apps/app/src/main.spec.ts", "2"),
+            ("libs/lib/src/index.ts", "1"),
+            ("libs/lib/src/util.ts", "3"),
+            ("libs/lib/README.md", "4"),
+            ("README.md", "4"),
+        ]
+        .into_iter()
+        .map(|(path, hash)| (PathBuf::from(path), hash.to_string()))
+        .collect::<Vec<_>>();
+        // the files of a `WorkspaceContext` are sorted
+        files.sort();
+        files
+    }
+
+    fn matching_paths(query: FilesQuery) -> Vec<String> {
+        let files = files();
+        let filter = FileFilter::new(&query).unwrap();
+        paginate(filter.matching(&files), &query)
+            .map(|(path, _)| path.to_normalized_string())
+            .collect()
+    }
+
+    #[test]
+    fn should_combine_filters() {
+        assert_eq!(
+            matching_paths(FilesQuery {
+                include: Some(vec!["**/*.ts".into()]),
+                exclude: Some(vec!["**/*.spec.ts".into()]),
+                directory: Some("apps".into()),
+                ..Default::default()
+            }),
+            vec!["apps/app/src/main.ts"]
+        );
+        assert_eq!(
+            matching_paths(FilesQuery {
+                hash: Some("1".into()),
+                ..Default::default()
+            }),
+            vec!["apps/app/src/main.ts", "libs/lib/src/index.ts"]
+        );
+        // directories only match whole path segments
+        assert!(
+            matching_paths(FilesQuery {
+                directory: Some("lib".into()),
+                ..Default::default()
+            })
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn should_not_match_anything_with_an_empty_include() {
+        assert!(
+            matching_paths(FilesQuery {
+                include: Some(vec![]),
+                ..Default::default()
+            })
+            .is_empty()
+        );
+        assert_eq!(
+            matching_paths(FilesQuery {
+                exclude: Some(vec![]),
+                directory: Some("apps".into()),
+                ..Default::default()
+            }),
+            vec!["apps/app/src/main.spec.ts", "apps/app/src/main.ts"]
+        );
+    }
+
+    #[test]
+    fn should_paginate() {
+        let query = FilesQuery {
+            directory: Some("libs".into()),
+            offset: Some(1),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(matching_paths(query), vec!["libs/lib/src/index.ts"]);
+    }
+
+    #[test]
+    fn should_find_duplicates() {
+        let files = files();
+        assert_eq!(
+            find_duplicates(
+                files
+                    .iter()
+                    .map(|(path, hash)| (path.as_path(), hash.as_str()))
+            ),
+            vec![
+                DuplicateFiles {
+                    hash: "4".into(),
+                    files: vec!["README.md".into(), "libs/lib/README.md".into()],
+                },
+                DuplicateFiles {
+                    hash: "1".into(),
+                    files: vec![
+                        "apps/app/src/main.ts".into(),
+                        "libs/lib/src/index.ts".into()
+                    ],
+                },
+            ]
+        );
+    }
+}
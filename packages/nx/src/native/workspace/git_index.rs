@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail};
+use tracing::trace;
+
+use crate::native::utils::Normalize;
+
+const ENTRY_EXTENDED_FLAG: u16 = 0x4000;
+const ENTRY_STAGE_MASK: u16 = 0x3000;
+const EXTENDED_SKIP_WORKTREE: u16 = 0x4000;
+const EXTENDED_INTENT_TO_ADD: u16 = 0x2000;
+const REGULAR_FILE_TYPE: u32 = 0b1000;
+
+/// Hashes content the same way git hashes blobs, so hashed files can be compared to blob ids in the index
+pub fn hash_git_blob(content: &[u8]) -> String {
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(format!("blob {}\0", content.len()).as_bytes());
+    hasher.update(content);
+    hasher.digest().to_string()
+}
+
+/// Finds the git directory of the repository containing `workspace_root`,
+/// along with the path of `workspace_root` relative to the root of the repository
+pub fn find_git_dir(workspace_root: &Path) -> Option<(PathBuf, String)> {
+    for dir in workspace_root.ancestors() {
+        let dot_git = dir.join(".git");
+        let git_dir = if dot_git.is_dir() {
+            dot_git
+        } else if dot_git.is_file() {
+            // worktrees and submodules point to their git directory
+            let git_dir = std::fs::read_to_string(&dot_git)
+                .ok()
+                .and_then(|contents| Some(dir.join(contents.strip_prefix("gitdir:")?.trim())));
+            match git_dir {
+                Some(git_dir) => git_dir,
+                None => {
+                    trace!(
+                        "could not read the git directory from {}",
+                        dot_git.display()
+                    );
+                    continue;
+                }
+            }
+        } else {
+            continue;
+        };
+        let relative_path = workspace_root.strip_prefix(dir).ok()?;
+        // an empty path is normalized to "."
+        let prefix = if relative_path.as_os_str().is_empty() {
+            String::new()
+        } else {
+            relative_path.to_normalized_string()
+        };
+        return Some((git_dir, prefix));
+    }
+    None
+}
+
+#[derive(Debug, PartialEq)]
+struct IndexEntry {
+    blob_id: String,
+    mtime: (u32, u32),
+    size: u32,
+    seedable: bool,
+}
+
+/// The entries of `.git/index` which are within the workspace, keyed by their path relative to the workspace root
+#[derive(Debug)]
+pub struct GitIndex {
+    entries: HashMap<String, IndexEntry>,
+    /// Files modified at the same time as the index or later may have changed without changing their stats
+    index_mtime: (u32, u32),
+}
+
+impl GitIndex {
+    pub fn read(workspace_root: &Path) -> anyhow::Result<Option<Self>> {
+        let Some((git_dir, prefix)) = find_git_dir(workspace_root) else {
+            return Ok(None);
+        };
+        let index_path = git_dir.join("index");
+        if !index_path.exists() {
+            return Ok(None);
+        }
+        let now = std::time::Instant::now();
+        let bytes = std::fs::read(&index_path)?;
+        let index_mtime = to_index_time(std::fs::metadata(&index_path)?.modified()?);
+        let entries = parse_index(&bytes, &prefix)?;
+        trace!(
+            "read {} entries from {} in {:?}",
+            entries.len(),
+            index_path.display(),
+            now.elapsed()
+        );
+        Ok(Some(GitIndex {
+            entries,
+            index_mtime,
+        }))
+    }
+
+    /// The blob id of a file, when it is unchanged since it was staged
+    pub fn clean_blob_id(&self, path: &str, metadata: &Metadata) -> Option<&str> {
+        let entry = self.entries.get(path)?;
+        if !entry.seedable || !metadata.is_file() || metadata.len() as u32 != entry.size {
+            return None;
+        }
+        let mtime = to_index_time(metadata.modified().ok()?);
+        if mtime != entry.mtime || mtime >= self.index_mtime {
+            return None;
+        }
+        Some(&entry.blob_id)
+    }
+}
+
+fn to_index_time(time: SystemTime) -> (u32, u32) {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since_epoch.as_secs() as u32, since_epoch.subsec_nanos())
+}
+
+/// Parses the entries of index versions 2 to 4, see https://git-scm.com/docs/index-format
+fn parse_index(bytes: &[u8], prefix: &str) -> anyhow::Result<HashMap<String, IndexEntry>> {
+    let mut reader = IndexReader { bytes, position: 0 };
+    if reader.take(4)? != b"DIRC" {
+        bail!("not a git index");
+    }
+    let version = reader.u32()?;
+    if !(2..=4).contains(&version) {
+        bail!("unsupported git index version {}", version);
+    }
+    let count = reader.u32()?;
+
+    let prefix = if prefix.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", prefix)
+    };
+    let mut entries = HashMap::new();
+    let mut path: Vec<u8> = vec![];
+    for _ in 0..count {
+        let start = reader.position;
+        reader.take(8)?; // ctime
+        let mtime = (reader.u32()?, reader.u32()?);
+        reader.take(8)?; // dev and ino
+        let mode = reader.u32()?;
+        reader.take(8)?; // uid and gid
+        let size = reader.u32()?;
+        let blob_id = reader
+            .take(20)?
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        let flags = reader.u16()?;
+        let extended_flags = if flags & ENTRY_EXTENDED_FLAG != 0 {
+            reader.u16()?
+        } else {
+            0
+        };
+
+        if version == 4 {
+            let strip = reader.varint()?;
+            path.truncate(path.len().saturating_sub(strip));
+            path.extend_from_slice(reader.until_nul()?);
+        } else {
+            path = reader.until_nul()?.to_vec();
+            // entries are padded with 1 to 8 nul bytes to a multiple of 8 bytes
+            let length = reader.position - start;
+            reader.take((8 - length % 8) % 8)?;
+        }
+
+        let Ok(full_path) = std::str::from_utf8(&path) else {
+            continue;
+        };
+        let Some(workspace_path) = full_path.strip_prefix(&prefix) else {
+            continue;
+        };
+        entries.insert(
+            workspace_path.to_string(),
+            IndexEntry {
+                blob_id,
+                mtime,
+                size,
+                seedable: flags & ENTRY_STAGE_MASK == 0
+                    && extended_flags & (EXTENDED_SKIP_WORKTREE | EXTENDED_INTENT_TO_ADD) == 0
+                    && mode >> 12 == REGULAR_FILE_TYPE,
+            },
+        );
+    }
+
+    // a split index keeps most entries in a separate shared index
+    while reader.bytes.len() - reader.position > 20 {
+        let signature = reader.take(4)?;
+        if signature == b"link" {
+            bail!("split git indexes are not supported");
+        }
+        let size = reader.u32()? as usize;
+        reader.take(size)?;
+    }
+
+    Ok(entries)
+}
+
+struct IndexReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> IndexReader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self.position + len;
+        let slice = self
+            .bytes
+            .get(self.position..end)
+            .ok_or_else(|| anyhow!("unexpected end of git index"))?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn until_nul(&mut self) -> anyhow::Result<&'a [u8]> {
+        let rest = &self.bytes[self.position..];
+        let len = rest
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| anyhow!("unterminated path in git index"))?;
+        self.position += len + 1;
+        Ok(&rest[..len])
+    }
+
+    /// The offset encoding used for prefix compression in version 4
+    fn varint(&mut self) -> anyhow::Result<usize> {
+        let mut byte = self.take(1)?[0];
+        let mut value = (byte & 0x7f) as usize;
+        while byte & 0x80 != 0 {
+            byte = self.take(1)?[0];
+            value = ((value + 1) << 7) | (byte & 0x7f) as usize;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env("GIT_AUTHOR_NAME", "nx")
+            .env("GIT_AUTHOR_EMAIL", "nx@example.com")
+            .env("GIT_COMMITTER_NAME", "nx")
+            .env("GIT_COMMITTER_EMAIL", "nx@example.com")
+            .status()
+            .expect("git should be installed");
+        assert!(status.success());
+    }
+
+    #[test]
+    fn should_hash_blobs_like_git() {
+        assert_eq!(
+            hash_git_blob(b"hello\n"),
+            "ce013625030ba8dba906f756967f9e9ca394464a"
+        );
+    }
+
+    #[test]
+    fn should_find_the_git_dir() {
+        let temp = TempDir::new().unwrap();
+        git(temp.path(), &["init", "-q"]);
+        temp.child("workspace/.git")
+            .write_str("not a git file")
+            .unwrap();
+
+        let workspace_root = temp.path().join("workspace");
+
+        assert_eq!(
+            find_git_dir(&workspace_root),
+            Some((temp.path().join(".git"), "workspace".to_string()))
+        );
+        assert_eq!(
+            find_git_dir(temp.path()),
+            Some((temp.path().join(".git"), String::new()))
+        );
+    }
+
+    #[test]
+    fn should_read_blob_ids_from_the_index() {
+        for version in ["2", "3", "4"] {
+            let temp = TempDir::new().unwrap();
+            temp.child("workspace/libs/lib/index.ts")
+                .write_str("hello\n")
+                .unwrap();
+            temp.child("workspace/libs/lib/long/nested/path/to/a/file.ts")
+                .write_str("export {}")
+                .unwrap();
+            temp.child("outside.txt").write_str("outside").unwrap();
+            git(temp.path(), &["init", "-q"]);
+            git(temp.path(), &["update-index", "--index-version", version]);
+            git(temp.path(), &["add", "."]);
+            // let the index be written after the files were, so they are not racily clean
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            git(temp.path(), &["update-index", "--really-refresh"]);
+
+            let workspace_root = temp.path().join("workspace");
+            let index = GitIndex::read(&workspace_root).unwrap().unwrap();
+
+            let metadata = |path: &str| std::fs::metadata(workspace_root.join(path)).unwrap();
+            assert_eq!(
+                index.clean_blob_id("libs/lib/index.ts", &metadata("libs/lib/index.ts")),
+                Some("ce013625030ba8dba906f756967f9e9ca394464a"),
+                "index version {}",
+                version
+            );
+            let nested = "libs/lib/long/nested/path/to/a/file.ts";
+            assert_eq!(
+                index.clean_blob_id(nested, &metadata(nested)),
+                Some(hash_git_blob(b"export {}").as_str())
+            );
+            assert!(!index.entries.contains_key("outside.txt"));
+
+            temp.child("workspace/libs/lib/index.ts")
+                .write_str("changed\n")
+                .unwrap();
+            assert_eq!(
+                index.clean_blob_id("libs/lib/index.ts", &metadata("libs/lib/index.ts")),
+                None
+            );
+        }
+    }
+}
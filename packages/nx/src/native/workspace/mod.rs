@@ -8,6 +8,9 @@ pub mod context;
 mod errors;
 mod files_archive;
 mod files_hashing;
+pub mod files_query;
+mod git_index;
+mod project_file_map;
 pub mod types;
 pub mod workspace_files;
 
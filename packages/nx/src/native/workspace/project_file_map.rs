@@ -0,0 +1,285 @@
+use std::collections::{BTreeMap, HashMap};
+
+use napi::bindgen_prelude::External;
+use tracing::trace;
+
+use crate::native::project_graph::utils::{ProjectRootMappings, find_project_for_path};
+use crate::native::types::FileData;
+use crate::native::workspace::types::{
+    FileLocation, FilesDelta, NxWorkspaceFilesExternals, ProjectFiles,
+};
+
+/// The project file map of a workspace, kept up to date in place as files change
+pub(super) struct ProjectFileMap {
+    project_root_mappings: ProjectRootMappings,
+    /// Files of each project, sorted by path
+    project_files: ProjectFiles,
+    /// Files which are not part of any project, sorted by path
+    global_files: Vec<FileData>,
+    /// Where each file is stored, ordered so that the files of a deleted directory can be found by prefix
+    locations: BTreeMap<String, FileLocation>,
+}
+
+/// How the files of each location changed in a single update
+#[derive(Debug, Default)]
+pub(super) struct ProjectFileMapDelta(HashMap<FileLocation, FilesDelta>);
+
+impl ProjectFileMapDelta {
+    fn location(&mut self, location: &FileLocation) -> &mut FilesDelta {
+        self.0.entry(location.clone()).or_default()
+    }
+
+    /// Splits the delta into the projects which were affected, sorted by name, their deltas, and the delta of global files
+    pub fn into_parts(self) -> (Vec<String>, HashMap<String, FilesDelta>, FilesDelta) {
+        let mut project_deltas = HashMap::new();
+        let mut global_delta = FilesDelta::default();
+        for (location, mut delta) in self.0 {
+            delta.added.sort();
+            delta.changed.sort();
+            delta.removed.sort();
+            match location {
+                FileLocation::Global => global_delta = delta,
+                FileLocation::Project(project) => {
+                    project_deltas.insert(project, delta);
+                }
+            }
+        }
+        let mut affected_projects = project_deltas.keys().cloned().collect::<Vec<_>>();
+        affected_projects.sort();
+        (affected_projects, project_deltas, global_delta)
+    }
+}
+
+impl ProjectFileMap {
+    pub fn new(
+        project_root_mappings: ProjectRootMappings,
+        project_files: ProjectFiles,
+        global_files: Vec<FileData>,
+    ) -> Self {
+        let locations = project_files
+            .iter()
+            .flat_map(|(project, files)| {
+                files
+                    .iter()
+                    .map(|f| (f.file.clone(), FileLocation::Project(project.clone())))
+            })
+            .chain(
+                global_files
+                    .iter()
+                    .map(|f| (f.file.clone(), FileLocation::Global)),
+            )
+            .collect();
+        Self {
+            project_root_mappings,
+            project_files,
+            global_files,
+            locations,
+        }
+    }
+
+    /// The files of a project, sorted by path
+    pub fn project_files(&self, project: &str) -> &[FileData] {
+        self.project_files
+            .get(project)
+            .map(|files| files.as_slice())
+            .unwrap_or_default()
+    }
+
+    fn files_mut(&mut self, location: &FileLocation) -> &mut Vec<FileData> {
+        match location {
+            FileLocation::Global => &mut self.global_files,
+            FileLocation::Project(project) => {
+                self.project_files.entry(project.clone()).or_default()
+            }
+        }
+    }
+
+    /// Applies updated file hashes and deleted files or directories, as reported by `WorkspaceContext::incremental_update`
+    pub fn update(
+        &mut self,
+        updated_files: HashMap<String, String>,
+        deleted_files: Vec<&str>,
+    ) -> ProjectFileMapDelta {
+        let mut delta = ProjectFileMapDelta::default();
+
+        trace!(
+            "updating {} files in the project file map",
+            updated_files.len()
+        );
+        for (file, hash) in updated_files {
+            let location = match self.locations.get(&file) {
+                Some(location) => location.clone(),
+                None => find_project_for_path(&file, &self.project_root_mappings)
+                    .map(|project| FileLocation::Project(project.to_string()))
+                    .unwrap_or(FileLocation::Global),
+            };
+            let files = self.files_mut(&location);
+            match files.binary_search_by(|f| f.file.as_str().cmp(&file)) {
+                Ok(pos) if files[pos].hash == hash => continue,
+                Ok(pos) => {
+                    files[pos].hash.clone_from(&hash);
+                    delta
+                        .location(&location)
+                        .changed
+                        .push(FileData { file, hash });
+                }
+                Err(pos) => {
+                    let file_data = FileData { file, hash };
+                    files.insert(pos, file_data.clone());
+                    self.locations
+                        .insert(file_data.file.clone(), location.clone());
+                    delta.location(&location).added.push(file_data);
+                }
+            }
+        }
+
+        trace!(
+            "removing {} deleted paths from the project file map",
+            deleted_files.len()
+        );
+        for deleted_path in deleted_files {
+            let deleted_files = if self.locations.contains_key(deleted_path) {
+                vec![deleted_path.to_string()]
+            } else {
+                // the path is a directory, so every file within it was deleted
+                let prefix = format!("{}/", deleted_path);
+                self.locations
+                    .range(prefix.clone()..)
+                    .take_while(|(file, _)| file.starts_with(&prefix))
+                    .map(|(file, _)| file.clone())
+                    .collect()
+            };
+            for deleted_file in deleted_files {
+                let Some(location) = self.locations.remove(&deleted_file) else {
+                    continue;
+                };
+                let files = self.files_mut(&location);
+                if let Ok(pos) = files.binary_search_by(|f| f.file.cmp(&deleted_file)) {
+                    files.remove(pos);
+                    delta.location(&location).removed.push(deleted_file);
+                }
+            }
+        }
+
+        delta
+    }
+
+    pub fn external_references(
+        &self,
+        all_workspace_files: Vec<FileData>,
+    ) -> NxWorkspaceFilesExternals {
+        NxWorkspaceFilesExternals {
+            project_files: External::new(self.project_files.clone()),
+            global_files: External::new(self.global_files.clone()),
+            all_workspace_files: External::new(all_workspace_files),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn file(file: &str, hash: &str) -> FileData {
+        FileData {
+            file: file.into(),
+            hash: hash.into(),
+        }
+    }
+
+    fn files(files: &[FileData]) -> Vec<(String, String)> {
+        files
+            .iter()
+            .map(|f| (f.file.clone(), f.hash.clone()))
+            .collect()
+    }
+
+    fn project_file_map() -> ProjectFileMap {
+        ProjectFileMap::new(
+            HashMap::from([("libs/a".into(), "a".into()), ("libs/b".into(), "b".into())]),
+            HashMap::from([
+                (
+                    "a".into(),
+                    vec![
+                        file("libs/a/index.ts", "1"),
+                        file("libs/a/src/a.ts", "2"),
+                        file("libs/a/src/b.ts", "3"),
+                    ],
+                ),
+                ("b".into(), vec![file("libs/b/index.ts", "4")]),
+            ]),
+            vec![file("nx.json", "5"), file("package.json", "6")],
+        )
+    }
+
+    #[test]
+    fn should_update_files_in_place() {
+        let mut map = project_file_map();
+
+        let delta = map.update(
+            HashMap::from([
+                ("libs/a/index.ts".into(), "10".into()),
+                ("libs/a/src/aa.ts".into(), "11".into()),
+                ("libs/b/index.ts".into(), "4".into()),
+                ("tsconfig.json".into(), "12".into()),
+            ]),
+            vec!["package.json"],
+        );
+
+        assert_eq!(
+            files(&map.project_files["a"]),
+            files(&[
+                file("libs/a/index.ts", "10"),
+                file("libs/a/src/a.ts", "2"),
+                file("libs/a/src/aa.ts", "11"),
+                file("libs/a/src/b.ts", "3"),
+            ])
+        );
+        assert_eq!(
+            files(&map.global_files),
+            files(&[file("nx.json", "5"), file("tsconfig.json", "12")])
+        );
+
+        let (affected_projects, project_deltas, global_delta) = delta.into_parts();
+        // the hash of libs/b/index.ts did not change
+        assert_eq!(affected_projects, vec!["a".to_string()]);
+        assert_eq!(
+            files(&project_deltas["a"].added),
+            files(&[file("libs/a/src/aa.ts", "11")])
+        );
+        assert_eq!(
+            files(&project_deltas["a"].changed),
+            files(&[file("libs/a/index.ts", "10")])
+        );
+        assert_eq!(
+            files(&global_delta.added),
+            files(&[file("tsconfig.json", "12")])
+        );
+        assert_eq!(global_delta.removed, vec!["package.json".to_string()]);
+    }
+
+    #[test]
+    fn should_remove_the_files_of_deleted_directories() {
+        let mut map = project_file_map();
+
+        let delta = map.update(HashMap::new(), vec!["libs/a/src", "libs/b"]);
+
+        assert_eq!(
+            files(&map.project_files["a"]),
+            files(&[file("libs/a/index.ts", "1")])
+        );
+        assert!(map.project_files["b"].is_empty());
+        assert!(!map.locations.contains_key("libs/a/src/a.ts"));
+
+        let (affected_projects, project_deltas, _) = delta.into_parts();
+        assert_eq!(affected_projects, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(
+            project_deltas["a"].removed,
+            vec!["libs/a/src/a.ts".to_string(), "libs/a/src/b.ts".to_string()]
+        );
+        assert_eq!(
+            project_deltas["b"].removed,
+            vec!["libs/b/index.ts".to_string()]
+        );
+    }
+}
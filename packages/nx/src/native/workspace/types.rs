@@ -2,7 +2,7 @@ use crate::native::types::FileData;
 use napi::bindgen_prelude::External;
 use std::collections::HashMap;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum FileLocation {
     Global,
     Project(String),
@@ -25,14 +25,21 @@ pub struct NxWorkspaceFilesExternals {
     pub all_workspace_files: External<Vec<FileData>>,
 }
 
+/// Files which were added, changed or removed in a project, or outside of projects
 #[napi(object)]
-pub struct UpdatedWorkspaceFiles {
-    pub file_map: FileMap,
-    pub external_references: NxWorkspaceFilesExternals,
+#[derive(Debug, Default)]
+pub struct FilesDelta {
+    pub added: Vec<FileData>,
+    pub changed: Vec<FileData>,
+    pub removed: Vec<String>,
 }
 
 #[napi(object)]
-pub struct FileMap {
-    pub project_file_map: ProjectFiles,
-    pub non_project_files: Vec<FileData>,
+pub struct ProjectFileMapUpdate {
+    /// Identifies the project file map after the update, to copy its files with `get_project_file_map_references`
+    pub generation: u32,
+    /// Projects which had files added, changed or removed, sorted by name
+    pub affected_projects: Vec<String>,
+    pub project_files: HashMap<String, FilesDelta>,
+    pub non_project_files: FilesDelta,
 }
@@ -1,6 +1,15 @@
 import { ProjectType } from '../config/workspace-json-project-json';
+import {
+  getProjectFileMapReferences,
+  updateProjectFiles,
+} from '../utils/workspace-context';
 import { createFileMap, updateFileMap } from './file-map-utils';
 
+jest.mock('../utils/workspace-context', () => ({
+  updateProjectFiles: jest.fn(),
+  getProjectFileMapReferences: jest.fn(),
+}));
+
 describe('fileMapUtils', () => {
   describe('createFileMap', () => {
     it('should map files to projects', () => {
@@ -56,4 +65,88 @@ describe('fileMapUtils', () => {
       });
     });
   });
+
+  describe('updateFileMap', () => {
+    const file = (file: string, hash = 'some-hash') => ({ file, hash });
+
+    it('should apply the delta to the file map and all workspace files in place', () => {
+      const workspaceFileMap = {
+        fileMap: {
+          projectFileMap: {
+            demo: [file('apps/demo/a.ts'), file('apps/demo/c.ts')],
+            ui: [file('libs/ui/index.ts')],
+          },
+          nonProjectFiles: [file('tools/myfile.txt')],
+        },
+        allWorkspaceFiles: [
+          file('apps/demo/a.ts'),
+          file('apps/demo/c.ts'),
+          file('libs/ui/index.ts'),
+          file('tools/myfile.txt'),
+        ],
+      };
+      const { projectFileMap } = workspaceFileMap.fileMap;
+      const uiFiles = projectFileMap.ui;
+      (updateProjectFiles as jest.Mock).mockReturnValue({
+        generation: 2,
+        affectedProjects: ['demo'],
+        projectFiles: {
+          demo: {
+            added: [file('apps/demo/b.ts', 'new-hash')],
+            changed: [file('apps/demo/c.ts', 'changed-hash')],
+            removed: ['apps/demo/a.ts'],
+          },
+        },
+        nonProjectFiles: {
+          added: [],
+          changed: [],
+          removed: ['tools/myfile.txt'],
+        },
+      });
+
+      const result = updateFileMap(
+        workspaceFileMap,
+        { 'apps/demo/b.ts': 'new-hash', 'apps/demo/c.ts': 'changed-hash' },
+        ['apps/demo/a.ts', 'tools/myfile.txt']
+      );
+
+      expect(result.fileMap).toBe(workspaceFileMap.fileMap);
+      expect(result.allWorkspaceFiles).toBe(
+        workspaceFileMap.allWorkspaceFiles
+      );
+      expect(projectFileMap.ui).toBe(uiFiles);
+      expect(projectFileMap.demo).toEqual([
+        file('apps/demo/b.ts', 'new-hash'),
+        file('apps/demo/c.ts', 'changed-hash'),
+      ]);
+      expect(result.fileMap.nonProjectFiles).toEqual([]);
+      expect(result.allWorkspaceFiles).toEqual([
+        file('apps/demo/b.ts', 'new-hash'),
+        file('apps/demo/c.ts', 'changed-hash'),
+        file('libs/ui/index.ts'),
+      ]);
+    });
+
+    it('should only copy the files for the task hasher from the same update', () => {
+      (updateProjectFiles as jest.Mock).mockReturnValue({
+        generation: 2,
+        affectedProjects: [],
+        projectFiles: {},
+        nonProjectFiles: { added: [], changed: [], removed: [] },
+      });
+      const result = updateFileMap(
+        {
+          fileMap: { projectFileMap: {}, nonProjectFiles: [] },
+          allWorkspaceFiles: [],
+        },
+        {},
+        []
+      );
+      expect(getProjectFileMapReferences).not.toHaveBeenCalled();
+
+      (getProjectFileMapReferences as jest.Mock).mockReturnValue(null);
+      expect(() => result.rustReferences.allWorkspaceFiles).toThrow();
+      expect(getProjectFileMapReferences).toHaveBeenCalledWith(2);
+    });
+  });
 });
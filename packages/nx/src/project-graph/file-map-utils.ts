@@ -4,19 +4,16 @@ import {
   ProjectFileMap,
   ProjectGraph,
 } from '../config/project-graph';
-import {
-  ProjectConfiguration,
-  ProjectsConfigurations,
-} from '../config/workspace-json-project-json';
+import { ProjectsConfigurations } from '../config/workspace-json-project-json';
 import { daemonClient } from '../daemon/client/client';
-import { NxWorkspaceFilesExternals } from '../native';
+import type { FilesDelta, NxWorkspaceFilesExternals } from '../native';
 import {
   getAllFileDataInContext,
+  getProjectFileMapReferences,
   updateProjectFiles,
 } from '../utils/workspace-context';
 import { workspaceRoot } from '../utils/workspace-root';
 import { readProjectsConfigurationFromProjectGraph } from './project-graph';
-import { buildAllWorkspaceFiles } from './utils/build-all-workspace-files';
 import {
   createProjectRootMappingsFromProjectConfigurations,
   findProjectForPath,
@@ -78,26 +75,122 @@ export function createFileMap(
   };
 }
 
+/**
+ * Applies the files updated and deleted since the file map was created to it,
+ * and to all workspace files, in place.
+ * Returns `undefined` when the workspace context has no project file map to update,
+ * in which case the file map has to be recreated.
+ */
 export function updateFileMap(
-  projectsConfigurations: Record<string, ProjectConfiguration>,
-  rustReferences: NxWorkspaceFilesExternals,
+  { fileMap, allWorkspaceFiles }: WorkspaceFileMap,
   updatedFiles: Record<string, string>,
   deletedFiles: string[]
 ) {
-  const updates = updateProjectFiles(
-    Object.fromEntries(
-      createProjectRootMappingsFromProjectConfigurations(projectsConfigurations)
-    ),
-    rustReferences,
-    updatedFiles,
-    deletedFiles
+  const update = updateProjectFiles(updatedFiles, deletedFiles);
+  if (!update) {
+    return undefined;
+  }
+  const deltas = [update.nonProjectFiles];
+  for (const project of update.affectedProjects) {
+    const delta = update.projectFiles[project];
+    fileMap.projectFileMap[project] ??= [];
+    applyFilesDelta(fileMap.projectFileMap[project], delta, compareFiles);
+    deltas.push(delta);
+  }
+  applyFilesDelta(
+    fileMap.nonProjectFiles,
+    update.nonProjectFiles,
+    compareFiles
   );
+  // sorted like `buildAllWorkspaceFiles` sorts them
+  for (const delta of deltas) {
+    applyFilesDelta(allWorkspaceFiles, delta, (a, b) => a.localeCompare(b));
+  }
   return {
-    fileMap: updates.fileMap,
-    allWorkspaceFiles: buildAllWorkspaceFiles(
-      updates.fileMap.projectFileMap,
-      updates.fileMap.nonProjectFiles
-    ),
-    rustReferences: updates.externalReferences,
+    fileMap,
+    allWorkspaceFiles,
+    rustReferences: lazyProjectFileMapReferences(update.generation),
   };
 }
+
+/**
+ * Copying the project file map for the task hasher copies every file in the workspace,
+ * so it is only done once a hasher needs it rather than on every update.
+ * The copy has to describe the same files as the file map of the update,
+ * so it fails once the workspace context was updated again.
+ */
+function lazyProjectFileMapReferences(
+  generation: number
+): NxWorkspaceFilesExternals {
+  let references: NxWorkspaceFilesExternals | undefined;
+  const get = () => {
+    references ??= getProjectFileMapReferences(generation);
+    if (!references) {
+      throw new Error(
+        'The project file map changed before its files were copied for the task hasher'
+      );
+    }
+    return references;
+  };
+  return {
+    get projectFiles() {
+      return get().projectFiles;
+    },
+    get globalFiles() {
+      return get().globalFiles;
+    },
+    get allWorkspaceFiles() {
+      return get().allWorkspaceFiles;
+    },
+  };
+}
+
+function compareFiles(a: string, b: string) {
+  return a < b ? -1 : a > b ? 1 : 0;
+}
+
+/**
+ * The index of the file in the sorted files, or of where it would be inserted
+ */
+function findFileIndex(
+  files: FileData[],
+  file: string,
+  compare: (a: string, b: string) => number
+) {
+  let low = 0;
+  let high = files.length;
+  while (low < high) {
+    const middle = (low + high) >>> 1;
+    if (compare(files[middle].file, file) < 0) {
+      low = middle + 1;
+    } else {
+      high = middle;
+    }
+  }
+  return low;
+}
+
+/**
+ * Applies the delta to the files sorted by `compare` in place, without visiting the other files.
+ * Changed files are replaced rather than mutated, since the same objects are in all workspace files.
+ */
+function applyFilesDelta(
+  files: FileData[],
+  delta: FilesDelta,
+  compare: (a: string, b: string) => number
+) {
+  for (const file of delta.removed) {
+    const index = findFileIndex(files, file, compare);
+    if (files[index]?.file === file) {
+      files.splice(index, 1);
+    }
+  }
+  for (const file of [...delta.changed, ...delta.added]) {
+    const index = findFileIndex(files, file.file, compare);
+    if (files[index]?.file === file.file) {
+      files[index] = { ...file };
+    } else {
+      files.splice(index, 0, { ...file });
+    }
+  }
+}
@@ -131,7 +131,7 @@ export async function buildProjectGraphAndSourceMapsWithoutDaemon() {
 
   performance.mark('retrieve-workspace-files:start');
   const { allWorkspaceFiles, fileMap, rustReferences } =
-    await retrieveWorkspaceFiles(workspaceRoot, projectRootMap);
+    await retrieveWorkspaceFiles(workspaceRoot, projectRootMap, true);
   performance.mark('retrieve-workspace-files:end');
 
   const cacheEnabled = process.env.NX_CACHE_PROJECT_GRAPH !== 'false';
@@ -23,11 +23,13 @@ import { getOnlyDefaultPlugins, getPlugins } from '../plugins/get-plugins';
  * Walks the workspace directory to create the `projectFileMap`, `ProjectConfigurations` and `allWorkspaceFiles`
  * @throws
  * @param workspaceRoot
- * @param nxJson
+ * @param projectRootMap
+ * @param trackProjectFiles keeps the project file map in the workspace context so that it can be updated incrementally
  */
 export async function retrieveWorkspaceFiles(
   workspaceRoot: string,
-  projectRootMap: Record<string, string>
+  projectRootMap: Record<string, string>,
+  trackProjectFiles = false
 ) {
   performance.mark('native-file-deps:start');
   performance.mark('native-file-deps:end');
@@ -40,7 +42,11 @@ export async function retrieveWorkspaceFiles(
   performance.mark('get-workspace-files:start');
 
   const { projectFileMap, globalFiles, externalReferences } =
-    await getNxWorkspaceFilesFromContext(workspaceRoot, projectRootMap);
+    await getNxWorkspaceFilesFromContext(
+      workspaceRoot,
+      projectRootMap,
+      trackProjectFiles
+    );
   performance.mark('get-workspace-files:end');
   performance.measure(
     'get-workspace-files',
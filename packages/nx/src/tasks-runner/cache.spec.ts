@@ -1,4 +1,8 @@
-import { formatCacheSize, parseMaxCacheSize } from './cache';
+import {
+  formatCacheSize,
+  getCacheRestoreMode,
+  parseMaxCacheSize,
+} from './cache';
 
 describe('cache', () => {
   describe('parseMaxCacheSize', () => {
@@ -46,6 +50,30 @@ describe('cache', () => {
     });
   });
 
+  describe('getCacheRestoreMode', () => {
+    afterEach(() => {
+      delete process.env.NX_CACHE_RESTORE_MODE;
+    });
+
+    it('should default to copying', () => {
+      expect(getCacheRestoreMode({})).toEqual('copy');
+    });
+
+    it('should read the mode from nx.json', () => {
+      expect(getCacheRestoreMode({ cacheRestoreMode: 'link' })).toEqual('link');
+    });
+
+    it('should prefer the environment variable', () => {
+      process.env.NX_CACHE_RESTORE_MODE = 'copy';
+      expect(getCacheRestoreMode({ cacheRestoreMode: 'link' })).toEqual('copy');
+    });
+
+    it('should error if invalid mode', () => {
+      process.env.NX_CACHE_RESTORE_MODE = 'symlink';
+      expect(() => getCacheRestoreMode({})).toThrow();
+    });
+  });
+
   describe('formatCacheSize', () => {
     it('should format bytes', () => {
       expect(formatCacheSize(1)).toEqual('1.00 B');
@@ -15,9 +15,11 @@ import { machineId } from 'node-machine-id';
 import {
   NxCache,
   CachedResult as NativeCacheResult,
+  CacheRestoreMode,
   IS_WASM,
   getDefaultMaxCacheSize,
   HttpRemoteCache,
+  FsRemoteCache,
 } from '../native';
 import { getDbConnection } from '../utils/db-connection';
 import { isNxCloudUsed } from '../utils/nx-cloud-utils';
@@ -78,13 +80,17 @@ export class DbCache {
     undefined,
     this.nxJson.maxCacheSize !== undefined
       ? parseMaxCacheSize(this.nxJson.maxCacheSize)
-      : getDefaultMaxCacheSize(cacheDir)
+      : getDefaultMaxCacheSize(cacheDir),
+    getCacheRestoreMode(this.nxJson)
   );
 
   private remoteCache: RemoteCacheV2 | null;
   private remoteCachePromise: Promise<RemoteCacheV2>;
 
   private isVerbose = process.env.NX_VERBOSE_LOGGING === 'true';
+  private remoteCacheDegraded = false;
+  // Entries downloaded by prefetch are read locally but were remote cache hits
+  private prefetchedHashes = new Set<string>();
 
   constructor(
     private readonly options: {
@@ -105,19 +111,20 @@ export class DbCache {
     const res = this.cache.get(task.hash);
 
     if (res) {
+      const remote = this.prefetchedHashes.has(task.hash);
+      this.prefetchedHashes.delete(task.hash);
       return {
         ...res,
         terminalOutput: res.terminalOutput ?? '',
-        remote: false,
+        remote,
       };
     }
     if (this.remoteCache) {
       // didn't find it locally but we have a remote cache
       // attempt remote cache
-      const res = await this.remoteCache.retrieve(
-        task.hash,
-        this.cache.cacheDirectory
-      );
+      const res = await this.remoteCache
+        .retrieve(task.hash, this.cache.cacheDirectory)
+        .catch((e) => this.handleRemoteCacheError(e, null));
 
       if (res) {
         this.applyRemoteCacheResults(task.hash, res, task.outputs);
@@ -135,6 +142,27 @@ export class DbCache {
     }
   }
 
+  /**
+   * Downloads the remote cache entries of tasks which are not cached locally,
+   * so that they are restored from the local cache once the tasks run.
+   */
+  async prefetch(tasks: Task[], concurrency?: number) {
+    if (!(this.remoteCache instanceof HttpRemoteCache)) {
+      return;
+    }
+    const hashes = this.cache.missingHashes(
+      tasks.map((task) => task.hash).filter(Boolean)
+    );
+    const results = await this.remoteCache
+      .prefetch(hashes, this.cache.cacheDirectory, concurrency)
+      .catch((e) => this.handleRemoteCacheError(e, []));
+    // The outputs are restored from the local cache once the task runs
+    for (const { hash, result } of results) {
+      this.applyRemoteCacheResults(hash, result, []);
+      this.prefetchedHashes.add(hash);
+    }
+  }
+
   getUsedCacheSpace() {
     return this.cache.getCacheSize();
   }
@@ -157,16 +185,37 @@ export class DbCache {
       this.cache.put(task.hash, terminalOutput, outputs, code);
 
       if (this.remoteCache) {
-        await this.remoteCache.store(
-          task.hash,
-          this.cache.cacheDirectory,
-          terminalOutput,
-          code
-        );
+        await this.remoteCache
+          .store(task.hash, this.cache.cacheDirectory, terminalOutput, code)
+          .catch((e) => this.handleRemoteCacheError(e, false));
       }
     });
   }
 
+  /**
+   * The self-hosted remote caches stop sending requests once too many have failed,
+   * report that once instead of failing the task.
+   */
+  private handleRemoteCacheError<T>(e: Error, fallback: T): T {
+    if (
+      !(this.remoteCache instanceof HttpRemoteCache) ||
+      !this.remoteCache.isDegraded()
+    ) {
+      throw e;
+    }
+    if (!this.remoteCacheDegraded) {
+      this.remoteCacheDegraded = true;
+      output.warn({
+        title: 'Remote Cache Disabled',
+        bodyLines: [
+          e.message,
+          'Nx will continue running without reading from or writing to the remote cache.',
+        ],
+      });
+    }
+    return fallback;
+  }
+
   copyFilesFromCache(_: string, cachedResult: CachedResult, outputs: string[]) {
     return tryAndRetry(async () =>
       this.cache.copyFilesFromCache(cachedResult, outputs)
@@ -177,6 +226,16 @@ export class DbCache {
     return this.cache.removeOldCacheRecords();
   }
 
+  /**
+   * Makes sure a task which is about to run cannot write through its outputs
+   * into the cache, when outputs are restored as links
+   */
+  breakOutputLinks(task: Task) {
+    if (task.outputs.length) {
+      this.cache.breakOutputLinks(task.outputs);
+    }
+  }
+
   temporaryOutputPath(task: Task) {
     return this.cache.getTaskOutputsPath(task.hash);
   }
@@ -218,6 +277,7 @@ export class DbCache {
         (await this.getGcsCache()) ??
         (await this.getAzureCache()) ??
         this.getHttpCache() ??
+        this.getFsCache() ??
         null
       );
     }
@@ -260,6 +320,19 @@ export class DbCache {
     return null;
   }
 
+  private getFsCache(): RemoteCacheV2 | null {
+    if (process.env.NX_SELF_HOSTED_REMOTE_CACHE_DIRECTORY) {
+      if (IS_WASM) {
+        logger.warn(
+          'The filesystem remote cache is not yet supported in the wasm build of Nx.'
+        );
+        return null;
+      }
+      return new FsRemoteCache();
+    }
+    return null;
+  }
+
   private async resolveRemoteCache(pkg: string): Promise<RemoteCacheV2 | null> {
     let getRemoteCache = null;
     try {
@@ -647,6 +720,23 @@ export function parseMaxCacheSize(
   }
 }
 
+/**
+ * The mode outputs are restored from the local cache with.
+ * `NX_CACHE_RESTORE_MODE` overrides `cacheRestoreMode` in nx.json.
+ */
+export function getCacheRestoreMode(
+  nxJson: NxJsonConfiguration
+): CacheRestoreMode {
+  const mode =
+    process.env.NX_CACHE_RESTORE_MODE || nxJson.cacheRestoreMode || 'copy';
+  if (mode !== 'copy' && mode !== 'link') {
+    throw new Error(
+      `Invalid cache restore mode: ${mode}. Must be either "copy" or "link"`
+    );
+  }
+  return mode as CacheRestoreMode;
+}
+
 export function formatCacheSize(maxCacheSize: number, decimals = 2): string {
   const exponents = ['B', 'KB', 'MB', 'GB'];
   let exponent = 0;
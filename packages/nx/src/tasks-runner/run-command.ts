@@ -1229,6 +1229,10 @@ export function getRunnerOptions(
     result.cacheDirectory ??= nxJson.cacheDirectory;
   }
 
+  if (nxJson.detailedFileSets) {
+    result.detailedFileSets ??= nxJson.detailedFileSets;
+  }
+
   if (defaultCacheableOperations.length) {
     result.cacheableOperations ??= [];
     result.cacheableOperations = result.cacheableOperations.concat(
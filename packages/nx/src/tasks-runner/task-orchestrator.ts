@@ -118,6 +118,13 @@ export class TaskOrchestrator {
 
   async run() {
     await this.init();
+    // Tasks which run before their entry is prefetched retrieve it themselves
+    this.prefetchRemoteCacheResults().catch((e) => {
+      output.warn({
+        title: 'Failed to prefetch the remote cache',
+        bodyLines: [e.message ?? String(e)],
+      });
+    });
 
     performance.mark('task-execution:start');
 
@@ -394,6 +401,7 @@ export class TaskOrchestrator {
   ): Promise<TaskResult[]> {
     const runBatchStart = performance.mark('TaskOrchestrator-run-batch:start');
     try {
+      this.breakOutputLinks(Object.values(batch.taskGraph.tasks));
       const batchProcess =
         await this.forkedProcessTaskRunner.forkProcessForBatch(
           batch,
@@ -510,6 +518,7 @@ export class TaskOrchestrator {
     temporaryOutputPath: string,
     pipeOutput: boolean
   ): Promise<RunningTask> {
+    this.breakOutputLinks([task]);
     const shouldPrefix =
       streamOutput && process.env.NX_PREFIX_OUTPUT === 'true';
     const targetConfiguration = getTargetConfigurationForTask(
@@ -993,6 +1002,25 @@ export class TaskOrchestrator {
     this.groups[id] = false;
   }
 
+  /**
+   * Downloads the remote cache entries of the tasks which are already hashed
+   * in the background, instead of retrieving them one at a time as the tasks run
+   */
+  private async prefetchRemoteCacheResults() {
+    const doNotSkipCache =
+      this.options.skipNxCache === false ||
+      this.options.skipNxCache === undefined;
+    if (!doNotSkipCache || !('prefetch' in this.cache)) {
+      return;
+    }
+    // Other tasks are hashed once the tasks they depend on have run
+    const hashedTasks = Object.values(this.taskGraph.tasks).filter(
+      (task) =>
+        task.hash && !task.continuous && isCacheableTask(task, this.options)
+    );
+    await this.cache.prefetch(hashedTasks);
+  }
+
   private async shouldCopyOutputsFromCache(outputs: string[], hash: string) {
     if (this.daemon?.enabled()) {
       return !(await this.daemon.outputsHashesMatch(outputs, hash));
@@ -1001,6 +1029,14 @@ export class TaskOrchestrator {
     }
   }
 
+  private breakOutputLinks(tasks: Task[]) {
+    if ('breakOutputLinks' in this.cache) {
+      for (const task of tasks) {
+        this.cache.breakOutputLinks(task);
+      }
+    }
+  }
+
   private async recordOutputsHash(task: Task) {
     if (this.daemon?.enabled()) {
       return this.daemon.recordOutputsHash(task.outputs, task.hash);
@@ -1,8 +1,9 @@
-import type { NxWorkspaceFilesExternals, WorkspaceContext } from '../native';
+import type { FilesQuery, WorkspaceContext } from '../native';
 import { performance } from 'perf_hooks';
 import { workspaceDataDirectoryForWorkspace } from './cache-directory';
 import { isOnDaemon } from '../daemon/is-on-daemon';
 import { daemonClient } from '../daemon/client/client';
+import { logger } from './logger';
 
 let workspaceContext: WorkspaceContext | undefined;
 
@@ -12,7 +13,8 @@ export function setupWorkspaceContext(workspaceRoot: string) {
   performance.mark('workspace-context');
   workspaceContext = new WorkspaceContext(
     workspaceRoot,
-    workspaceDataDirectoryForWorkspace(workspaceRoot)
+    workspaceDataDirectoryForWorkspace(workspaceRoot),
+    { seedFromGitIndex: process.env.NX_SEED_FILES_FROM_GIT === 'true' }
   );
   performance.mark('workspace-context:end');
   performance.measure(
@@ -22,17 +24,38 @@ export function setupWorkspaceContext(workspaceRoot: string) {
   );
 }
 
+/**
+ * @param trackProjectFiles keeps the project file map in the context so that `updateProjectFiles`
+ * can update it, only the project graph computation which owns the context should set this
+ */
 export async function getNxWorkspaceFilesFromContext(
   workspaceRoot: string,
-  projectRootMap: Record<string, string>
+  projectRootMap: Record<string, string>,
+  trackProjectFiles = false
 ) {
   if (isOnDaemon() || !daemonClient.enabled()) {
     ensureContextAvailable(workspaceRoot);
-    return workspaceContext.getWorkspaceFiles(projectRootMap);
+    const files = trackProjectFiles
+      ? workspaceContext.trackWorkspaceFiles(projectRootMap)
+      : workspaceContext.getWorkspaceFiles(projectRootMap);
+    reportFilesArchiveMismatch();
+    return files;
   }
   return daemonClient.getWorkspaceFiles(projectRootMap);
 }
 
+/**
+ * The native side falls back to hashing every file when the archived file hashes
+ * could not be reused, e.g. when they were written by another version of Nx.
+ */
+function reportFilesArchiveMismatch() {
+  try {
+    workspaceContext.checkFilesArchive();
+  } catch (e) {
+    logger.warn(e.message);
+  }
+}
+
 /**
  * Sync method to get files matching globs from workspace context.
  * NOTE: This method will create the workspace context if it doesn't exist.
@@ -97,6 +120,32 @@ export async function hashMultiGlobWithWorkspaceContext(
   return daemonClient.hashMultiGlob(globGroups);
 }
 
+/**
+ * Finds the files matching a query without transferring every file of the workspace.
+ * Pass `limit: 0` to only count the matching files.
+ */
+export async function queryFilesWithWorkspaceContext(
+  workspaceRoot: string,
+  query: FilesQuery
+) {
+  if (isOnDaemon() || !daemonClient.enabled()) {
+    ensureContextAvailable(workspaceRoot);
+    return workspaceContext.queryFiles(query);
+  }
+  return daemonClient.queryFiles(query);
+}
+
+export async function findDuplicateFilesWithWorkspaceContext(
+  workspaceRoot: string,
+  query?: FilesQuery
+) {
+  if (isOnDaemon() || !daemonClient.enabled()) {
+    ensureContextAvailable(workspaceRoot);
+    return workspaceContext.findDuplicateFiles(query);
+  }
+  return daemonClient.findDuplicateFiles(query);
+}
+
 export async function updateContextWithChangedFiles(
   workspaceRoot: string,
   createdFiles: string[],
@@ -155,18 +204,14 @@ export async function getFilesInDirectoryUsingContext(
 }
 
 export function updateProjectFiles(
-  projectRootMappings: Record<string, string>,
-  rustReferences: NxWorkspaceFilesExternals,
   updatedFiles: Record<string, string>,
   deletedFiles: string[]
 ) {
-  return workspaceContext?.updateProjectFiles(
-    projectRootMappings,
-    rustReferences.projectFiles,
-    rustReferences.globalFiles,
-    updatedFiles,
-    deletedFiles
-  );
+  return workspaceContext?.updateProjectFiles(updatedFiles, deletedFiles);
+}
+
+export function getProjectFileMapReferences(generation: number) {
+  return workspaceContext?.getProjectFileMapReferences(generation);
 }
 
 function ensureContextAvailable(workspaceRoot: string) {